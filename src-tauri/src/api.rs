@@ -1,3 +1,4 @@
 pub mod gdrive;
 pub mod local;
 pub mod rclone;
+pub mod system;
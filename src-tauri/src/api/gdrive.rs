@@ -1,21 +1,94 @@
+mod backups;
+mod bandwidth;
+mod compare;
 mod download;
+mod history;
+mod jobs;
 mod list;
+mod listing_cache;
+mod options;
+mod plan;
+mod profiles;
 mod remotes;
+mod report;
+mod schedule;
 mod types;
 mod utils;
+mod verify;
 
 // Types
+pub use backups::{BackupInfo, RetentionPolicy};
+pub use compare::{ComparedFile, FileComparison, LocalRemoteComparison};
+pub use download::{CancelledDownload, DownloadConfig, SyncJobError, SyncJobResult};
+pub use history::{FileChangeKind, FileHistoryEvent, HistoryEntry, HistorySummary, RunKind};
+pub use jobs::{DownloadJob, JobManager, JobStatus};
+pub use listing_cache::{CachedListing, ListingUpdate};
+pub use options::{CompareMode, TransferOptions};
+pub use plan::{PlannedFile, PlannedRename, SyncPlan};
+pub use profiles::{Profile, ProfileManager, ProfileValidation};
 pub use remotes::GdriveAuthState;
-pub use types::GdriveFile;
+pub use report::DownloadReport;
+pub use schedule::{
+    Schedule, ScheduleDay, ScheduleManager, ScheduleTrigger, ScheduledRunResult, start_scheduler,
+};
+pub use types::{GdriveDirEntry, GdriveFile};
+pub use verify::{VerifyProgress, VerifyReport};
 
 // Command functions
-pub use download::{__cmd__check_dry_run, __cmd__download_gdrive};
-pub use list::__cmd__list_gdrive_files;
+pub use backups::{
+    __cmd__delete_backup, __cmd__list_backups, __cmd__prune_backups, __cmd__restore_backup,
+};
+pub use compare::__cmd__compare_local_remote;
+pub use download::{__cmd__cancel_download, __cmd__check_dry_run, __cmd__download_gdrive};
+pub use history::{
+    __cmd__find_file_history, __cmd__get_download_history, __cmd__get_history_entry,
+};
+pub use jobs::{
+    __cmd__cancel_job, __cmd__get_global_bwlimit, __cmd__get_job_concurrency,
+    __cmd__get_resume_after_crash, __cmd__list_jobs, __cmd__pause_job, __cmd__queue_download,
+    __cmd__reorder_job, __cmd__resume_job, __cmd__set_global_bwlimit, __cmd__set_job_bwlimit,
+    __cmd__set_job_concurrency, __cmd__set_resume_after_crash, __cmd__wait_for_job,
+};
+pub use list::{__cmd__list_gdrive_dir, __cmd__list_gdrive_files};
+pub use profiles::{
+    __cmd__create_profile, __cmd__delete_profile, __cmd__export_profiles, __cmd__get_profile,
+    __cmd__import_profiles, __cmd__list_profiles, __cmd__update_profile, __cmd__validate_profile,
+};
 pub use remotes::{
     __cmd__cancel_gdrive_auth, __cmd__create_gdrive_remote, __cmd__get_gdrive_remotes,
 };
+pub use schedule::{
+    __cmd__create_schedule, __cmd__delete_schedule, __cmd__list_schedules, __cmd__preview_schedule,
+    __cmd__run_schedule_now, __cmd__update_schedule,
+};
+pub use verify::__cmd__verify_archive;
 
 // Functions
-pub use download::{DryRunResult, check_dry_run, download_gdrive};
-pub use list::list_gdrive_files;
-pub use remotes::{cancel_gdrive_auth, create_gdrive_remote, get_gdrive_remotes};
+pub use backups::{delete_backup, list_backups, prune_backups, restore_backup};
+pub use bandwidth::{current_bwlimit_rate, start_bwlimit_schedule, validate_bwlimit};
+pub use compare::{compare_files, compare_local_remote};
+pub use download::{
+    DryRunResult, cancel_download, check_dry_run, download_gdrive, dry_run, forget_stopped_jobs,
+    get_job_stats, poll_job_completion, poll_job_output, start_sync_job, submit_sync_job,
+};
+pub use history::{find_file_history, get_download_history, get_history_entry};
+pub use jobs::{
+    cancel_job, cancel_queued_job, dispatch_jobs, get_global_bwlimit, get_job_concurrency,
+    get_resume_after_crash, list_jobs, pause_job, queue_download, reorder_job, resume_job,
+    run_download, set_global_bwlimit, set_job_bwlimit, set_job_concurrency, set_resume_after_crash,
+    wait_for_job,
+};
+pub use list::{dir_index, list_dir, list_files, list_gdrive_dir, list_gdrive_files};
+pub use listing_cache::{read_cached_listing, refresh_listing};
+pub use profiles::{
+    check_profile, create_profile, delete_profile, export_profiles, get_profile, import_profiles,
+    list_profiles, update_profile, validate_profile,
+};
+pub use remotes::{
+    cancel_gdrive_auth, create_gdrive_remote, get_gdrive_remotes, list_drive_remotes,
+};
+pub use schedule::{
+    create_schedule, delete_schedule, list_schedules, preview_schedule, run_schedule_now,
+    update_schedule,
+};
+pub use verify::{redownload_config, verify, verify_archive};
@@ -0,0 +1,237 @@
+use super::download::{BACKUP_PREFIX, BACKUP_TIMESTAMP_FORMAT, build_destination_path};
+use crate::utils::walk_files;
+use serde::{Deserialize, Serialize};
+use std::path::{Component, Path, PathBuf};
+
+/// A backup directory created by a sync
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BackupInfo {
+    pub name: String,
+    pub path: String,
+    pub created_at: Option<String>,
+    pub size: u64,
+    pub file_count: u64,
+}
+
+/// Limits for which backups to keep, a backup is pruned if it falls outside any of them
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionPolicy {
+    /// Keep only the newest N backups
+    pub keep_last: Option<usize>,
+    /// Remove backups older than this many days
+    pub max_age_days: Option<u32>,
+    /// Remove the oldest backups once the total size exceeds this many bytes
+    pub max_total_size: Option<u64>,
+}
+
+/// Get the directory the backups of a destination are created in
+fn backups_dir(destination: &str, create_subfolder: bool) -> Result<PathBuf, String> {
+    build_destination_path(destination, create_subfolder)
+        .parent()
+        .map(Path::to_path_buf)
+        .ok_or("Cannot get parent directory of destination".to_string())
+}
+
+/// Total size and number of files under a directory
+fn dir_stats(root: &Path) -> (u64, u64) {
+    let files = walk_files(root).files;
+    (files.iter().map(|file| file.size).sum(), files.len() as u64)
+}
+
+/// Find all backups of a destination, newest first
+fn find_backups(destination: &str, create_subfolder: bool) -> Result<Vec<BackupInfo>, String> {
+    let dir = backups_dir(destination, create_subfolder)?;
+    if !dir.is_dir() {
+        return Ok(vec![]);
+    }
+
+    let mut backups = Vec::new();
+    let entries = std::fs::read_dir(&dir).map_err(|e| e.to_string())?;
+    for entry in entries {
+        let entry = entry.map_err(|e| e.to_string())?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        let Some(timestamp) = name.strip_prefix(BACKUP_PREFIX) else {
+            continue;
+        };
+        if !entry.path().is_dir() {
+            continue;
+        }
+
+        let created_at = chrono::NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT)
+            .ok()
+            .and_then(|t| t.and_local_timezone(chrono::Local).single())
+            .map(|t| t.to_rfc3339());
+        let (size, file_count) = dir_stats(&entry.path());
+
+        backups.push(BackupInfo {
+            name,
+            path: entry.path().to_string_lossy().to_string(),
+            created_at,
+            size,
+            file_count,
+        });
+    }
+
+    // The timestamp format sorts chronologically
+    backups.sort_by(|a, b| b.name.cmp(&a.name));
+    Ok(backups)
+}
+
+/// Resolve a backup by name, rejecting anything that isn't a backup directory
+fn backup_path(destination: &str, create_subfolder: bool, name: &str) -> Result<PathBuf, String> {
+    if !name.starts_with(BACKUP_PREFIX) || name.contains(['/', '\\']) {
+        return Err(format!("{} is not a backup", name));
+    }
+
+    let path = backups_dir(destination, create_subfolder)?.join(name);
+    if !path.is_dir() {
+        return Err(format!("Backup {} not found", name));
+    }
+    Ok(path)
+}
+
+/// Join a relative path from the frontend onto `root`, refusing to escape it
+fn join_relative(root: &Path, relative: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(relative.trim_start_matches(['/', '\\']));
+    if relative
+        .components()
+        .any(|c| !matches!(c, Component::Normal(_)))
+    {
+        return Err(format!("Invalid path: {}", relative.display()));
+    }
+    Ok(root.join(relative))
+}
+
+/// Copy a file or directory tree, overwriting existing files, returning the number of files copied.
+/// Symlinks and folders that can't be read are left out.
+fn copy_recursive(src: &Path, dst: &Path) -> Result<u64, String> {
+    let is_file = std::fs::symlink_metadata(src).is_ok_and(|metadata| metadata.is_file());
+    let files = if is_file {
+        vec![(src.to_path_buf(), dst.to_path_buf())]
+    } else {
+        walk_files(src)
+            .files
+            .into_iter()
+            .map(|file| (file.path, dst.join(file.relative)))
+            .collect()
+    };
+
+    for (src, dst) in &files {
+        if let Some(parent) = dst.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        std::fs::copy(src, dst)
+            .map_err(|e| format!("Failed to restore {}: {}", dst.display(), e))?;
+    }
+    Ok(files.len() as u64)
+}
+
+/// Select the backups a retention policy would remove
+fn backups_to_prune(backups: &[BackupInfo], policy: &RetentionPolicy) -> Vec<BackupInfo> {
+    let now = chrono::Local::now();
+    let mut total_size = 0;
+
+    backups
+        .iter()
+        .enumerate()
+        .filter(|(index, backup)| {
+            total_size += backup.size;
+
+            let over_count = policy.keep_last.is_some_and(|keep| *index >= keep);
+            let over_size = policy.max_total_size.is_some_and(|max| total_size > max);
+            let over_age = policy.max_age_days.is_some_and(|days| {
+                backup
+                    .created_at
+                    .as_deref()
+                    .and_then(|t| chrono::DateTime::parse_from_rfc3339(t).ok())
+                    .is_some_and(|created| {
+                        (now - created.with_timezone(&chrono::Local)).num_days() > days as i64
+                    })
+            });
+
+            over_count || over_size || over_age
+        })
+        .map(|(_, backup)| backup.clone())
+        .collect()
+}
+
+/// List the backups of a destination with their size and file count, newest first
+#[tauri::command]
+pub async fn list_backups(
+    destination: String,
+    create_subfolder: bool,
+) -> Result<Vec<BackupInfo>, String> {
+    tauri::async_runtime::spawn_blocking(move || find_backups(&destination, create_subfolder))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Copy files from a backup back into the destination, or the whole backup if `files` is empty
+#[tauri::command]
+pub async fn restore_backup(
+    destination: String,
+    create_subfolder: bool,
+    name: String,
+    files: Option<Vec<String>>,
+) -> Result<u64, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let backup = backup_path(&destination, create_subfolder, &name)?;
+        let dst_path = build_destination_path(&destination, create_subfolder);
+
+        let files = files.unwrap_or_default();
+        if files.is_empty() {
+            return copy_recursive(&backup, &dst_path);
+        }
+
+        let mut restored = 0;
+        for file in &files {
+            let src = join_relative(&backup, file)?;
+            if !src.exists() {
+                return Err(format!("{} is not in backup {}", file, name));
+            }
+            restored += copy_recursive(&src, &join_relative(&dst_path, file)?)?;
+        }
+        Ok(restored)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Delete a single backup
+#[tauri::command]
+pub async fn delete_backup(
+    destination: String,
+    create_subfolder: bool,
+    name: String,
+) -> Result<(), String> {
+    let path = backup_path(&destination, create_subfolder, &name)?;
+    tokio::fs::remove_dir_all(&path)
+        .await
+        .map_err(|e| format!("Failed to delete backup {}: {}", name, e))
+}
+
+/// Delete backups outside the retention policy, returning the ones removed
+#[tauri::command]
+pub async fn prune_backups(
+    destination: String,
+    create_subfolder: bool,
+    policy: RetentionPolicy,
+    dry_run: Option<bool>,
+) -> Result<Vec<BackupInfo>, String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let backups = find_backups(&destination, create_subfolder)?;
+        let pruned = backups_to_prune(&backups, &policy);
+
+        if !dry_run.unwrap_or(false) {
+            for backup in &pruned {
+                std::fs::remove_dir_all(&backup.path)
+                    .map_err(|e| format!("Failed to delete backup {}: {}", backup.name, e))?;
+            }
+        }
+        Ok(pruned)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
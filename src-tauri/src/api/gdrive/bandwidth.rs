@@ -0,0 +1,190 @@
+use crate::service::JobHost;
+use chrono::{DateTime, Datelike, TimeZone, Timelike};
+use rclone_sdk::ClientInfo;
+use std::time::Duration;
+
+/// How often a timetable limit is re-applied
+const SCHEDULE_INTERVAL: Duration = Duration::from_secs(60);
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+const WEEKDAYS: [&str; 7] = [
+    "monday",
+    "tuesday",
+    "wednesday",
+    "thursday",
+    "friday",
+    "saturday",
+    "sunday",
+];
+
+/// Check a limit in rclone's `--bwlimit` syntax: `off`, a rate like `512k`,
+/// an `upload:download` pair like `1M:256k`, or a timetable like `08:00,512k 23:00,off`
+pub fn validate_bwlimit(limit: &str) -> Result<(), String> {
+    let limit = limit.trim();
+    if limit.is_empty() {
+        return Err("Bandwidth limit is empty".to_string());
+    }
+
+    if !limit.contains(',') {
+        return validate_rate_pair(limit);
+    }
+
+    for (_, rate) in parse_timetable(limit)? {
+        validate_rate_pair(rate)?;
+    }
+    Ok(())
+}
+
+/// The rate in effect at `now`: the current slot of a timetable, other limits as they are.
+/// Slots without a day repeat every day, the last slot of the week carries over into the next.
+pub fn current_bwlimit_rate<Tz: TimeZone>(
+    limit: &str,
+    now: &DateTime<Tz>,
+) -> Result<String, String> {
+    let limit = limit.trim();
+    if !is_timetable(limit) {
+        return Ok(limit.to_string());
+    }
+
+    let now =
+        now.weekday().num_days_from_monday() * MINUTES_PER_DAY + now.hour() * 60 + now.minute();
+    let slots = parse_timetable(limit)?;
+    let started = slots.iter().filter(|(start, _)| *start <= now);
+    let current = started
+        .max_by_key(|(start, _)| *start)
+        .or_else(|| slots.iter().max_by_key(|(start, _)| *start))
+        .ok_or("Bandwidth timetable is empty")?;
+    Ok(current.1.to_string())
+}
+
+/// Split a timetable into its slots, as minutes since Monday 00:00 and the rate from then on
+fn parse_timetable(limit: &str) -> Result<Vec<(u32, &str)>, String> {
+    let mut slots = Vec::new();
+    for entry in limit.split_whitespace() {
+        let (time, rate) = entry.split_once(',').ok_or(format!(
+            "Invalid timetable entry '{}', expected TIME,RATE",
+            entry
+        ))?;
+        let (day, minutes) = parse_time(time)?;
+        match day {
+            Some(day) => slots.push((day * MINUTES_PER_DAY + minutes, rate)),
+            None => slots.extend((0..7).map(|day| (day * MINUTES_PER_DAY + minutes, rate))),
+        }
+    }
+    Ok(slots)
+}
+
+/// Whether a limit changes with the time of day
+fn is_timetable(limit: &str) -> bool {
+    limit.contains(',')
+}
+
+/// Validate `RATE` or `UPLOAD:DOWNLOAD`
+fn validate_rate_pair(pair: &str) -> Result<(), String> {
+    let mut rates = pair.split(':');
+    let valid = rates.by_ref().take(2).all(is_valid_rate) && rates.next().is_none();
+    if !valid {
+        return Err(format!(
+            "Invalid bandwidth '{}', expected e.g. 512k or 1M:256k",
+            pair
+        ));
+    }
+    Ok(())
+}
+
+fn is_valid_rate(rate: &str) -> bool {
+    if rate.eq_ignore_ascii_case("off") {
+        return true;
+    }
+
+    let split = rate
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(rate.len());
+    let (number, suffix) = rate.split_at(split);
+    number.parse::<f64>().is_ok_and(|n| n >= 0.0)
+        && matches!(
+            suffix.to_ascii_lowercase().as_str(),
+            "" | "b" | "k" | "m" | "g" | "t" | "p" | "ki" | "mi" | "gi" | "ti" | "pi"
+        )
+}
+
+/// Parse `HH:MM` or `DAY-HH:MM` into the day of the week, from 0 for Monday, and minutes since midnight
+fn parse_time(time: &str) -> Result<(Option<u32>, u32), String> {
+    let invalid = || format!("Invalid time '{}', expected e.g. 08:00 or Mon-08:00", time);
+
+    let (day, clock) = match time.split_once('-') {
+        Some((day, clock)) => {
+            let day = day.to_ascii_lowercase();
+            let index = WEEKDAYS
+                .iter()
+                .position(|weekday| *weekday == day || weekday[..3] == day)
+                .ok_or_else(invalid)?;
+            (Some(index as u32), clock)
+        }
+        None => (None, time),
+    };
+
+    let (hours, minutes) = clock.split_once(':').ok_or_else(invalid)?;
+    let hours: u32 = hours.parse().map_err(|_| invalid())?;
+    let minutes: u32 = minutes.parse().map_err(|_| invalid())?;
+    if hours > 23 || minutes > 59 {
+        return Err(invalid());
+    }
+    Ok((day, hours * 60 + minutes))
+}
+
+/// Set the rate in effect on rclone through `core/bwlimit`.
+/// rclone's limiter is shared by all transfers, so a job's own limit applies to everything while it runs.
+pub(super) async fn apply_bwlimit(
+    host: &impl JobHost,
+    client: &rclone_sdk::Client,
+) -> Result<(), String> {
+    let rate = match host.job_manager().bwlimit() {
+        Some(limit) => current_bwlimit_rate(&limit, &chrono::Local::now())?,
+        None => "off".to_string(),
+    };
+
+    let response = client
+        .client()
+        .post(format!("{}/core/bwlimit", client.baseurl()))
+        .json(&serde_json::json!({
+            "rate": rate
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to set bandwidth limit: {}", e))?;
+
+    if !response.status().is_success() {
+        let err_text = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to set bandwidth limit: {}", err_text));
+    }
+
+    Ok(())
+}
+
+/// Apply the limit in effect if rclone is running, without starting it
+pub(super) async fn refresh_bwlimit(host: &impl JobHost) {
+    let Some(client) = host.running_rc_client().await else {
+        return;
+    };
+    if let Err(e) = apply_bwlimit(host, &client).await {
+        log::warn!("{}", e);
+    }
+}
+
+/// `core/bwlimit` only takes a single rate, so keep applying the current slot of
+/// timetable limits for them to follow the clock
+pub fn start_bwlimit_schedule(host: &impl JobHost) {
+    let host = host.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(SCHEDULE_INTERVAL).await;
+
+            let limit = host.job_manager().bwlimit();
+            if limit.as_deref().is_some_and(is_timetable) {
+                refresh_bwlimit(&host).await;
+            }
+        }
+    });
+}
@@ -0,0 +1,252 @@
+use super::download::{SyncJobError, build_destination_path, poll_job_output};
+use super::list::list_files;
+use super::utils::{escape_glob, local_fs, parse_gdrive_id};
+use crate::api::rclone::LogSpan;
+use crate::service::Host;
+use crate::utils::walk_files;
+use rclone_sdk::ClientInfo;
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+/// How a file in the destination compares to the Drive
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum FileComparison {
+    MissingLocally,
+    ExtraLocally,
+    SizeMismatch,
+    HashMismatch,
+    Identical,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ComparedFile {
+    pub path: String,
+    pub status: FileComparison,
+    pub local_size: Option<u64>,
+    /// `None` for files without a size on the Drive, e.g. Google Docs
+    pub remote_size: Option<u64>,
+    pub local_md5: Option<String>,
+    pub remote_md5: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalRemoteComparison {
+    /// Every file on either side, sorted by path
+    pub files: Vec<ComparedFile>,
+    pub missing_locally: usize,
+    pub extra_locally: usize,
+    pub size_mismatch: usize,
+    pub hash_mismatch: usize,
+    pub identical: usize,
+    /// Whether MD5 hashes were fetched for files of the same size
+    pub hashes_checked: bool,
+}
+
+impl LocalRemoteComparison {
+    fn push(&mut self, file: ComparedFile) {
+        match file.status {
+            FileComparison::MissingLocally => self.missing_locally += 1,
+            FileComparison::ExtraLocally => self.extra_locally += 1,
+            FileComparison::SizeMismatch => self.size_mismatch += 1,
+            FileComparison::HashMismatch => self.hash_mismatch += 1,
+            FileComparison::Identical => self.identical += 1,
+        }
+        self.files.push(file);
+    }
+}
+
+/// Compare the files in the destination with the Drive folder without running a sync.
+/// Sizes come from the listing, MD5 hashes are only fetched with `check_hashes` and only
+/// for files of the same size, since rclone has to read every one of them locally.
+#[tauri::command]
+pub async fn compare_local_remote(
+    app: tauri::AppHandle,
+    source: String,
+    destination: String,
+    remote_config: String,
+    create_subfolder: bool,
+    check_hashes: Option<bool>,
+) -> Result<LocalRemoteComparison, String> {
+    let local_root = build_destination_path(&destination, create_subfolder);
+    compare_files(
+        &app,
+        &source,
+        &remote_config,
+        &local_root,
+        check_hashes.unwrap_or(false),
+    )
+    .await
+}
+
+/// Compare the files under `local_root` with those in a Drive folder
+pub async fn compare_files(
+    host: &impl Host,
+    source: &str,
+    remote_config: &str,
+    local_root: &Path,
+    check_hashes: bool,
+) -> Result<LocalRemoteComparison, String> {
+    let remote: BTreeMap<String, Option<u64>> = list_files(host, source, remote_config)
+        .await?
+        .into_iter()
+        .filter(|file| !file.is_dir)
+        .map(|file| (file.path, u64::try_from(file.size).ok()))
+        .collect();
+
+    let root = local_root.to_path_buf();
+    let local = if local_root.is_dir() {
+        tauri::async_runtime::spawn_blocking(move || local_sizes(&root))
+            .await
+            .map_err(|e| format!("Failed to scan {}: {}", local_root.display(), e))??
+    } else {
+        // Nothing downloaded yet
+        BTreeMap::new()
+    };
+
+    // Files of different sizes differ anyway
+    let same_size: Vec<&String> = local
+        .iter()
+        .filter(|(path, size)| remote.get(*path).copied().flatten() == Some(**size))
+        .map(|(path, _)| path)
+        .collect();
+    let hashes_checked = check_hashes && !same_size.is_empty();
+    let (local_md5, remote_md5) = if hashes_checked {
+        let remote_fs = format!(
+            "{},root_folder_id={}:",
+            remote_config,
+            parse_gdrive_id(source)
+        );
+        let local_fs = local_fs(&local_root.to_string_lossy());
+        let filter = hash_filter(&same_size);
+        // Files that can't be read are logged, which would end up in the reports of running downloads
+        let _log = LogSpan::open(host).await;
+        tokio::try_join!(
+            md5_sums(host, &local_fs, &filter),
+            md5_sums(host, &remote_fs, &filter)
+        )?
+    } else {
+        (HashMap::new(), HashMap::new())
+    };
+
+    let mut paths: Vec<&String> = remote.keys().chain(local.keys()).collect();
+    paths.sort();
+    paths.dedup();
+
+    let mut comparison = LocalRemoteComparison {
+        hashes_checked,
+        ..Default::default()
+    };
+    for path in paths {
+        let local_size = local.get(path).copied();
+        let remote_size = remote.get(path).copied().flatten();
+        let local_md5 = local_md5.get(path).cloned();
+        let remote_md5 = remote_md5.get(path).cloned();
+
+        let status = match (local_size, remote.contains_key(path)) {
+            (None, _) => FileComparison::MissingLocally,
+            (Some(_), false) => FileComparison::ExtraLocally,
+            (Some(local), true) if remote_size.is_some_and(|remote| remote != local) => {
+                FileComparison::SizeMismatch
+            }
+            _ => match (&local_md5, &remote_md5) {
+                (Some(local), Some(remote)) if !local.eq_ignore_ascii_case(remote) => {
+                    FileComparison::HashMismatch
+                }
+                _ => FileComparison::Identical,
+            },
+        };
+
+        comparison.push(ComparedFile {
+            path: path.clone(),
+            status,
+            local_size,
+            remote_size,
+            local_md5,
+            remote_md5,
+        });
+    }
+
+    Ok(comparison)
+}
+
+/// Sizes of the files under `root`, by path relative to it
+fn local_sizes(root: &Path) -> Result<BTreeMap<String, u64>, String> {
+    let walked = walk_files(root);
+    // Files in a folder that couldn't be read would show up as missing
+    if let Some(dir) = walked.failed_dirs.first() {
+        return Err(format!("Failed to read {}", root.join(dir).display()));
+    }
+
+    Ok(walked
+        .files
+        .into_iter()
+        .map(|file| (file.relative, file.size))
+        .collect())
+}
+
+/// Filter matching exactly the given paths
+fn hash_filter(paths: &[&String]) -> serde_json::Value {
+    let rules: Vec<String> = paths
+        .iter()
+        .map(|path| format!("/{}", escape_glob(path)))
+        .collect();
+
+    serde_json::json!({
+        "IncludeRule": rules
+    })
+}
+
+/// MD5 of the files in an rclone fs matching `filter`, by path. Files without one are left out.
+/// Runs as a job since hashing local files can take longer than a request is allowed to.
+async fn md5_sums(
+    host: &impl Host,
+    fs: &str,
+    filter: &serde_json::Value,
+) -> Result<HashMap<String, String>, String> {
+    let client = host.rc_client().await?;
+    let response = client
+        .client()
+        .post(format!("{}/operations/hashsum", client.baseurl()))
+        .json(&serde_json::json!({
+            "fs": fs,
+            "hashType": "md5",
+            "_filter": filter,
+            "_async": true
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to get hashes of {}: {}", fs, e))?;
+    if !response.status().is_success() {
+        let err_text = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to get hashes of {}: {}", fs, err_text));
+    }
+    let jobid = response
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("Failed to parse hashsum response: {}", e))?["jobid"]
+        .as_i64()
+        .ok_or("No jobid returned")?;
+
+    let output = match poll_job_output(&client, jobid).await {
+        Ok(output) => output.unwrap_or_default(),
+        Err(SyncJobError::ServerStopped) => {
+            return Err("Comparison interrupted (rclone stopped)".to_string());
+        }
+        Err(SyncJobError::Cancelled) => return Err("Comparison cancelled".to_string()),
+        Err(SyncJobError::Failed(e)) => {
+            return Err(format!("Failed to get hashes of {}: {}", fs, e));
+        }
+    };
+
+    // Lines look like `<hash>  <path>`, files without a hash have blanks or an error instead
+    let lines = output["hashsum"].as_array().into_iter().flatten();
+    Ok(lines
+        .filter_map(|line| line.as_str()?.split_once("  "))
+        .filter(|(hash, _)| hash.len() == 32 && hash.chars().all(|c| c.is_ascii_hexdigit()))
+        .map(|(hash, path)| (path.to_string(), hash.to_string()))
+        .collect())
+}
@@ -1,17 +1,84 @@
-use super::utils::parse_gdrive_id;
+use super::bandwidth::validate_bwlimit;
+use super::history::{self, HistoryEntry};
+use super::jobs::run_download;
+use super::options::TransferOptions;
+use super::plan::SyncPlan;
+use super::report::DownloadReport;
+use super::utils::{escape_glob, local_fs, parse_gdrive_id};
 use crate::api::rclone;
+use crate::service::{Host, RcClientFactory};
 use rclone_sdk::ClientInfo;
 use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
 use std::path::PathBuf;
+use std::sync::{LazyLock, Mutex};
 use std::time::Duration;
 use tokio::time::sleep;
 
+/// Prefix of the backup directories created next to the destination
+pub(super) const BACKUP_PREFIX: &str = "Backup-KAR-";
+pub(super) const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%d_%H%M%S";
+
+/// How long to wait for rclone to acknowledge a `job/stop`
+const CANCEL_ACK_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Jobs stopped through `job/stop`, so their failure can be reported as a cancellation.
+/// Ids are dropped once the job settles, and all of them when a new server starts
+/// since rclone numbers jobs from 1 again.
+static STOPPED_JOBS: LazyLock<Mutex<HashSet<i64>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
 /// Result from executing a sync job
-struct SyncJobResult {
-    deletes: i64,
-    checks: i64,
-    transfers: i64,
-    errors: i64,
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncJobResult {
+    pub deletes: i64,
+    pub checks: i64,
+    pub transfers: i64,
+    pub renames: i64,
+    pub errors: i64,
+    pub bytes: i64,
+    /// Seconds the job has been running
+    pub elapsed_time: f64,
+}
+
+/// Reason a sync job ended without completing
+#[derive(Debug)]
+pub enum SyncJobError {
+    /// The rc server went away while the job was running
+    ServerStopped,
+    /// The job was stopped through `job/stop`
+    Cancelled,
+    Failed(String),
+}
+
+impl std::fmt::Display for SyncJobError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ServerStopped => write!(f, "Download interrupted (rclone stopped)"),
+            Self::Cancelled => write!(f, "Download cancelled"),
+            Self::Failed(e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl From<SyncJobError> for String {
+    fn from(e: SyncJobError) -> Self {
+        e.to_string()
+    }
+}
+
+impl From<String> for SyncJobError {
+    fn from(e: String) -> Self {
+        Self::Failed(e)
+    }
+}
+
+/// Result of cancelling a download, with the stats of the work done before it stopped
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CancelledDownload {
+    pub jobid: i64,
+    pub stats: SyncJobResult,
 }
 
 /// Result from a dry run check
@@ -20,19 +87,34 @@ pub struct DryRunResult {
     pub would_delete: bool,
     pub deleted_files: Vec<String>,
     pub stats: String,
+    pub plan: SyncPlan,
 }
 
 /// Configuration for a Google Drive download operation
-struct DownloadConfig {
-    source: String,
-    destination: String,
-    remote_config: String,
-    sync_mode: bool,
-    create_subfolder: bool,
-    selected_files: Option<Vec<String>>,
-    create_backup: bool,
-    delete_excluded: bool,
-    track_renames: bool,
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadConfig {
+    pub source: String,
+    pub destination: String,
+    pub remote_config: String,
+    pub sync_mode: bool,
+    pub create_subfolder: bool,
+    pub selected_files: Option<Vec<String>>,
+    pub create_backup: bool,
+    pub delete_excluded: bool,
+    pub track_renames: bool,
+    /// Bandwidth limit while this download runs, in rclone's `--bwlimit` syntax
+    #[serde(default)]
+    pub bw_limit: Option<String>,
+    #[serde(default)]
+    pub options: TransferOptions,
+}
+
+/// A download ready to be submitted to rclone
+pub(super) struct PreparedDownload {
+    pub body: serde_json::Value,
+    pub endpoint: &'static str,
+    pub backup_path: Option<String>,
 }
 
 /// Paths for source and destination filesystems
@@ -43,7 +125,7 @@ struct FilesystemPaths {
 }
 
 impl DownloadConfig {
-    fn new(
+    pub fn new(
         source: String,
         destination: String,
         remote_config: Option<String>,
@@ -53,11 +135,13 @@ impl DownloadConfig {
         create_backup: bool,
         delete_excluded: bool,
         track_renames: bool,
+        bw_limit: Option<String>,
+        options: Option<TransferOptions>,
     ) -> Result<Self, String> {
         let remote_config = remote_config
             .ok_or("Remote configuration is required. Please authorize first.".to_string())?;
 
-        Ok(Self {
+        let config = Self {
             source,
             destination,
             remote_config,
@@ -67,6 +151,49 @@ impl DownloadConfig {
             create_backup,
             delete_excluded,
             track_renames,
+            bw_limit: bw_limit.filter(|limit| !limit.trim().is_empty()),
+            options: options.unwrap_or_default(),
+        };
+        config.validate()?;
+        Ok(config)
+    }
+
+    /// Check a config received from the frontend or loaded from disk before running it
+    pub fn validate(&self) -> Result<(), String> {
+        if self.source.trim().is_empty() {
+            return Err("Source is required".to_string());
+        }
+        if self.destination.trim().is_empty() {
+            return Err("Destination is required".to_string());
+        }
+        if self.remote_config.trim().is_empty() {
+            return Err("Remote configuration is required. Please authorize first.".to_string());
+        }
+        if let Some(limit) = &self.bw_limit {
+            validate_bwlimit(limit)?;
+        }
+        self.options.validate()
+    }
+
+    /// Build the request for a sync/copy job, reusing `backup_path` if one was already chosen
+    pub(super) fn prepare(&self, backup_path: Option<String>) -> Result<PreparedDownload, String> {
+        let mut paths = self.build_filesystem_paths()?;
+
+        // Keep resumed runs in the same backup directory so backups aren't split
+        if paths.backup_path.is_some() && backup_path.is_some() {
+            paths.backup_path = backup_path;
+        }
+
+        let endpoint = if self.sync_mode {
+            "/sync/sync"
+        } else {
+            "/sync/copy"
+        };
+
+        Ok(PreparedDownload {
+            body: self.build_request_body(&paths),
+            endpoint,
+            backup_path: paths.backup_path,
         })
     }
 
@@ -76,27 +203,10 @@ impl DownloadConfig {
         let src_fs = format!("{},root_folder_id={}:", self.remote_config, root_id);
 
         let dst_path = self.build_destination_path();
-        let mut dst_fs = dst_path.to_string_lossy().to_string();
-
-        // Windows: Avoid `C:` being interpreted as a remote, use UNC paths
-        if cfg!(windows) {
-            let mut path_str = dst_fs.replace("/", "\\");
-            if !path_str.starts_with("\\\\?\\") {
-                path_str = format!("\\\\?\\{}", path_str);
-            }
-            dst_fs = path_str;
-        }
+        let dst_fs = local_fs(&dst_path.to_string_lossy());
 
         let backup_path = if self.create_backup {
-            let mut backup = self.build_backup_path(&dst_path)?;
-            if cfg!(windows) {
-                let mut path_str = backup.replace("/", "\\");
-                if !path_str.starts_with("\\\\?\\") {
-                    path_str = format!("\\\\?\\{}", path_str);
-                }
-                backup = path_str;
-            }
-            Some(backup)
+            Some(local_fs(&self.build_backup_path(&dst_path)?))
         } else {
             None
         };
@@ -110,27 +220,15 @@ impl DownloadConfig {
 
     /// Build the destination path, optionally adding a subfolder
     fn build_destination_path(&self) -> PathBuf {
-        let mut dst_path = PathBuf::from(&self.destination);
-
-        if self.create_subfolder {
-            let already_has_subfolder = dst_path
-                .file_name()
-                .and_then(|name| name.to_str())
-                .map(|name| name == "Unofficial-Neuro-Karaoke-Archive")
-                .unwrap_or(false);
-
-            if !already_has_subfolder {
-                dst_path.push("Unofficial-Neuro-Karaoke-Archive");
-            }
-        }
-
-        dst_path
+        build_destination_path(&self.destination, self.create_subfolder)
     }
 
     /// Build backup path one level above the destination
     fn build_backup_path(&self, dst_path: &PathBuf) -> Result<String, String> {
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S").to_string();
-        let backup_name = format!("Backup-KAR-{}", timestamp);
+        let timestamp = chrono::Local::now()
+            .format(BACKUP_TIMESTAMP_FORMAT)
+            .to_string();
+        let backup_name = format!("{}{}", BACKUP_PREFIX, timestamp);
 
         let parent_path = dst_path
             .parent()
@@ -150,6 +248,7 @@ impl DownloadConfig {
 
         // Build config object
         let mut config = serde_json::Map::new();
+        self.options.apply_to_config(&mut config);
 
         if let Some(ref backup) = paths.backup_path {
             config.insert("BackupDir".to_string(), serde_json::json!(backup));
@@ -182,6 +281,48 @@ impl DownloadConfig {
     }
 }
 
+/// Build the request for an `operations/check` of the destination against the Drive,
+/// comparing hashes without downloading
+pub(super) fn build_check_body(config: &DownloadConfig) -> Result<serde_json::Value, String> {
+    let paths = config.build_filesystem_paths()?;
+    let mut body = serde_json::json!({
+        "srcFs": paths.src_fs,
+        "dstFs": paths.dst_fs,
+        "download": false,
+        "combined": true
+    });
+
+    let mut rc_config = serde_json::Map::new();
+    config.options.apply_to_config(&mut rc_config);
+    if !rc_config.is_empty() {
+        body["_config"] = serde_json::json!(rc_config);
+    }
+    if let Some(ref files) = config.selected_files {
+        body["_filter"] = build_file_filter(files);
+    }
+
+    Ok(body)
+}
+
+/// Build the destination path, optionally adding a subfolder
+pub(super) fn build_destination_path(destination: &str, create_subfolder: bool) -> PathBuf {
+    let mut dst_path = PathBuf::from(destination);
+
+    if create_subfolder {
+        let already_has_subfolder = dst_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .map(|name| name == "Unofficial-Neuro-Karaoke-Archive")
+            .unwrap_or(false);
+
+        if !already_has_subfolder {
+            dst_path.push("Unofficial-Neuro-Karaoke-Archive");
+        }
+    }
+
+    dst_path
+}
+
 /// Build filter rules for selected files
 fn build_file_filter(files: &[String]) -> serde_json::Value {
     if files.is_empty() {
@@ -190,7 +331,7 @@ fn build_file_filter(files: &[String]) -> serde_json::Value {
 
     let mut final_includes = Vec::new();
     for f in files {
-        let clean_f = f.trim_start_matches('/');
+        let clean_f = escape_glob(f.trim_start_matches('/'));
         final_includes.push(format!("/{}", clean_f));
         final_includes.push(format!("/{}/**", clean_f));
     }
@@ -201,11 +342,21 @@ fn build_file_filter(files: &[String]) -> serde_json::Value {
 }
 
 /// Start the sync/copy operation, wait for completion, and return the results
-async fn start_sync_job(
+pub async fn start_sync_job(
     client: &rclone_sdk::Client,
     body: &serde_json::Value,
     endpoint: &str,
 ) -> Result<SyncJobResult, String> {
+    let jobid = submit_sync_job(client, body, endpoint).await?;
+    Ok(wait_for_sync_job(client, jobid).await?)
+}
+
+/// Submit the sync/copy operation and return its jobid
+pub async fn submit_sync_job(
+    client: &rclone_sdk::Client,
+    body: &serde_json::Value,
+    endpoint: &str,
+) -> Result<i64, String> {
     let response = client
         .client()
         .post(format!("{}{}", client.baseurl(), endpoint))
@@ -224,17 +375,34 @@ async fn start_sync_job(
         .await
         .map_err(|e| format!("Failed to parse sync response: {}", e))?;
 
-    let jobid = result.jobid.ok_or("No jobid returned".to_string())?;
+    result.jobid.ok_or("No jobid returned".to_string())
+}
 
+/// Wait for a submitted job to finish and return its results
+async fn wait_for_sync_job(
+    client: &rclone_sdk::Client,
+    jobid: i64,
+) -> Result<SyncJobResult, SyncJobError> {
     // Poll for job completion
     poll_job_completion(client, jobid).await?;
 
     // Get final stats
-    get_job_stats(client, jobid).await
+    Ok(get_job_stats(client, jobid).await?)
 }
 
 /// Poll for job completion
-async fn poll_job_completion(client: &rclone_sdk::Client, jobid: i64) -> Result<(), String> {
+pub async fn poll_job_completion(
+    client: &rclone_sdk::Client,
+    jobid: i64,
+) -> Result<(), SyncJobError> {
+    poll_job_output(client, jobid).await.map(|_| ())
+}
+
+/// Poll until the job finishes and return its output, e.g. the result of `operations/check`
+pub async fn poll_job_output(
+    client: &rclone_sdk::Client,
+    jobid: i64,
+) -> Result<Option<serde_json::Value>, SyncJobError> {
     loop {
         let response_result = client
             .client()
@@ -247,23 +415,17 @@ async fn poll_job_completion(client: &rclone_sdk::Client, jobid: i64) -> Result<
 
         let response = match response_result {
             Ok(res) => res,
-            Err(e) => {
-                let err_str = e.to_string();
-                if err_str.contains("error sending request")
-                    || err_str.contains("connection refused")
-                {
-                    return Err("Download cancelled (server stopped)".to_string());
-                }
-                return Err(format!("Failed to check job status: {}", e));
-            }
+            Err(e) if e.is_connect() => return Err(SyncJobError::ServerStopped),
+            Err(e) => return Err(format!("Failed to check job status: {}", e).into()),
         };
 
         if !response.status().is_success() {
             let err_text = response.text().await.unwrap_or_default();
             if err_text.contains("job not found") {
-                return Err("Download cancelled".to_string());
+                forget_stopped_job(jobid);
+                return Err(SyncJobError::Cancelled);
             }
-            return Err(format!("Job status check failed: {}", err_text));
+            return Err(format!("Job status check failed: {}", err_text).into());
         }
 
         let status: rclone_sdk::types::JobStatusResponse = response
@@ -272,18 +434,82 @@ async fn poll_job_completion(client: &rclone_sdk::Client, jobid: i64) -> Result<
             .map_err(|e| format!("Failed to parse job status: {}", e))?;
 
         if status.finished {
+            let stopped = forget_stopped_job(jobid);
             if !status.error.is_empty() {
-                return Err(format!("Job failed: {}", status.error));
+                if stopped {
+                    return Err(SyncJobError::Cancelled);
+                }
+                return Err(format!("Job failed: {}", status.error).into());
             }
-            return Ok(());
+            return Ok(status.output);
         }
 
         sleep(Duration::from_secs(1)).await;
     }
 }
 
+/// Drop a settled job from the stopped ones, returning whether it was stopped
+fn forget_stopped_job(jobid: i64) -> bool {
+    STOPPED_JOBS
+        .lock()
+        .map(|mut stopped| stopped.remove(&jobid))
+        .unwrap_or(false)
+}
+
+/// Forget every stopped job, called when a new rc server starts
+pub fn forget_stopped_jobs() {
+    if let Ok(mut stopped) = STOPPED_JOBS.lock() {
+        stopped.clear();
+    }
+}
+
+/// Ask rclone to stop a running job, leaving the rc server up
+pub(super) async fn stop_sync_job(client: &rclone_sdk::Client, jobid: i64) -> Result<(), String> {
+    if let Ok(mut stopped) = STOPPED_JOBS.lock() {
+        stopped.insert(jobid);
+    }
+
+    let response = client
+        .client()
+        .post(format!("{}/job/stop", client.baseurl()))
+        .json(&serde_json::json!({
+            "jobid": jobid
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Failed to stop job: {}", e))?;
+
+    if !response.status().is_success() {
+        let err_text = response.text().await.unwrap_or_default();
+        return Err(format!("Failed to stop job: {}", err_text));
+    }
+
+    Ok(())
+}
+
+/// Stop a running job, wait for rclone to acknowledge it and return its partial stats
+pub(super) async fn cancel_sync_job(
+    client: &rclone_sdk::Client,
+    jobid: i64,
+) -> Result<SyncJobResult, String> {
+    stop_sync_job(client, jobid).await?;
+
+    match tokio::time::timeout(CANCEL_ACK_TIMEOUT, poll_job_completion(client, jobid)).await {
+        Err(_) => return Err(format!("Timed out waiting for job {} to stop", jobid)),
+        Ok(Err(SyncJobError::ServerStopped)) => return Err(SyncJobError::ServerStopped.into()),
+        // Jobs that ended before the stop arrived, or whose runner saw the stop first,
+        // have their final stats reported too
+        Ok(_) => {}
+    }
+
+    get_job_stats(client, jobid).await
+}
+
 /// Get job statistics from rclone
-async fn get_job_stats(client: &rclone_sdk::Client, jobid: i64) -> Result<SyncJobResult, String> {
+pub async fn get_job_stats(
+    client: &rclone_sdk::Client,
+    jobid: i64,
+) -> Result<SyncJobResult, String> {
     let stats_response = client
         .client()
         .post(format!("{}/core/stats", client.baseurl()))
@@ -303,7 +529,10 @@ async fn get_job_stats(client: &rclone_sdk::Client, jobid: i64) -> Result<SyncJo
         deletes: stats["deletes"].as_i64().unwrap_or(0),
         checks: stats["checks"].as_i64().unwrap_or(0),
         transfers: stats["transfers"].as_i64().unwrap_or(0),
+        renames: stats["renames"].as_i64().unwrap_or(0),
         errors: stats["errors"].as_i64().unwrap_or(0),
+        bytes: stats["bytes"].as_i64().unwrap_or(0),
+        elapsed_time: stats["elapsedTime"].as_f64().unwrap_or(0.0),
     })
 }
 
@@ -319,7 +548,9 @@ pub async fn download_gdrive(
     create_backup: bool,
     delete_excluded: bool,
     track_renames: bool,
-) -> Result<String, String> {
+    bw_limit: Option<String>,
+    options: Option<TransferOptions>,
+) -> Result<DownloadReport, String> {
     let config = DownloadConfig::new(
         source,
         destination,
@@ -330,23 +561,26 @@ pub async fn download_gdrive(
         create_backup,
         delete_excluded,
         track_renames,
+        bw_limit,
+        options,
     )?;
 
-    let client = rclone::get_sdk_client(&app).await?;
-    let paths = config.build_filesystem_paths()?;
-    let body = config.build_request_body(&paths);
+    run_download(&app, config).await
+}
 
-    let endpoint = if config.sync_mode {
-        "/sync/sync"
-    } else {
-        "/sync/copy"
-    };
-    let _result = start_sync_job(&client, &body, endpoint).await?;
+/// Cancel a single rclone job without stopping the rc server
+#[tauri::command]
+pub async fn cancel_download(
+    app: tauri::AppHandle,
+    jobid: i64,
+) -> Result<CancelledDownload, String> {
+    let client = app.rc_client().await?;
+    let stats = cancel_sync_job(&client, jobid).await?;
 
-    Ok("Download completed successfully".to_string())
+    Ok(CancelledDownload { jobid, stats })
 }
 
-/// Perform a dry run sync to detect what files would be deleted
+/// Perform a dry run sync to find what a sync would add, update, rename and delete
 #[tauri::command]
 pub async fn check_dry_run(
     app: tauri::AppHandle,
@@ -357,6 +591,8 @@ pub async fn check_dry_run(
     selected_files: Option<Vec<String>>,
     delete_excluded: bool,
     track_renames: bool,
+    create_backup: Option<bool>,
+    options: Option<TransferOptions>,
 ) -> Result<DryRunResult, String> {
     let config = DownloadConfig::new(
         source,
@@ -365,13 +601,42 @@ pub async fn check_dry_run(
         true, // Dry run is only for sync mode
         create_subfolder,
         selected_files,
-        false, // No backup for dry run check
+        create_backup.unwrap_or(false),
         delete_excluded,
         track_renames,
+        None,
+        options,
     )?;
 
-    let client = rclone::get_sdk_client(&app).await?;
-    let paths = config.build_filesystem_paths()?;
+    dry_run(&app, config).await
+}
+
+/// Dry run a download and record it in the history
+pub async fn dry_run(host: &impl Host, config: DownloadConfig) -> Result<DryRunResult, String> {
+    let started_at = chrono::Local::now().to_rfc3339();
+    let result = run_dry_run(host, &config).await;
+
+    let report = result
+        .as_ref()
+        .map(|(_, report)| report.clone())
+        .map_err(|e| e.clone());
+    if let Err(e) = history::record(host, &HistoryEntry::dry_run(config, started_at, report)) {
+        log::warn!("Failed to record dry run history: {}", e);
+    }
+
+    result.map(|(dry_run, _)| dry_run)
+}
+
+/// Run the dry run sync, returning the result along with a report for the history
+async fn run_dry_run(
+    host: &impl Host,
+    config: &DownloadConfig,
+) -> Result<(DryRunResult, DownloadReport), String> {
+    let client = host.rc_client().await?;
+    let mut paths = config.build_filesystem_paths()?;
+
+    // Nothing is moved during a dry run, backups are only reported in the plan
+    paths.backup_path = None;
 
     // Build the sync request with dry-run flag
     let mut body = config.build_request_body(&paths);
@@ -387,23 +652,42 @@ pub async fn check_dry_run(
         });
     }
 
-    // Capture the current log offset to ignore previous logs
-    let start_offset = rclone::LogManager::get_current_offset(&app).await;
+    // Only read what's logged from here on, and let other jobs know about this one
+    let log = rclone::LogSpan::open(host).await;
 
     let result = start_sync_job(&client, &body, "/sync/sync").await?;
 
     // Parse logs from the offset
-    let deleted_files = rclone::LogManager::parse_deleted_files(&app, start_offset).await?;
+    let actions = rclone::LogManager::parse_dry_run_actions(host, log.offset).await?;
+    let plan = SyncPlan::from_skipped_actions(
+        actions,
+        &config.build_destination_path(),
+        config.create_backup,
+    );
 
+    let deleted_files: Vec<String> = plan.deleted_files.iter().map(|f| f.path.clone()).collect();
     let would_delete = result.deletes > 0 || !deleted_files.is_empty();
     let stats_summary = format!(
         "Checks: {}, Transfers: {}, Deletes: {}, Errors: {}",
         result.checks, result.transfers, result.deletes, result.errors
     );
 
-    Ok(DryRunResult {
-        would_delete,
-        deleted_files,
-        stats: stats_summary,
-    })
+    let report = DownloadReport {
+        changes: Some(plan.to_changes()),
+        stats: SyncJobResult {
+            bytes: plan.bytes_to_transfer as i64,
+            ..result
+        },
+        ..Default::default()
+    };
+
+    Ok((
+        DryRunResult {
+            would_delete,
+            deleted_files,
+            stats: stats_summary,
+            plan,
+        },
+        report,
+    ))
 }
@@ -0,0 +1,276 @@
+use super::download::{DownloadConfig, SyncJobResult};
+use super::jobs::{DownloadJob, JobStatus};
+use super::report::DownloadReport;
+use crate::service::PathProvider;
+use crate::utils::app_data_file;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, Write};
+use std::sync::{LazyLock, Mutex};
+use tauri::AppHandle;
+
+const HISTORY_FILE_NAME: &str = "history.jsonl";
+
+static HISTORY_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum RunKind {
+    Download,
+    DryRun,
+}
+
+/// A past download or dry run, including the files it changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistoryEntry {
+    pub id: String,
+    pub kind: RunKind,
+    pub config: DownloadConfig,
+    pub status: JobStatus,
+    pub started_at: Option<String>,
+    pub finished_at: String,
+    pub error: Option<String>,
+    pub report: Option<DownloadReport>,
+}
+
+/// A history entry without its file lists
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HistorySummary {
+    pub id: String,
+    pub kind: RunKind,
+    pub source: String,
+    pub destination: String,
+    pub remote_config: String,
+    pub status: JobStatus,
+    pub started_at: Option<String>,
+    pub finished_at: String,
+    pub error: Option<String>,
+    pub stats: Option<SyncJobResult>,
+    pub backup_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum FileChangeKind {
+    Added,
+    Updated,
+    Deleted,
+    RenamedFrom,
+    RenamedTo,
+    Error,
+}
+
+/// A change to a single file recorded in the history
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileHistoryEvent {
+    pub entry_id: String,
+    pub finished_at: String,
+    pub destination: String,
+    pub path: String,
+    pub change: FileChangeKind,
+}
+
+impl HistoryEntry {
+    fn new_id(kind: RunKind) -> String {
+        let prefix = match kind {
+            RunKind::Download => "download",
+            RunKind::DryRun => "dry-run",
+        };
+        format!(
+            "{}-{}",
+            prefix,
+            chrono::Local::now().format("%Y%m%d%H%M%S%3f")
+        )
+    }
+
+    /// Build the entry for a finished job
+    pub fn from_job(job: &DownloadJob) -> Self {
+        Self {
+            id: Self::new_id(RunKind::Download),
+            kind: RunKind::Download,
+            config: job.config.clone(),
+            status: job.status,
+            started_at: job.started_at.clone(),
+            finished_at: job
+                .finished_at
+                .clone()
+                .unwrap_or_else(|| chrono::Local::now().to_rfc3339()),
+            error: job.error.clone(),
+            report: job.report.clone(),
+        }
+    }
+
+    /// Build the entry for a dry run
+    pub fn dry_run(
+        config: DownloadConfig,
+        started_at: String,
+        result: Result<DownloadReport, String>,
+    ) -> Self {
+        let (status, error, report) = match result {
+            Ok(report) => (JobStatus::Completed, None, Some(report)),
+            Err(e) => (JobStatus::Failed, Some(e), None),
+        };
+
+        Self {
+            id: Self::new_id(RunKind::DryRun),
+            kind: RunKind::DryRun,
+            config,
+            status,
+            started_at: Some(started_at),
+            finished_at: chrono::Local::now().to_rfc3339(),
+            error,
+            report,
+        }
+    }
+
+    fn summary(&self) -> HistorySummary {
+        HistorySummary {
+            id: self.id.clone(),
+            kind: self.kind,
+            source: self.config.source.clone(),
+            destination: self.config.destination.clone(),
+            remote_config: self.config.remote_config.clone(),
+            status: self.status,
+            started_at: self.started_at.clone(),
+            finished_at: self.finished_at.clone(),
+            error: self.error.clone(),
+            stats: self.report.as_ref().map(|r| r.stats.clone()),
+            backup_dir: self.report.as_ref().and_then(|r| r.backup_dir.clone()),
+        }
+    }
+
+    /// Every file change in this entry
+    fn file_events(&self) -> Vec<FileHistoryEvent> {
+        let Some(changes) = self.report.as_ref().and_then(|r| r.changes.as_ref()) else {
+            return vec![];
+        };
+
+        let event = |path: &str, change| FileHistoryEvent {
+            entry_id: self.id.clone(),
+            finished_at: self.finished_at.clone(),
+            destination: self.config.destination.clone(),
+            path: path.to_string(),
+            change,
+        };
+
+        let mut events = Vec::new();
+        events.extend(
+            changes
+                .added
+                .iter()
+                .map(|p| event(p, FileChangeKind::Added)),
+        );
+        events.extend(
+            changes
+                .updated
+                .iter()
+                .map(|p| event(p, FileChangeKind::Updated)),
+        );
+        events.extend(
+            changes
+                .deleted
+                .iter()
+                .map(|p| event(p, FileChangeKind::Deleted)),
+        );
+        for renamed in &changes.renamed {
+            events.push(event(&renamed.from, FileChangeKind::RenamedFrom));
+            events.push(event(&renamed.to, FileChangeKind::RenamedTo));
+        }
+        events.extend(
+            changes
+                .errors
+                .iter()
+                .map(|e| event(&e.path, FileChangeKind::Error)),
+        );
+        events
+    }
+}
+
+/// Append an entry to the history file
+pub fn record(paths: &impl PathProvider, entry: &HistoryEntry) -> Result<(), String> {
+    let path = app_data_file(paths, HISTORY_FILE_NAME)?;
+    let line = serde_json::to_string(entry).map_err(|e| e.to_string())?;
+
+    let _guard = HISTORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create app dir: {}", e))?;
+    }
+
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .map_err(|e| format!("Failed to open history file: {}", e))?;
+    writeln!(file, "{}", line).map_err(|e| format!("Failed to write history file: {}", e))
+}
+
+/// Read all history entries, oldest first
+fn read_all(paths: &impl PathProvider) -> Result<Vec<HistoryEntry>, String> {
+    let path = app_data_file(paths, HISTORY_FILE_NAME)?;
+
+    let _guard = HISTORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    if !path.exists() {
+        return Ok(vec![]);
+    }
+
+    let file =
+        std::fs::File::open(&path).map_err(|e| format!("Failed to open history file: {}", e))?;
+
+    let mut entries = Vec::new();
+    for line in std::io::BufReader::new(file).lines() {
+        let line = line.map_err(|e| format!("Failed to read history file: {}", e))?;
+        // Skip lines from a write that was cut short
+        if let Ok(entry) = serde_json::from_str(&line) {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// List past runs, newest first
+#[tauri::command]
+pub async fn get_download_history(
+    app: AppHandle,
+    limit: Option<usize>,
+    include_dry_runs: Option<bool>,
+) -> Result<Vec<HistorySummary>, String> {
+    let include_dry_runs = include_dry_runs.unwrap_or(true);
+
+    Ok(read_all(&app)?
+        .iter()
+        .rev()
+        .filter(|entry| include_dry_runs || entry.kind == RunKind::Download)
+        .take(limit.unwrap_or(usize::MAX))
+        .map(HistoryEntry::summary)
+        .collect())
+}
+
+/// Get a single run with the full list of files it changed
+#[tauri::command]
+pub async fn get_history_entry(app: AppHandle, id: String) -> Result<HistoryEntry, String> {
+    read_all(&app)?
+        .into_iter()
+        .find(|entry| entry.id == id)
+        .ok_or(format!("History entry {} not found", id))
+}
+
+/// Find every recorded change to files whose path contains `query`, newest first
+#[tauri::command]
+pub async fn find_file_history(
+    app: AppHandle,
+    query: String,
+) -> Result<Vec<FileHistoryEvent>, String> {
+    let query = query.to_lowercase();
+
+    Ok(read_all(&app)?
+        .iter()
+        .rev()
+        // Dry runs never changed anything
+        .filter(|entry| entry.kind == RunKind::Download)
+        .flat_map(HistoryEntry::file_events)
+        .filter(|event| event.path.to_lowercase().contains(&query))
+        .collect())
+}
@@ -0,0 +1,725 @@
+use super::bandwidth::{self, validate_bwlimit};
+use super::download::{
+    DownloadConfig, SyncJobError, cancel_sync_job, poll_job_completion, stop_sync_job,
+    submit_sync_job,
+};
+use super::history::{self, HistoryEntry};
+use super::options::TransferOptions;
+use super::report::DownloadReport;
+use crate::api::rclone::LogSpan;
+use crate::service::{EventSink, JobHost, PathProvider, RcClientFactory};
+use crate::utils::{app_data_file, read_json_file, write_json_file};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, State};
+use tokio::sync::oneshot;
+
+const JOBS_FILE_NAME: &str = "download_jobs.json";
+const DEFAULT_CONCURRENCY: usize = 1;
+/// Attempts after which a job interrupted by an rclone crash is no longer re-queued
+const MAX_CRASH_ATTEMPTS: u32 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Paused,
+    Completed,
+    Failed,
+    Cancelled,
+    /// The app or rclone stopped while the job was queued or running
+    Interrupted,
+}
+
+impl JobStatus {
+    /// Whether the job is done and no longer needs to be persisted
+    pub fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+
+    /// Whether someone waiting on the job should stop waiting
+    fn is_settled(self) -> bool {
+        self.is_finished() || self == Self::Interrupted
+    }
+}
+
+/// A download queued in the job manager
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadJob {
+    pub id: u64,
+    pub config: DownloadConfig,
+    pub status: JobStatus,
+    /// Id of the rclone job for the current attempt
+    pub rclone_jobid: Option<i64>,
+    pub backup_path: Option<String>,
+    pub attempts: u32,
+    pub created_at: String,
+    pub started_at: Option<String>,
+    pub finished_at: Option<String>,
+    pub error: Option<String>,
+    pub report: Option<DownloadReport>,
+    /// Why there is no report for a job that ran
+    #[serde(default)]
+    pub report_error: Option<String>,
+}
+
+impl DownloadJob {
+    /// Convert a settled job into the result returned to the frontend
+    pub fn into_result(self) -> Result<DownloadReport, String> {
+        match self.status {
+            JobStatus::Completed => self.report.ok_or_else(|| {
+                format!(
+                    "Download completed, but its report is unavailable: {}",
+                    self.report_error
+                        .as_deref()
+                        .unwrap_or("it was not collected")
+                )
+            }),
+            JobStatus::Cancelled => Err("Download cancelled".to_string()),
+            _ => Err(self
+                .error
+                .unwrap_or_else(|| format!("Download {:?}", self.status).to_lowercase())),
+        }
+    }
+}
+
+/// Queue state persisted between sessions
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct JobQueue {
+    next_id: u64,
+    concurrency: usize,
+    /// Re-queue jobs interrupted by rclone crashing, to run once it restarted
+    #[serde(default)]
+    resume_after_crash: bool,
+    /// Bandwidth limit for jobs without their own, in rclone's `--bwlimit` syntax
+    #[serde(default)]
+    bw_limit: Option<String>,
+    jobs: Vec<DownloadJob>,
+}
+
+impl Default for JobQueue {
+    fn default() -> Self {
+        Self {
+            next_id: 1,
+            concurrency: DEFAULT_CONCURRENCY,
+            resume_after_crash: false,
+            bw_limit: None,
+            jobs: Vec::new(),
+        }
+    }
+}
+
+impl JobQueue {
+    fn get_mut(&mut self, id: u64) -> Result<&mut DownloadJob, String> {
+        self.jobs
+            .iter_mut()
+            .find(|job| job.id == id)
+            .ok_or(format!("Job {} not found", id))
+    }
+}
+
+#[derive(Default)]
+struct JobState {
+    queue: JobQueue,
+    waiters: HashMap<u64, Vec<oneshot::Sender<DownloadJob>>>,
+}
+
+impl JobState {
+    /// Hand the settled job to everyone waiting on it
+    fn notify(&mut self, job: &DownloadJob) {
+        if let Some(waiters) = self.waiters.remove(&job.id) {
+            for tx in waiters {
+                let _ = tx.send(job.clone());
+            }
+        }
+    }
+}
+
+/// Manages queued downloads and runs them with a limited concurrency
+pub struct JobManager {
+    state: Mutex<JobState>,
+    /// Whether the queue is saved, off for managers not owning the jobs file
+    persist: bool,
+}
+
+impl JobManager {
+    /// Load unfinished jobs from the previous session, marking them as interrupted
+    pub fn load(paths: &impl PathProvider) -> Self {
+        let mut queue: JobQueue = app_data_file(paths, JOBS_FILE_NAME)
+            .and_then(|path| read_json_file(&path))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        for job in &mut queue.jobs {
+            if matches!(job.status, JobStatus::Queued | JobStatus::Running) {
+                job.status = JobStatus::Interrupted;
+            }
+        }
+
+        Self {
+            state: Mutex::new(JobState {
+                queue,
+                waiters: HashMap::new(),
+            }),
+            persist: true,
+        }
+    }
+
+    /// Use the saved settings with an empty queue that is never saved,
+    /// so running downloads from the CLI doesn't touch the app's queue
+    pub fn detached(paths: &impl PathProvider) -> Self {
+        let saved: JobQueue = app_data_file(paths, JOBS_FILE_NAME)
+            .and_then(|path| read_json_file(&path))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        Self {
+            state: Mutex::new(JobState {
+                queue: JobQueue {
+                    jobs: Vec::new(),
+                    ..saved
+                },
+                waiters: HashMap::new(),
+            }),
+            persist: false,
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, JobState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Apply a change to the queue, then persist it and notify the frontend
+    fn update<R>(
+        &self,
+        host: &(impl PathProvider + EventSink),
+        f: impl FnOnce(&mut JobState) -> R,
+    ) -> R {
+        let mut state = self.lock();
+        let result = f(&mut state);
+        if !self.persist {
+            return result;
+        }
+
+        let unfinished = JobQueue {
+            next_id: state.queue.next_id,
+            concurrency: state.queue.concurrency,
+            resume_after_crash: state.queue.resume_after_crash,
+            bw_limit: state.queue.bw_limit.clone(),
+            jobs: state
+                .queue
+                .jobs
+                .iter()
+                .filter(|job| !job.status.is_finished())
+                .cloned()
+                .collect(),
+        };
+        if let Err(e) =
+            app_data_file(host, JOBS_FILE_NAME).and_then(|path| write_json_file(&path, &unfinished))
+        {
+            log::error!("Failed to save download jobs: {}", e);
+        }
+
+        host.send_event("download-jobs-changed", &state.queue.jobs);
+        result
+    }
+
+    /// Get a snapshot of all jobs in queue order
+    pub fn list(&self) -> Vec<DownloadJob> {
+        self.lock().queue.jobs.clone()
+    }
+
+    /// Add a download to the end of the queue, returning its id
+    pub fn enqueue(&self, host: &(impl PathProvider + EventSink), config: DownloadConfig) -> u64 {
+        self.update(host, |state| {
+            let id = state.queue.next_id;
+            state.queue.next_id += 1;
+            state.queue.jobs.push(DownloadJob {
+                id,
+                config,
+                status: JobStatus::Queued,
+                rclone_jobid: None,
+                backup_path: None,
+                attempts: 0,
+                created_at: chrono::Local::now().to_rfc3339(),
+                started_at: None,
+                finished_at: None,
+                error: None,
+                report: None,
+                report_error: None,
+            });
+            id
+        })
+    }
+
+    /// The bandwidth limit in effect: the own limit of the most recently
+    /// started running job that has one, otherwise the global limit
+    pub fn bwlimit(&self) -> Option<String> {
+        let state = self.lock();
+        state
+            .queue
+            .jobs
+            .iter()
+            .filter(|job| job.status == JobStatus::Running && job.config.bw_limit.is_some())
+            .max_by(|a, b| a.started_at.cmp(&b.started_at))
+            .and_then(|job| job.config.bw_limit.clone())
+            .or_else(|| state.queue.bw_limit.clone())
+    }
+
+    /// Wait until a job has finished or been interrupted
+    pub async fn wait(&self, id: u64) -> Result<DownloadJob, String> {
+        let (rx, snapshot) = {
+            let mut state = self.lock();
+            let job = state
+                .queue
+                .jobs
+                .iter()
+                .find(|job| job.id == id)
+                .cloned()
+                .ok_or(format!("Job {} not found", id))?;
+            if job.status.is_settled() {
+                return Ok(job);
+            }
+
+            let (tx, rx) = oneshot::channel();
+            state.waiters.entry(id).or_default().push(tx);
+            (rx, job)
+        };
+
+        Ok(rx.await.unwrap_or(snapshot))
+    }
+}
+
+/// Queue a download, wait for it and return its report
+pub async fn run_download(
+    host: &impl JobHost,
+    config: DownloadConfig,
+) -> Result<DownloadReport, String> {
+    let manager = host.job_manager();
+    let id = manager.enqueue(host, config);
+    dispatch_jobs(host);
+
+    manager.wait(id).await?.into_result()
+}
+
+/// Start queued jobs until the concurrency limit is reached
+pub fn dispatch_jobs(host: &impl JobHost) {
+    let manager = host.job_manager();
+    let started = manager.update(host, |state| {
+        let queue = &mut state.queue;
+        let running = queue
+            .jobs
+            .iter()
+            .filter(|job| job.status == JobStatus::Running)
+            .count();
+        let available = queue.concurrency.saturating_sub(running);
+
+        let mut started = Vec::new();
+        for job in queue
+            .jobs
+            .iter_mut()
+            .filter(|job| job.status == JobStatus::Queued)
+            .take(available)
+        {
+            job.status = JobStatus::Running;
+            job.attempts += 1;
+            job.rclone_jobid = None;
+            job.error = None;
+            job.report = None;
+            job.report_error = None;
+            job.started_at = Some(chrono::Local::now().to_rfc3339());
+            started.push(job.clone());
+        }
+        started
+    });
+
+    for job in started {
+        let host = host.clone();
+        tauri::async_runtime::spawn(async move {
+            run_job(host, job).await;
+        });
+    }
+}
+
+/// Run a single attempt of a job and record its outcome
+async fn run_job(host: impl JobHost, job: DownloadJob) {
+    let (result, report) = execute_job(&host, &job).await;
+
+    let manager = host.job_manager();
+    let finished = manager.update(&host, |state| {
+        let resume_after_crash = state.queue.resume_after_crash;
+        let current = state.queue.get_mut(job.id).ok()?;
+
+        // Already retried, or paused by the user and going to run again
+        if current.attempts != job.attempts || current.status == JobStatus::Paused {
+            return None;
+        }
+
+        // Run again once the supervisor restarted rclone
+        if current.status == JobStatus::Running
+            && matches!(result, Err(SyncJobError::ServerStopped))
+            && resume_after_crash
+            && current.attempts < MAX_CRASH_ATTEMPTS
+        {
+            current.status = JobStatus::Queued;
+            current.rclone_jobid = None;
+            return None;
+        }
+
+        // Cancelled by the user jobs keep their status but still get their report
+        if current.status == JobStatus::Running {
+            current.status = match &result {
+                Ok(()) => JobStatus::Completed,
+                Err(SyncJobError::ServerStopped) => JobStatus::Interrupted,
+                Err(SyncJobError::Cancelled) => JobStatus::Cancelled,
+                Err(SyncJobError::Failed(_)) => JobStatus::Failed,
+            };
+            current.error = result.err().map(String::from);
+            current.finished_at = Some(chrono::Local::now().to_rfc3339());
+        }
+        (current.report, current.report_error) = match report {
+            Ok(report) => (Some(report), None),
+            Err(e) => (None, Some(e)),
+        };
+
+        let settled = current.clone();
+        state.notify(&settled);
+        Some(settled)
+    });
+
+    if let Some(job) = finished
+        && let Err(e) = history::record(&host, &HistoryEntry::from_job(&job))
+    {
+        log::warn!("Failed to record download history: {}", e);
+    }
+
+    // Drop the job's own bandwidth limit
+    if job.config.bw_limit.is_some() {
+        bandwidth::refresh_bwlimit(&host).await;
+    }
+
+    dispatch_jobs(&host);
+}
+
+/// A job accepted by rclone
+struct StartedJob {
+    client: rclone_sdk::Client,
+    jobid: i64,
+    log: LogSpan,
+    backup_path: Option<String>,
+}
+
+async fn execute_job(
+    host: &impl JobHost,
+    job: &DownloadJob,
+) -> (Result<(), SyncJobError>, Result<DownloadReport, String>) {
+    let started = match start_job(host, job).await {
+        Ok(started) => started,
+        Err(e) => return (Err(e), Err("the download didn't start".to_string())),
+    };
+
+    let result = poll_job_completion(&started.client, started.jobid).await;
+
+    // Stats and logs are gone along with the server
+    let report = if matches!(result, Err(SyncJobError::ServerStopped)) {
+        Err("rclone stopped".to_string())
+    } else {
+        DownloadReport::collect(
+            host,
+            &started.client,
+            started.jobid,
+            &started.log,
+            started.backup_path,
+        )
+        .await
+        .inspect_err(|e| log::warn!("Failed to collect download report: {}", e))
+    };
+
+    (result, report)
+}
+
+async fn start_job(host: &impl JobHost, job: &DownloadJob) -> Result<StartedJob, SyncJobError> {
+    let prepared = job.config.prepare(job.backup_path.clone())?;
+    let client = host.rc_client().await?;
+    if let Err(e) = bandwidth::apply_bwlimit(host, &client).await {
+        log::warn!("{}", e);
+    }
+    let log = LogSpan::open(host).await;
+    let jobid = submit_sync_job(&client, &prepared.body, prepared.endpoint).await?;
+
+    let manager = host.job_manager();
+    let still_running = manager.update(host, |state| {
+        let Ok(current) = state.queue.get_mut(job.id) else {
+            return false;
+        };
+        if current.attempts != job.attempts {
+            return false;
+        }
+        current.rclone_jobid = Some(jobid);
+        current.backup_path = prepared.backup_path.clone();
+        current.status == JobStatus::Running
+    });
+
+    // The job was paused or cancelled before rclone accepted it
+    if !still_running {
+        stop_sync_job(&client, jobid).await?;
+    }
+
+    Ok(StartedJob {
+        client,
+        jobid,
+        log,
+        backup_path: prepared.backup_path,
+    })
+}
+
+/// Stop the rclone job behind a job that was running and wait for it to end
+async fn stop_running(rc: &impl RcClientFactory, rclone_jobid: Option<i64>) -> Result<(), String> {
+    if let Some(jobid) = rclone_jobid {
+        let client = rc.rc_client().await?;
+        cancel_sync_job(&client, jobid).await?;
+    }
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn queue_download(
+    app: AppHandle,
+    state: State<'_, JobManager>,
+    source: String,
+    destination: String,
+    remote_config: Option<String>,
+    sync_mode: bool,
+    create_subfolder: bool,
+    selected_files: Option<Vec<String>>,
+    create_backup: bool,
+    delete_excluded: bool,
+    track_renames: bool,
+    bw_limit: Option<String>,
+    options: Option<TransferOptions>,
+) -> Result<u64, String> {
+    let config = DownloadConfig::new(
+        source,
+        destination,
+        remote_config,
+        sync_mode,
+        create_subfolder,
+        selected_files,
+        create_backup,
+        delete_excluded,
+        track_renames,
+        bw_limit,
+        options,
+    )?;
+
+    let id = state.enqueue(&app, config);
+    dispatch_jobs(&app);
+    Ok(id)
+}
+
+#[tauri::command]
+pub async fn list_jobs(state: State<'_, JobManager>) -> Result<Vec<DownloadJob>, String> {
+    Ok(state.list())
+}
+
+/// Wait for a queued job to finish
+#[tauri::command]
+pub async fn wait_for_job(state: State<'_, JobManager>, id: u64) -> Result<DownloadReport, String> {
+    state.wait(id).await?.into_result()
+}
+
+#[tauri::command]
+pub async fn cancel_job(app: AppHandle, id: u64) -> Result<(), String> {
+    cancel_queued_job(&app, id).await
+}
+
+/// Cancel a queued or running job, stopping its rclone job
+pub async fn cancel_queued_job(host: &impl JobHost, id: u64) -> Result<(), String> {
+    let rclone_jobid = host.job_manager().update(host, |state| {
+        let job = state.queue.get_mut(id)?;
+        if job.status.is_finished() {
+            return Err(format!("Job {} has already finished", id));
+        }
+
+        let rclone_jobid = job
+            .rclone_jobid
+            .filter(|_| job.status == JobStatus::Running);
+        job.status = JobStatus::Cancelled;
+        job.finished_at = Some(chrono::Local::now().to_rfc3339());
+
+        let settled = job.clone();
+        state.notify(&settled);
+        Ok(rclone_jobid)
+    })?;
+
+    stop_running(host, rclone_jobid).await?;
+    dispatch_jobs(host);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn pause_job(
+    app: AppHandle,
+    state: State<'_, JobManager>,
+    id: u64,
+) -> Result<(), String> {
+    let rclone_jobid = state.update(&app, |state| {
+        let job = state.queue.get_mut(id)?;
+        if !matches!(
+            job.status,
+            JobStatus::Queued | JobStatus::Running | JobStatus::Interrupted
+        ) {
+            return Err(format!("Job {} cannot be paused", id));
+        }
+
+        let rclone_jobid = job
+            .rclone_jobid
+            .filter(|_| job.status == JobStatus::Running);
+        job.status = JobStatus::Paused;
+        Ok(rclone_jobid)
+    })?;
+
+    stop_running(&app, rclone_jobid).await?;
+    dispatch_jobs(&app);
+    Ok(())
+}
+
+/// Put a paused, interrupted or failed job back in the queue
+#[tauri::command]
+pub async fn resume_job(
+    app: AppHandle,
+    state: State<'_, JobManager>,
+    id: u64,
+) -> Result<(), String> {
+    state.update(&app, |state| {
+        let job = state.queue.get_mut(id)?;
+        if !matches!(
+            job.status,
+            JobStatus::Paused | JobStatus::Interrupted | JobStatus::Failed
+        ) {
+            return Err(format!("Job {} cannot be resumed", id));
+        }
+
+        job.status = JobStatus::Queued;
+        job.finished_at = None;
+        Ok(())
+    })?;
+
+    dispatch_jobs(&app);
+    Ok(())
+}
+
+/// Move a job to a new position in the queue
+#[tauri::command]
+pub async fn reorder_job(
+    app: AppHandle,
+    state: State<'_, JobManager>,
+    id: u64,
+    position: usize,
+) -> Result<(), String> {
+    state.update(&app, |state| {
+        let jobs = &mut state.queue.jobs;
+        let index = jobs
+            .iter()
+            .position(|job| job.id == id)
+            .ok_or(format!("Job {} not found", id))?;
+
+        let job = jobs.remove(index);
+        jobs.insert(position.min(jobs.len()), job);
+        Ok(())
+    })
+}
+
+/// Set how many jobs may run at the same time
+#[tauri::command]
+pub async fn set_job_concurrency(
+    app: AppHandle,
+    state: State<'_, JobManager>,
+    concurrency: usize,
+) -> Result<(), String> {
+    if concurrency == 0 {
+        return Err("Concurrency must be at least 1".to_string());
+    }
+
+    state.update(&app, |state| state.queue.concurrency = concurrency);
+    dispatch_jobs(&app);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_job_concurrency(state: State<'_, JobManager>) -> Result<usize, String> {
+    Ok(state.lock().queue.concurrency)
+}
+
+/// Set whether jobs interrupted by rclone crashing are run again after it restarts
+#[tauri::command]
+pub async fn set_resume_after_crash(
+    app: AppHandle,
+    state: State<'_, JobManager>,
+    enabled: bool,
+) -> Result<(), String> {
+    state.update(&app, |state| state.queue.resume_after_crash = enabled);
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_resume_after_crash(state: State<'_, JobManager>) -> Result<bool, String> {
+    Ok(state.lock().queue.resume_after_crash)
+}
+
+/// Set the bandwidth limit of jobs without their own, `None` for no limit
+#[tauri::command]
+pub async fn set_global_bwlimit(
+    app: AppHandle,
+    state: State<'_, JobManager>,
+    limit: Option<String>,
+) -> Result<(), String> {
+    let limit = limit.filter(|limit| !limit.trim().is_empty());
+    if let Some(limit) = &limit {
+        validate_bwlimit(limit)?;
+    }
+
+    state.update(&app, |state| state.queue.bw_limit = limit);
+    bandwidth::refresh_bwlimit(&app).await;
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_global_bwlimit(state: State<'_, JobManager>) -> Result<Option<String>, String> {
+    Ok(state.lock().queue.bw_limit.clone())
+}
+
+/// Change the bandwidth limit of a job, applying it right away if the job is running
+#[tauri::command]
+pub async fn set_job_bwlimit(
+    app: AppHandle,
+    state: State<'_, JobManager>,
+    id: u64,
+    limit: Option<String>,
+) -> Result<(), String> {
+    let limit = limit.filter(|limit| !limit.trim().is_empty());
+    if let Some(limit) = &limit {
+        validate_bwlimit(limit)?;
+    }
+
+    let running = state.update(&app, |state| {
+        let job = state.queue.get_mut(id)?;
+        if job.status.is_finished() {
+            return Err(format!("Job {} has already finished", id));
+        }
+
+        job.config.bw_limit = limit;
+        Ok(job.status == JobStatus::Running)
+    })?;
+
+    if running {
+        bandwidth::refresh_bwlimit(&app).await;
+    }
+    Ok(())
+}
@@ -1,15 +1,166 @@
-use super::types::GdriveFile;
+use super::listing_cache::{read_cached_listing, read_dir_index, refresh_listing, spawn_refresh};
+use super::types::{GdriveDirEntry, GdriveFile};
 use super::utils::parse_gdrive_id;
-use crate::api::rclone;
+use crate::service::RcClientFactory;
+use std::collections::HashMap;
 
+/// List every file and folder under a Drive folder, folders first.
+/// Returns the cached listing right away when there is one, refreshing it in the
+/// background if it's older than a few minutes, or listing it all again if `refresh` is set.
 #[tauri::command]
 pub async fn list_gdrive_files(
     app: tauri::AppHandle,
     source: String,
     remote_config: String,
+    refresh: Option<bool>,
 ) -> Result<Vec<GdriveFile>, String> {
-    let client = rclone::get_sdk_client(&app).await?;
-    let root_id = parse_gdrive_id(&source);
+    match read_cached_listing(&app, &source, &remote_config) {
+        Some(listing) => {
+            let refresh = refresh.unwrap_or(false);
+            if refresh || listing.is_stale() {
+                spawn_refresh(&app, source, remote_config, refresh);
+            }
+            Ok(listing.files)
+        }
+        None => Ok(refresh_listing(&app, &source, &remote_config, true)
+            .await?
+            .files),
+    }
+}
+
+/// List the files and folders directly inside `path` of a Drive folder, folders first.
+/// Folder sizes and counts come from the cached listing. Without one, only this folder
+/// is listed without them, and the whole Drive folder is listed in the background. Its
+/// `gdrive-listing-updated` event tells the frontend to ask again for the totals.
+#[tauri::command]
+pub async fn list_gdrive_dir(
+    app: tauri::AppHandle,
+    source: String,
+    remote_config: String,
+    path: Option<String>,
+) -> Result<Vec<GdriveDirEntry>, String> {
+    let path = path.unwrap_or_default();
+    let path = path.trim_matches('/');
+    match read_dir_index(&app, &source, &remote_config) {
+        Some(index) => {
+            if index.is_stale() {
+                spawn_refresh(&app, source.clone(), remote_config.clone(), false);
+            }
+            Ok(index.entries(path))
+        }
+        None => {
+            let entries = list_dir(&app, &source, &remote_config, path).await?;
+            spawn_refresh(&app, source, remote_config, true);
+            Ok(entries)
+        }
+    }
+}
+
+/// List the files and folders directly inside `path` of a Drive folder through rclone,
+/// without folder sizes and counts
+pub async fn list_dir(
+    rc: &impl RcClientFactory,
+    source: &str,
+    remote_config: &str,
+    path: &str,
+) -> Result<Vec<GdriveDirEntry>, String> {
+    let client = rc.rc_client().await?;
+    let root_id = parse_gdrive_id(source);
+    let fs_str = format!("{},root_folder_id={}:", remote_config, root_id);
+
+    let result = client
+        .operations_list(
+            None, None, None, None, &fs_str, // fs
+            None, None, None, None, None, None, path, // remote
+            None, None, None,
+        )
+        .await
+        .map_err(|e| format!("List failed: {}", e))?;
+
+    let mut entries: Vec<GdriveDirEntry> = result
+        .into_inner()
+        .list
+        .into_iter()
+        .map(|item| GdriveDirEntry {
+            path: item.path,
+            name: item.name,
+            is_dir: item.is_dir,
+            size: (!item.is_dir).then(|| item.size.map(|s| s as i64).unwrap_or(0)),
+            mime_type: item.mime_type.unwrap_or_default(),
+            child_count: None,
+            file_count: None,
+        })
+        .collect();
+    sort_entries(&mut entries);
+
+    Ok(entries)
+}
+
+/// The files and folders directly inside every folder of a listing, by folder path with
+/// the root as `""`. Folder totals are summed up from everything under them.
+pub fn dir_index(files: &[GdriveFile]) -> HashMap<String, Vec<GdriveDirEntry>> {
+    // Size, files and folders directly inside, and files anywhere inside each folder
+    let mut totals: HashMap<&str, (i64, usize, usize)> = HashMap::new();
+    for file in files {
+        let mut path = file.path.as_str();
+        let mut direct = true;
+        while let Some((parent, _)) = path.rsplit_once('/') {
+            let total = totals.entry(parent).or_default();
+            if direct {
+                total.1 += 1;
+                direct = false;
+            }
+            if !file.is_dir {
+                // Google Docs have no size
+                total.0 += file.size.max(0);
+                total.2 += 1;
+            }
+            path = parent;
+        }
+    }
+
+    let mut index: HashMap<String, Vec<GdriveDirEntry>> = HashMap::new();
+    for file in files {
+        let parent = file.path.rsplit_once('/').map_or("", |(parent, _)| parent);
+        let (size, child_count, file_count) = if file.is_dir {
+            let &(size, children, files) = totals.get(file.path.as_str()).unwrap_or(&(0, 0, 0));
+            (size, Some(children), Some(files))
+        } else {
+            (file.size, None, None)
+        };
+        index
+            .entry(parent.to_string())
+            .or_default()
+            .push(GdriveDirEntry {
+                path: file.path.clone(),
+                name: file.name.clone(),
+                is_dir: file.is_dir,
+                size: Some(size),
+                mime_type: file.mime_type.clone(),
+                child_count,
+                file_count,
+            });
+    }
+
+    for entries in index.values_mut() {
+        sort_entries(entries);
+    }
+    index
+}
+
+/// Sort folders first, then alphabetically
+fn sort_entries(entries: &mut [GdriveDirEntry]) {
+    entries.sort_by_cached_key(|entry| (!entry.is_dir, entry.name.to_lowercase()));
+}
+
+/// List every file and folder under a Drive folder, folders first
+pub async fn list_files(
+    rc: &impl RcClientFactory,
+    source: &str,
+    remote_config: &str,
+) -> Result<Vec<GdriveFile>, String> {
+    let client = rc.rc_client().await?;
+    let root_id = parse_gdrive_id(source);
 
     // Construct fs pointing to the root of the share/folder
     let fs_str = format!("{},root_folder_id={}:", remote_config, root_id);
@@ -53,6 +204,7 @@ pub async fn list_gdrive_files(
                 is_dir: item.is_dir,
                 size: item.size.map(|s| s as i64).unwrap_or(0),
                 mime_type: item.mime_type.unwrap_or_default(),
+                id: item.id,
             });
         }
     }
@@ -74,6 +226,7 @@ pub async fn list_gdrive_files(
                     is_dir: true,
                     size: 0,
                     mime_type: "inode/directory".to_string(),
+                    id: None,
                 });
                 added_paths.insert(parent_path);
             }
@@ -0,0 +1,521 @@
+use super::list::{dir_index, list_files};
+use super::types::{GdriveDirEntry, GdriveFile};
+use super::utils::parse_gdrive_id;
+use crate::service::{Host, PathProvider, RcClientFactory};
+use crate::utils::{app_data_file, read_json_file, write_json_file};
+use rclone_sdk::ClientInfo;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::path::PathBuf;
+use std::sync::{Arc, LazyLock, Mutex};
+use tauri::{AppHandle, Emitter};
+
+/// Folder in the app data directory holding one listing per remote and root folder
+const LISTING_CACHE_DIR: &str = "listings";
+/// Cached listings younger than this are returned without refreshing them
+const REFRESH_INTERVAL_SECS: i64 = 10 * 60;
+/// Permanently deleted files and files moved out of the folder don't show up as changes,
+/// so the whole folder is listed again this often
+const FULL_LISTING_INTERVAL_SECS: i64 = 24 * 60 * 60;
+/// Changes are queried from this long before the last refresh, in case the clocks differ
+const CHANGES_LEEWAY_SECS: i64 = 5 * 60;
+/// Folders whose contents one Drive query asks for, keeping the query short
+const QUERY_FOLDER_BATCH: usize = 50;
+const FOLDER_MIME_TYPE: &str = "application/vnd.google-apps.folder";
+
+/// Listings being refreshed in the background, by cache key
+static REFRESHING: LazyLock<Mutex<HashSet<String>>> = LazyLock::new(|| Mutex::new(HashSet::new()));
+
+/// Folder contents of the cached listings read so far, by cache file, so browsing
+/// doesn't read and go through the whole listing for every folder
+static DIR_INDEXES: LazyLock<Mutex<HashMap<PathBuf, Arc<DirIndex>>>> =
+    LazyLock::new(|| Mutex::new(HashMap::new()));
+
+/// Listing of a Drive folder saved on disk
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedListing {
+    pub remote_config: String,
+    pub root_id: String,
+    pub fetched_at: String,
+    /// When the whole folder was last listed, rather than only what changed
+    #[serde(default)]
+    pub fully_listed_at: Option<String>,
+    pub files: Vec<GdriveFile>,
+}
+
+impl CachedListing {
+    /// Whether it's old enough to be refreshed in the background
+    pub fn is_stale(&self) -> bool {
+        seconds_since(&self.fetched_at).is_none_or(|age| age >= REFRESH_INTERVAL_SECS)
+    }
+
+    /// Whether changes can't be applied to it, because it's too old to know what
+    /// was deleted or was cached without Drive IDs
+    fn needs_full_listing(&self) -> bool {
+        let age = self.fully_listed_at.as_deref().and_then(seconds_since);
+        age.is_none_or(|age| age >= FULL_LISTING_INTERVAL_SECS)
+            || self.files.iter().all(|file| file.id.is_none())
+    }
+}
+
+/// Contents of every folder in a cached listing
+pub(super) struct DirIndex {
+    fetched_at: String,
+    dirs: HashMap<String, Vec<GdriveDirEntry>>,
+}
+
+impl DirIndex {
+    fn new(listing: &CachedListing) -> Self {
+        Self {
+            fetched_at: listing.fetched_at.clone(),
+            dirs: dir_index(&listing.files),
+        }
+    }
+
+    /// Whether the listing it was made from is old enough to be refreshed
+    pub fn is_stale(&self) -> bool {
+        seconds_since(&self.fetched_at).is_none_or(|age| age >= REFRESH_INTERVAL_SECS)
+    }
+
+    /// The files and folders directly inside `path`
+    pub fn entries(&self, path: &str) -> Vec<GdriveDirEntry> {
+        self.dirs.get(path).cloned().unwrap_or_default()
+    }
+}
+
+fn seconds_since(time: &str) -> Option<i64> {
+    chrono::DateTime::parse_from_rfc3339(time)
+        .ok()
+        .map(|time| (chrono::Local::now().fixed_offset() - time).num_seconds())
+}
+
+/// File or folder created, changed or trashed on the Drive, from the `query` backend command
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DriveChange {
+    id: String,
+    name: String,
+    #[serde(default)]
+    mime_type: String,
+    #[serde(default)]
+    parents: Vec<String>,
+    /// Sent as a string, missing for folders and Google Docs
+    size: Option<String>,
+    #[serde(default)]
+    trashed: bool,
+}
+
+/// What a refresh found changed since the cached listing, sent with
+/// `gdrive-listing-updated` events
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ListingUpdate {
+    /// Source as given by the frontend, to tell which listing was refreshed
+    pub source: String,
+    pub remote_config: String,
+    pub root_id: String,
+    pub added: Vec<GdriveFile>,
+    pub changed: Vec<GdriveFile>,
+    pub removed: Vec<String>,
+    /// The whole refreshed listing
+    pub files: Vec<GdriveFile>,
+}
+
+impl ListingUpdate {
+    pub fn has_changes(&self) -> bool {
+        !self.added.is_empty() || !self.changed.is_empty() || !self.removed.is_empty()
+    }
+}
+
+fn cache_key(remote_config: &str, root_id: &str) -> String {
+    format!("{}_{}", remote_config, root_id)
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+fn cache_path(
+    paths: &impl PathProvider,
+    remote_config: &str,
+    root_id: &str,
+) -> Result<PathBuf, String> {
+    app_data_file(paths, LISTING_CACHE_DIR)
+        .map(|dir| dir.join(format!("{}.json", cache_key(remote_config, root_id))))
+}
+
+/// The cached listing of a Drive folder, if it was listed before
+pub fn read_cached_listing(
+    paths: &impl PathProvider,
+    source: &str,
+    remote_config: &str,
+) -> Option<CachedListing> {
+    let root_id = parse_gdrive_id(source);
+    cache_path(paths, remote_config, &root_id)
+        .and_then(|path| read_json_file::<CachedListing>(&path))
+        .inspect_err(|e| log::warn!("Failed to read cached listing: {}", e))
+        .ok()
+        .flatten()
+        // A listing cached for another remote with a clashing file name
+        .filter(|listing| listing.remote_config == remote_config && listing.root_id == root_id)
+}
+
+/// Contents of every folder in the cached listing of a Drive folder, read from disk
+/// only the first time
+pub(super) fn read_dir_index(
+    paths: &impl PathProvider,
+    source: &str,
+    remote_config: &str,
+) -> Option<Arc<DirIndex>> {
+    let path = cache_path(paths, remote_config, &parse_gdrive_id(source)).ok()?;
+    let cached = DIR_INDEXES
+        .lock()
+        .ok()
+        .and_then(|indexes| indexes.get(&path).cloned());
+    if cached.is_some() {
+        return cached;
+    }
+
+    let index = Arc::new(DirIndex::new(&read_cached_listing(
+        paths,
+        source,
+        remote_config,
+    )?));
+    // A refresh finishing in the meantime has put in a newer one
+    let mut indexes = DIR_INDEXES.lock().ok()?;
+    Some(indexes.entry(path).or_insert(index).clone())
+}
+
+/// Update the cached listing of a Drive folder and return what changed. Only what changed
+/// on the Drive since the last refresh is fetched, unless `full` is set or the whole folder
+/// wasn't listed for a day.
+pub async fn refresh_listing(
+    host: &impl Host,
+    source: &str,
+    remote_config: &str,
+    full: bool,
+) -> Result<ListingUpdate, String> {
+    let root_id = parse_gdrive_id(source);
+    let cached = read_cached_listing(host, source, remote_config);
+    let fetched_at = chrono::Local::now().to_rfc3339();
+
+    let incremental = match &cached {
+        Some(listing) if !full && !listing.needs_full_listing() => {
+            let folders = listing
+                .files
+                .iter()
+                .filter(|file| file.is_dir)
+                .filter_map(|file| file.id.clone())
+                .chain([root_id.clone()])
+                .collect();
+            query_changes(host, remote_config, &root_id, folders, &listing.fetched_at)
+                .await
+                .inspect_err(|e| log::warn!("Listing all of {} instead: {}", source, e))
+                .ok()
+                .map(|changes| {
+                    let files = apply_changes(listing.files.clone(), &root_id, changes);
+                    (files, listing.fully_listed_at.clone())
+                })
+        }
+        _ => None,
+    };
+    let (files, fully_listed_at) = match incremental {
+        Some(incremental) => incremental,
+        None => (
+            list_files(host, source, remote_config).await?,
+            Some(fetched_at.clone()),
+        ),
+    };
+
+    let listing = CachedListing {
+        remote_config: remote_config.to_string(),
+        root_id: root_id.clone(),
+        fetched_at,
+        fully_listed_at,
+        files,
+    };
+    let path = cache_path(host, remote_config, &root_id)?;
+    write_json_file(&path, &listing)?;
+    if let Ok(mut indexes) = DIR_INDEXES.lock() {
+        indexes.insert(path, Arc::new(DirIndex::new(&listing)));
+    }
+
+    let mut previous: HashMap<String, GdriveFile> = cached
+        .map(|listing| listing.files)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|file| (file.path.clone(), file))
+        .collect();
+    let mut added = Vec::new();
+    let mut changed = Vec::new();
+    for file in &listing.files {
+        match previous.remove(&file.path) {
+            None => added.push(file.clone()),
+            Some(old) if old != *file => changed.push(file.clone()),
+            Some(_) => {}
+        }
+    }
+    let mut removed: Vec<String> = previous.into_keys().collect();
+    removed.sort();
+
+    Ok(ListingUpdate {
+        source: source.to_string(),
+        remote_config: listing.remote_config,
+        root_id: listing.root_id,
+        added,
+        changed,
+        removed,
+        files: listing.files,
+    })
+}
+
+/// What was created, changed or trashed since `since` directly inside `folders`, and inside
+/// the folders created since then
+async fn query_changes(
+    rc: &impl RcClientFactory,
+    remote_config: &str,
+    root_id: &str,
+    folders: Vec<String>,
+    since: &str,
+) -> Result<Vec<DriveChange>, String> {
+    let since = chrono::DateTime::parse_from_rfc3339(since)
+        .map_err(|e| format!("Invalid refresh time {}: {}", since, e))?
+        - chrono::Duration::seconds(CHANGES_LEEWAY_SECS);
+    let since = since
+        .with_timezone(&chrono::Utc)
+        .format("%Y-%m-%dT%H:%M:%S")
+        .to_string();
+
+    let client = rc.rc_client().await?;
+    let fs = format!("{},root_folder_id={}:", remote_config, root_id);
+    let mut queried: HashSet<String> = folders.iter().cloned().collect();
+    let mut pending = folders;
+    let mut changes: Vec<DriveChange> = Vec::new();
+    let mut seen = HashSet::new();
+    while !pending.is_empty() {
+        let batch: Vec<String> = pending
+            .drain(..pending.len().min(QUERY_FOLDER_BATCH))
+            .collect();
+        for change in query_drive(&client, &fs, &change_query(&batch, &since)).await? {
+            // Everything in a new folder is new too. Its parent may only turn up
+            // later in the results, then it's found again when that is queried.
+            if change.mime_type == FOLDER_MIME_TYPE
+                && !change.trashed
+                && change.parents.iter().any(|parent| queried.contains(parent))
+                && queried.insert(change.id.clone())
+            {
+                pending.push(change.id.clone());
+            }
+            if seen.insert(change.id.clone()) {
+                changes.push(change);
+            }
+        }
+    }
+    Ok(changes)
+}
+
+/// Drive search for what changed directly inside `folders`. Trashed files keep their
+/// parents but not always a newer time, so they're asked for regardless.
+fn change_query(folders: &[String], since: &str) -> String {
+    let parents: Vec<String> = folders
+        .iter()
+        .map(|id| {
+            format!(
+                "'{}' in parents",
+                id.replace('\\', "\\\\").replace('\'', "\\'")
+            )
+        })
+        .collect();
+    format!(
+        "({}) and (modifiedTime > '{1}' or createdTime > '{1}' or trashed = true)",
+        parents.join(" or "),
+        since
+    )
+}
+
+/// Run the `query` backend command of the Drive remote
+async fn query_drive(
+    client: &rclone_sdk::Client,
+    fs: &str,
+    query: &str,
+) -> Result<Vec<DriveChange>, String> {
+    let response = client
+        .client()
+        .post(format!("{}/backend/command", client.baseurl()))
+        .json(&serde_json::json!({
+            "command": "query",
+            "fs": fs,
+            "arg": [query]
+        }))
+        .send()
+        .await
+        .map_err(|e| format!("Drive query failed: {}", e))?;
+    if !response.status().is_success() {
+        let err_text = response.text().await.unwrap_or_default();
+        return Err(format!("Drive query failed: {}", err_text));
+    }
+
+    let mut body = response
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("Failed to parse Drive query response: {}", e))?;
+    serde_json::from_value(body["result"].take())
+        .map_err(|e| format!("Failed to parse Drive query response: {}", e))
+}
+
+/// Apply changes from the Drive to a listing, placing each file under its parent folder
+fn apply_changes(
+    mut files: Vec<GdriveFile>,
+    root_id: &str,
+    mut changes: Vec<DriveChange>,
+) -> Vec<GdriveFile> {
+    let mut paths: HashMap<String, String> = files
+        .iter()
+        .filter_map(|file| Some((file.id.clone()?, file.path.clone())))
+        .collect();
+    paths.insert(root_id.to_string(), String::new());
+
+    // A change can be inside a folder that is itself new, so keep going until nothing fits
+    loop {
+        let pending = changes.len();
+        changes.retain(|change| {
+            let parent = change.parents.iter().find_map(|parent| paths.get(parent));
+            match parent.cloned() {
+                Some(parent) => {
+                    apply_change(&mut files, &mut paths, &parent, change);
+                    false
+                }
+                None => true,
+            }
+        });
+        if changes.is_empty() || changes.len() == pending {
+            break;
+        }
+    }
+
+    // The rest isn't under a folder of the listing, some of it maybe moved out of it
+    for change in changes {
+        if let Some(path) = paths.get(&change.id).cloned() {
+            remove_tree(&mut files, &mut paths, &path);
+        }
+    }
+    files
+}
+
+fn apply_change(
+    files: &mut Vec<GdriveFile>,
+    paths: &mut HashMap<String, String>,
+    parent: &str,
+    change: &DriveChange,
+) {
+    let is_dir = change.mime_type == FOLDER_MIME_TYPE;
+    // rclone lists Google Docs under the name of the format they're exported as
+    if !is_dir && change.mime_type.starts_with("application/vnd.google-apps.") && !change.trashed {
+        return;
+    }
+
+    // Drive allows slashes in names, rclone shows them as full-width ones
+    let name = change.name.replace('/', "／");
+    let path = if parent.is_empty() {
+        name.clone()
+    } else {
+        format!("{}/{}", parent, name)
+    };
+
+    // Renamed, moved or trashed
+    if let Some(old_path) = paths.get(&change.id).cloned()
+        && (change.trashed || old_path != path)
+    {
+        let moved = remove_tree(files, paths, &old_path);
+        if change.trashed {
+            return;
+        }
+        for mut file in moved {
+            if let Some(rest) = file.path.strip_prefix(&format!("{}/", old_path)) {
+                file.path = format!("{}/{}", path, rest);
+                if let Some(id) = &file.id {
+                    paths.insert(id.clone(), file.path.clone());
+                }
+                files.push(file);
+            }
+        }
+    }
+    if change.trashed {
+        return;
+    }
+
+    let file = GdriveFile {
+        path: path.clone(),
+        name,
+        is_dir,
+        // Like rclone, -1 when the size is unknown
+        size: change
+            .size
+            .as_deref()
+            .and_then(|size| size.parse().ok())
+            .unwrap_or(-1),
+        mime_type: if is_dir {
+            "inode/directory".to_string()
+        } else {
+            change.mime_type.clone()
+        },
+        id: Some(change.id.clone()),
+    };
+    match files.iter_mut().find(|existing| existing.path == path) {
+        Some(existing) => *existing = file,
+        None => files.push(file),
+    }
+    paths.insert(change.id.clone(), path);
+}
+
+/// Remove `path` and everything inside it from a listing, returning what was removed
+fn remove_tree(
+    files: &mut Vec<GdriveFile>,
+    paths: &mut HashMap<String, String>,
+    path: &str,
+) -> Vec<GdriveFile> {
+    let prefix = format!("{}/", path);
+    let (removed, kept) = std::mem::take(files)
+        .into_iter()
+        .partition(|file| file.path == path || file.path.starts_with(&prefix));
+    *files = kept;
+    for file in &removed {
+        if let Some(id) = &file.id {
+            paths.remove(id);
+        }
+    }
+    removed
+}
+
+/// Refresh a listing without waiting for it, emitting `gdrive-listing-updated` if it changed.
+/// With `full` the whole folder is listed again instead of asking for changes.
+pub(super) fn spawn_refresh(app: &AppHandle, source: String, remote_config: String, full: bool) {
+    let key = cache_key(&remote_config, &parse_gdrive_id(&source));
+    let started = REFRESHING
+        .lock()
+        .map(|mut refreshing| refreshing.insert(key.clone()))
+        .unwrap_or(false);
+    if !started {
+        return;
+    }
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        match refresh_listing(&app, &source, &remote_config, full).await {
+            Ok(update) if update.has_changes() => {
+                let _ = app.emit("gdrive-listing-updated", &update);
+            }
+            Ok(_) => {}
+            Err(e) => log::warn!("Failed to refresh listing of {}: {}", source, e),
+        }
+        if let Ok(mut refreshing) = REFRESHING.lock() {
+            refreshing.remove(&key);
+        }
+    });
+}
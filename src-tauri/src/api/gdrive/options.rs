@@ -0,0 +1,154 @@
+use crate::utils::parse_size;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// How rclone decides whether a file changed
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum CompareMode {
+    /// Size and modification time, rclone's default
+    #[default]
+    ModTime,
+    /// Size and MD5, slower as every local file has to be hashed
+    Checksum,
+    /// Size only, for destinations without reliable modification times
+    SizeOnly,
+}
+
+/// Transfer tuning passed to rclone with each download
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct TransferOptions {
+    /// Files transferred in parallel, `--transfers`
+    pub transfers: u32,
+    /// Files checked in parallel, `--checkers`
+    pub checkers: u32,
+    /// Streams used to download a single large file, `--multi-thread-streams`
+    pub multi_thread_streams: u32,
+    /// Size above which files are downloaded with several streams, `--multi-thread-cutoff`
+    pub multi_thread_cutoff: String,
+    /// Retries of the whole sync when some files failed, `--retries`
+    pub retries: u32,
+    /// Retries of single operations, `--low-level-retries`
+    pub low_level_retries: u32,
+    /// Stop starting new transfers after this long, e.g. `2h30m`, `--max-duration`
+    pub max_duration: Option<String>,
+    pub compare: CompareMode,
+}
+
+/// Suited to an archive of many mp3/flac files of a few to a few hundred MB
+impl Default for TransferOptions {
+    fn default() -> Self {
+        Self {
+            transfers: 8,
+            checkers: 16,
+            multi_thread_streams: 4,
+            multi_thread_cutoff: "64M".to_string(),
+            retries: 3,
+            low_level_retries: 10,
+            max_duration: None,
+            compare: CompareMode::ModTime,
+        }
+    }
+}
+
+impl TransferOptions {
+    /// Check the options are in ranges rclone and Drive accept
+    pub fn validate(&self) -> Result<(), String> {
+        check_range("Transfers", self.transfers, 1, 64)?;
+        check_range("Checkers", self.checkers, 1, 128)?;
+        check_range("Multi-thread streams", self.multi_thread_streams, 0, 32)?;
+        check_range("Retries", self.retries, 1, 100)?;
+        check_range("Low level retries", self.low_level_retries, 1, 1000)?;
+
+        parse_size(&self.multi_thread_cutoff).ok_or(format!(
+            "Invalid multi-thread cutoff '{}', expected e.g. 64M",
+            self.multi_thread_cutoff
+        ))?;
+
+        if let Some(max_duration) = &self.max_duration {
+            parse_duration(max_duration).ok_or(format!(
+                "Invalid max duration '{}', expected e.g. 2h30m",
+                max_duration
+            ))?;
+        }
+
+        Ok(())
+    }
+
+    /// Add the global options to the `_config` of a sync request
+    pub(super) fn apply_to_config(&self, config: &mut serde_json::Map<String, serde_json::Value>) {
+        config.insert("Transfers".to_string(), self.transfers.into());
+        config.insert("Checkers".to_string(), self.checkers.into());
+        config.insert(
+            "MultiThreadStreams".to_string(),
+            self.multi_thread_streams.into(),
+        );
+        if let Some(cutoff) = parse_size(&self.multi_thread_cutoff) {
+            config.insert("MultiThreadCutoff".to_string(), cutoff.into());
+        }
+        config.insert("Retries".to_string(), self.retries.into());
+        config.insert("LowLevelRetries".to_string(), self.low_level_retries.into());
+
+        // rclone takes durations in nanoseconds
+        if let Some(duration) = self.max_duration.as_deref().and_then(parse_duration) {
+            config.insert(
+                "MaxDuration".to_string(),
+                (duration.as_nanos() as u64).into(),
+            );
+        }
+
+        match self.compare {
+            CompareMode::ModTime => {}
+            CompareMode::Checksum => {
+                config.insert("CheckSum".to_string(), true.into());
+            }
+            CompareMode::SizeOnly => {
+                config.insert("SizeOnly".to_string(), true.into());
+            }
+        }
+    }
+}
+
+fn check_range(name: &str, value: u32, min: u32, max: u32) -> Result<(), String> {
+    if value < min || value > max {
+        return Err(format!(
+            "{} must be between {} and {}, got {}",
+            name, min, max, value
+        ));
+    }
+    Ok(())
+}
+
+/// Parse a duration like `2h30m`, `90m` or `1d`
+fn parse_duration(duration: &str) -> Option<Duration> {
+    let mut rest = duration.trim();
+    if rest.is_empty() {
+        return None;
+    }
+
+    let mut total = Duration::ZERO;
+    while !rest.is_empty() {
+        let split = rest.find(|c: char| !c.is_ascii_digit() && c != '.')?;
+        let (number, tail) = rest.split_at(split);
+        let number: f64 = number.parse().ok()?;
+
+        let unit_len = tail
+            .find(|c: char| c.is_ascii_digit())
+            .unwrap_or(tail.len());
+        let (unit, tail) = tail.split_at(unit_len);
+        let seconds = match unit {
+            "ms" => 0.001,
+            "s" => 1.0,
+            "m" => 60.0,
+            "h" => 3600.0,
+            "d" => 86400.0,
+            _ => return None,
+        };
+
+        total += Duration::try_from_secs_f64(number * seconds).ok()?;
+        rest = tail;
+    }
+
+    (!total.is_zero()).then_some(total)
+}
@@ -0,0 +1,104 @@
+use crate::api::rclone::{FileChanges, RenamedFile, SkippedAction};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// A file a sync would act on
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedFile {
+    pub path: String,
+    pub size: Option<u64>,
+}
+
+/// A file a sync would rename instead of downloading again
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlannedRename {
+    pub from: String,
+    /// New name, when rclone reports it
+    pub to: Option<String>,
+    pub size: Option<u64>,
+}
+
+/// Everything a sync would do, as found by a dry run
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncPlan {
+    pub new_files: Vec<PlannedFile>,
+    pub changed_files: Vec<PlannedFile>,
+    pub renamed_files: Vec<PlannedRename>,
+    pub deleted_files: Vec<PlannedFile>,
+    /// Files that would be moved to the backup directory instead of being lost
+    pub backed_up_files: Vec<PlannedFile>,
+    pub bytes_to_transfer: u64,
+}
+
+impl SyncPlan {
+    /// Build the plan from the actions skipped by a dry run into `dst_path`
+    pub fn from_skipped_actions(
+        actions: Vec<SkippedAction>,
+        dst_path: &Path,
+        create_backup: bool,
+    ) -> Self {
+        let mut plan = Self::default();
+
+        for action in actions {
+            let file = PlannedFile {
+                path: action.path,
+                size: action.size,
+            };
+
+            match action.action.as_str() {
+                "copy" => {
+                    plan.bytes_to_transfer += file.size.unwrap_or(0);
+                    // The destination is local, so an existing file means an overwrite
+                    if dst_path.join(&file.path).exists() {
+                        plan.changed_files.push(file);
+                    } else {
+                        plan.new_files.push(file);
+                    }
+                }
+                "delete" => plan.deleted_files.push(file),
+                action if action.contains("move") => plan.renamed_files.push(PlannedRename {
+                    to: action
+                        .split_once(" to ")
+                        .map(|(_, to)| to.trim_start_matches(':').trim().to_string()),
+                    from: file.path,
+                    size: file.size,
+                }),
+                _ => {}
+            }
+        }
+
+        if create_backup {
+            plan.backed_up_files = plan
+                .changed_files
+                .iter()
+                .chain(&plan.deleted_files)
+                .cloned()
+                .collect();
+        }
+
+        plan
+    }
+
+    /// Describe the plan as file changes for the history
+    pub fn to_changes(&self) -> FileChanges {
+        let paths = |files: &[PlannedFile]| files.iter().map(|f| f.path.clone()).collect();
+
+        FileChanges {
+            added: paths(&self.new_files),
+            updated: paths(&self.changed_files),
+            deleted: paths(&self.deleted_files),
+            renamed: self
+                .renamed_files
+                .iter()
+                .map(|r| RenamedFile {
+                    from: r.from.clone(),
+                    to: r.to.clone().unwrap_or_default(),
+                })
+                .collect(),
+            errors: Vec::new(),
+        }
+    }
+}
@@ -0,0 +1,354 @@
+use super::download::DownloadConfig;
+use super::remotes::list_drive_remotes;
+use crate::service::{PathProvider, RcClientFactory};
+use crate::utils::{app_data_file, read_json_file, write_json_file};
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, Emitter, State};
+
+const PROFILES_FILE_NAME: &str = "profiles.json";
+/// Version of the export format, bumped on breaking changes
+const EXPORT_VERSION: u32 = 1;
+
+/// A named download configuration, e.g. "Full archive to NAS"
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Profile {
+    pub id: u64,
+    pub name: String,
+    pub config: DownloadConfig,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+/// Whether a profile can still run on this machine
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProfileValidation {
+    pub destination_exists: bool,
+    pub remote_exists: bool,
+    pub errors: Vec<String>,
+}
+
+/// File written by `export_profiles`
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileExport {
+    version: u32,
+    profiles: Vec<ExportedProfile>,
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExportedProfile {
+    name: String,
+    config: DownloadConfig,
+}
+
+/// Profiles persisted between sessions
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ProfileList {
+    next_id: u64,
+    profiles: Vec<Profile>,
+}
+
+impl Default for ProfileList {
+    fn default() -> Self {
+        Self {
+            next_id: 1,
+            profiles: Vec::new(),
+        }
+    }
+}
+
+impl ProfileList {
+    fn get_mut(&mut self, id: u64) -> Result<&mut Profile, String> {
+        self.profiles
+            .iter_mut()
+            .find(|profile| profile.id == id)
+            .ok_or(format!("Profile {} not found", id))
+    }
+
+    /// Fail if another profile already uses the name
+    fn check_name(&self, name: &str, id: Option<u64>) -> Result<(), String> {
+        let taken = self
+            .profiles
+            .iter()
+            .any(|p| Some(p.id) != id && p.name.eq_ignore_ascii_case(name));
+        if taken {
+            return Err(format!("A profile named '{}' already exists", name));
+        }
+        Ok(())
+    }
+
+    /// Pick a free name for an imported profile, e.g. `Name (2)`
+    fn unique_name(&self, name: &str) -> String {
+        let mut candidate = name.to_string();
+        let mut suffix = 2;
+        while self.check_name(&candidate, None).is_err() {
+            candidate = format!("{} ({})", name, suffix);
+            suffix += 1;
+        }
+        candidate
+    }
+
+    fn add(&mut self, name: String, config: DownloadConfig) -> Profile {
+        let now = chrono::Local::now().to_rfc3339();
+        let profile = Profile {
+            id: self.next_id,
+            name,
+            config,
+            created_at: now.clone(),
+            updated_at: now,
+        };
+        self.next_id += 1;
+        self.profiles.push(profile.clone());
+        profile
+    }
+}
+
+/// Stores named download profiles
+#[derive(Default)]
+pub struct ProfileManager {
+    list: Mutex<ProfileList>,
+}
+
+impl ProfileManager {
+    pub fn load(paths: &impl PathProvider) -> Self {
+        let list = app_data_file(paths, PROFILES_FILE_NAME)
+            .and_then(|path| read_json_file(&path))
+            .inspect_err(|e| log::error!("Failed to load profiles: {}", e))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        Self {
+            list: Mutex::new(list),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ProfileList> {
+        self.list.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Apply a change to the profiles, then persist them and notify the frontend
+    fn update<R>(
+        &self,
+        app: &AppHandle,
+        f: impl FnOnce(&mut ProfileList) -> Result<R, String>,
+    ) -> Result<R, String> {
+        let mut list = self.lock();
+        let result = f(&mut list)?;
+
+        app_data_file(app, PROFILES_FILE_NAME).and_then(|path| write_json_file(&path, &*list))?;
+        let _ = app.emit("profiles-changed", &list.profiles);
+        Ok(result)
+    }
+
+    pub fn list(&self) -> Vec<Profile> {
+        self.lock().profiles.clone()
+    }
+
+    pub fn get(&self, id: u64) -> Option<Profile> {
+        self.lock().profiles.iter().find(|p| p.id == id).cloned()
+    }
+
+    /// Look up a profile by its name, ignoring case, or by its id
+    pub fn find(&self, name_or_id: &str) -> Option<Profile> {
+        let list = self.lock();
+        list.profiles
+            .iter()
+            .find(|p| p.name.eq_ignore_ascii_case(name_or_id.trim()))
+            .or_else(|| {
+                let id = name_or_id.trim().parse::<u64>().ok()?;
+                list.profiles.iter().find(|p| p.id == id)
+            })
+            .cloned()
+    }
+}
+
+fn validate_name(name: &str) -> Result<String, String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err("Profile name is required".to_string());
+    }
+    Ok(name.to_string())
+}
+
+#[tauri::command]
+pub async fn list_profiles(state: State<'_, ProfileManager>) -> Result<Vec<Profile>, String> {
+    Ok(state.list())
+}
+
+#[tauri::command]
+pub async fn get_profile(state: State<'_, ProfileManager>, id: u64) -> Result<Profile, String> {
+    state.get(id).ok_or(format!("Profile {} not found", id))
+}
+
+#[tauri::command]
+pub async fn create_profile(
+    app: AppHandle,
+    state: State<'_, ProfileManager>,
+    name: String,
+    config: DownloadConfig,
+) -> Result<Profile, String> {
+    let name = validate_name(&name)?;
+    config.validate()?;
+
+    state.update(&app, |list| {
+        list.check_name(&name, None)?;
+        Ok(list.add(name, config))
+    })
+}
+
+#[tauri::command]
+pub async fn update_profile(
+    app: AppHandle,
+    state: State<'_, ProfileManager>,
+    id: u64,
+    name: String,
+    config: DownloadConfig,
+) -> Result<Profile, String> {
+    let name = validate_name(&name)?;
+    config.validate()?;
+
+    state.update(&app, |list| {
+        list.check_name(&name, Some(id))?;
+        let profile = list.get_mut(id)?;
+        profile.name = name;
+        profile.config = config;
+        profile.updated_at = chrono::Local::now().to_rfc3339();
+        Ok(profile.clone())
+    })
+}
+
+#[tauri::command]
+pub async fn delete_profile(
+    app: AppHandle,
+    state: State<'_, ProfileManager>,
+    id: u64,
+) -> Result<(), String> {
+    state.update(&app, |list| {
+        let index = list
+            .profiles
+            .iter()
+            .position(|profile| profile.id == id)
+            .ok_or(format!("Profile {} not found", id))?;
+        list.profiles.remove(index);
+        Ok(())
+    })
+}
+
+/// Check that the destination folder and rclone remote of a profile still exist
+#[tauri::command]
+pub async fn validate_profile(
+    app: AppHandle,
+    state: State<'_, ProfileManager>,
+    id: u64,
+) -> Result<ProfileValidation, String> {
+    let profile = state.get(id).ok_or(format!("Profile {} not found", id))?;
+    Ok(check_profile(&app, &profile).await)
+}
+
+/// Validate a profile against the remotes configured in rclone
+pub async fn check_profile(rc: &impl RcClientFactory, profile: &Profile) -> ProfileValidation {
+    let mut errors = Vec::new();
+
+    if let Err(e) = profile.config.validate() {
+        errors.push(e);
+    }
+
+    let destination_exists = Path::new(&profile.config.destination).is_dir();
+    if !destination_exists {
+        errors.push(format!(
+            "Destination {} does not exist",
+            profile.config.destination
+        ));
+    }
+
+    let remote_exists = match list_drive_remotes(rc).await {
+        Ok(remotes) => remotes.contains(&profile.config.remote_config),
+        Err(e) => {
+            errors.push(e);
+            false
+        }
+    };
+    if !remote_exists {
+        errors.push(format!(
+            "Remote {} is not configured",
+            profile.config.remote_config
+        ));
+    }
+
+    ProfileValidation {
+        destination_exists,
+        remote_exists,
+        errors,
+    }
+}
+
+/// Write profiles to a JSON file, all of them if `ids` is not given
+#[tauri::command]
+pub async fn export_profiles(
+    state: State<'_, ProfileManager>,
+    path: String,
+    ids: Option<Vec<u64>>,
+) -> Result<usize, String> {
+    let profiles: Vec<ExportedProfile> = state
+        .lock()
+        .profiles
+        .iter()
+        .filter(|p| ids.as_ref().is_none_or(|ids| ids.contains(&p.id)))
+        .map(|p| ExportedProfile {
+            name: p.name.clone(),
+            config: p.config.clone(),
+        })
+        .collect();
+
+    let count = profiles.len();
+    let export = ProfileExport {
+        version: EXPORT_VERSION,
+        profiles,
+    };
+    write_json_file(&PathBuf::from(path), &export)?;
+    Ok(count)
+}
+
+/// Add the profiles from an exported JSON file, renaming ones whose name is taken
+#[tauri::command]
+pub async fn import_profiles(
+    app: AppHandle,
+    state: State<'_, ProfileManager>,
+    path: String,
+) -> Result<Vec<Profile>, String> {
+    let export: ProfileExport =
+        read_json_file(&PathBuf::from(&path))?.ok_or(format!("{} does not exist", path))?;
+    if export.version > EXPORT_VERSION {
+        return Err(format!(
+            "Profiles were exported by a newer version (format {})",
+            export.version
+        ));
+    }
+
+    // Reject the whole file rather than importing part of it
+    for profile in &export.profiles {
+        validate_name(&profile.name)?;
+        profile
+            .config
+            .validate()
+            .map_err(|e| format!("Profile '{}': {}", profile.name, e))?;
+    }
+
+    state.update(&app, |list| {
+        Ok(export
+            .profiles
+            .into_iter()
+            .map(|profile| {
+                let name = list.unique_name(profile.name.trim());
+                list.add(name, profile.config)
+            })
+            .collect())
+    })
+}
@@ -1,4 +1,5 @@
 use crate::api::rclone;
+use crate::service::RcClientFactory;
 use crate::utils::extract_json;
 use tauri::{AppHandle, Emitter, Manager, State};
 use tauri_plugin_shell::process::CommandEvent;
@@ -20,7 +21,12 @@ impl Default for GdriveAuthState {
 
 #[tauri::command]
 pub async fn get_gdrive_remotes(app: AppHandle) -> Result<Vec<String>, String> {
-    let client = rclone::get_sdk_client(&app).await?;
+    list_drive_remotes(&app).await
+}
+
+/// Names of the configured Google Drive remotes, sorted
+pub async fn list_drive_remotes(rc: &impl RcClientFactory) -> Result<Vec<String>, String> {
+    let client = rc.rc_client().await?;
 
     // config/dump
     let response = client
@@ -0,0 +1,48 @@
+use super::download::{SyncJobResult, get_job_stats};
+use crate::api::rclone::{FileChanges, LogManager, LogSpan};
+use crate::service::PathProvider;
+use serde::{Deserialize, Serialize};
+
+/// Summary of what a download changed in the destination
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadReport {
+    pub stats: SyncJobResult,
+    /// Average transfer speed in bytes per second
+    pub average_speed: f64,
+    /// What changed per file, `None` when other rclone jobs ran at the same time and
+    /// their log records can't be told apart
+    pub changes: Option<FileChanges>,
+    pub backup_dir: Option<String>,
+}
+
+impl DownloadReport {
+    /// Build the report for a finished job from its stats and the log written during `log`
+    pub(super) async fn collect(
+        paths: &impl PathProvider,
+        client: &rclone_sdk::Client,
+        jobid: i64,
+        log: &LogSpan,
+        backup_dir: Option<String>,
+    ) -> Result<Self, String> {
+        let stats = get_job_stats(client, jobid).await?;
+        let changes = if log.is_exclusive() {
+            Some(LogManager::parse_file_changes(paths, log.offset).await?)
+        } else {
+            None
+        };
+
+        let average_speed = if stats.elapsed_time > 0.0 {
+            stats.bytes as f64 / stats.elapsed_time
+        } else {
+            0.0
+        };
+
+        Ok(Self {
+            stats,
+            average_speed,
+            changes,
+            backup_dir,
+        })
+    }
+}
@@ -0,0 +1,621 @@
+use super::download::DownloadConfig;
+use super::jobs::{JobManager, JobStatus, dispatch_jobs};
+use super::profiles::ProfileManager;
+use super::report::DownloadReport;
+use crate::api::system;
+use crate::utils::{app_data_file, read_json_file, write_json_file};
+use chrono::{
+    DateTime, Datelike, Duration as ChronoDuration, Local, NaiveDate, NaiveTime, TimeZone, Timelike,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::{Mutex, MutexGuard};
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+
+const SCHEDULES_FILE_NAME: &str = "schedules.json";
+/// How often due schedules are looked for
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+/// How far ahead the next run of a cron expression is searched
+const MAX_SEARCH_DAYS: i64 = 366 * 4;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ScheduleDay {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl ScheduleDay {
+    /// Day number as used by cron, Sunday being 0
+    fn cron_number(self) -> usize {
+        match self {
+            Self::Sunday => 0,
+            Self::Monday => 1,
+            Self::Tuesday => 2,
+            Self::Wednesday => 3,
+            Self::Thursday => 4,
+            Self::Friday => 5,
+            Self::Saturday => 6,
+        }
+    }
+}
+
+/// When a schedule runs
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ScheduleTrigger {
+    /// Every day at `HH:MM`
+    Daily { time: String },
+    /// On the given days at `HH:MM`
+    Weekly {
+        days: Vec<ScheduleDay>,
+        time: String,
+    },
+    /// A standard 5-field cron expression, e.g. `0 3 * * 1-5`
+    Cron { expression: String },
+}
+
+impl ScheduleTrigger {
+    fn to_cron(&self) -> Result<CronSpec, String> {
+        match self {
+            Self::Daily { time } => {
+                let (hour, minute) = parse_time(time)?;
+                CronSpec::parse(&format!("{} {} * * *", minute, hour))
+            }
+            Self::Weekly { days, time } => {
+                if days.is_empty() {
+                    return Err("Weekly schedules need at least one day".to_string());
+                }
+                let (hour, minute) = parse_time(time)?;
+                let days: Vec<String> = days.iter().map(|d| d.cron_number().to_string()).collect();
+                CronSpec::parse(&format!("{} {} * * {}", minute, hour, days.join(",")))
+            }
+            Self::Cron { expression } => CronSpec::parse(expression),
+        }
+    }
+
+    /// The first time the trigger fires after `after`
+    pub fn next_run_after(&self, after: DateTime<Local>) -> Result<DateTime<Local>, String> {
+        self.to_cron()?
+            .next_after(after)
+            .ok_or("Schedule never runs".to_string())
+    }
+}
+
+fn parse_time(time: &str) -> Result<(u32, u32), String> {
+    let time = NaiveTime::parse_from_str(time.trim(), "%H:%M")
+        .map_err(|_| format!("Invalid time '{}', expected HH:MM", time))?;
+    Ok((time.hour(), time.minute()))
+}
+
+/// Parsed cron expression, each field a set of allowed values
+struct CronSpec {
+    minutes: Vec<bool>,
+    hours: Vec<bool>,
+    days_of_month: Vec<bool>,
+    months: Vec<bool>,
+    days_of_week: Vec<bool>,
+    /// Cron matches either day field when both are restricted
+    any_day_of_month: bool,
+    any_day_of_week: bool,
+}
+
+impl CronSpec {
+    fn parse(expression: &str) -> Result<Self, String> {
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days_of_month, months, days_of_week] = fields[..] else {
+            return Err(format!(
+                "Invalid cron expression '{}', expected 5 fields",
+                expression
+            ));
+        };
+
+        let mut days_of_week = parse_cron_field(days_of_week, 0, 7)?;
+        // Both 0 and 7 are Sunday
+        if days_of_week[7] {
+            days_of_week[0] = true;
+        }
+        days_of_week.truncate(7);
+
+        Ok(Self {
+            minutes: parse_cron_field(minutes, 0, 59)?,
+            hours: parse_cron_field(hours, 0, 23)?,
+            days_of_month: parse_cron_field(days_of_month, 1, 31)?,
+            months: parse_cron_field(months, 1, 12)?,
+            days_of_week,
+            any_day_of_month: days_of_month == "*",
+            any_day_of_week: fields[4] == "*",
+        })
+    }
+
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        if !self.months[date.month() as usize] {
+            return false;
+        }
+
+        let day_of_month = self.days_of_month[date.day() as usize];
+        let day_of_week = self.days_of_week[date.weekday().num_days_from_sunday() as usize];
+        match (self.any_day_of_month, self.any_day_of_week) {
+            (true, true) => true,
+            (false, true) => day_of_month,
+            (true, false) => day_of_week,
+            (false, false) => day_of_month || day_of_week,
+        }
+    }
+
+    fn next_after(&self, after: DateTime<Local>) -> Option<DateTime<Local>> {
+        // Whole minutes strictly after `after`
+        let start = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let start_date = start.date_naive();
+
+        for day in 0..MAX_SEARCH_DAYS {
+            let date = start_date + ChronoDuration::days(day);
+            if !self.matches_day(date) {
+                continue;
+            }
+
+            for hour in (0..24).filter(|h| self.hours[*h as usize]) {
+                for minute in (0..60).filter(|m| self.minutes[*m as usize]) {
+                    // Times skipped by a DST change don't exist and never run
+                    let Some(time) = date
+                        .and_hms_opt(hour, minute, 0)
+                        .and_then(|naive| Local.from_local_datetime(&naive).earliest())
+                    else {
+                        continue;
+                    };
+                    if time >= start {
+                        return Some(time);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Parse a cron field like `*`, `5`, `1-5`, `*/15` or `0,30` into a set of values
+fn parse_cron_field(field: &str, min: usize, max: usize) -> Result<Vec<bool>, String> {
+    let invalid = || format!("Invalid cron field '{}'", field);
+    let mut allowed = vec![false; max + 1];
+
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<usize>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                start.parse().map_err(|_| invalid())?,
+                end.parse().map_err(|_| invalid())?,
+            )
+        } else {
+            let value: usize = range.parse().map_err(|_| invalid())?;
+            // `5/10` means from 5 to the end in steps of 10
+            (value, if part.contains('/') { max } else { value })
+        };
+        if start < min || end > max || start > end {
+            return Err(invalid());
+        }
+
+        for value in (start..=end).step_by(step) {
+            allowed[value] = true;
+        }
+    }
+
+    Ok(allowed)
+}
+
+/// A saved download run automatically on a schedule
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Schedule {
+    pub id: u64,
+    pub name: String,
+    pub enabled: bool,
+    pub trigger: ScheduleTrigger,
+    pub config: DownloadConfig,
+    /// Profile whose current config is run instead of `config`
+    #[serde(default)]
+    pub profile_id: Option<u64>,
+    /// Wait for AC power, where the platform can tell
+    #[serde(default)]
+    pub require_ac_power: bool,
+    /// Wait for an unmetered connection, where the platform can tell
+    #[serde(default)]
+    pub require_unmetered: bool,
+    pub next_run: Option<String>,
+    pub last_run: Option<String>,
+    /// Download job of the last run
+    pub last_job_id: Option<u64>,
+    pub last_status: Option<JobStatus>,
+    pub last_error: Option<String>,
+    /// Why a due run hasn't started yet
+    pub waiting_reason: Option<String>,
+}
+
+impl Schedule {
+    fn validate(&self) -> Result<(), String> {
+        if self.name.trim().is_empty() {
+            return Err("Schedule name is required".to_string());
+        }
+        self.trigger.to_cron()?;
+        self.config.validate()
+    }
+
+    /// Config to run, following the linked profile if there is one
+    fn resolve_config(&self, profiles: &ProfileManager) -> Result<DownloadConfig, String> {
+        match self.profile_id {
+            Some(profile_id) => profiles
+                .get(profile_id)
+                .map(|profile| profile.config)
+                .ok_or(format!("Profile {} no longer exists", profile_id)),
+            None => Ok(self.config.clone()),
+        }
+    }
+
+    fn update_next_run(&mut self, after: DateTime<Local>) {
+        self.next_run = self
+            .trigger
+            .next_run_after(after)
+            .ok()
+            .map(|time| time.to_rfc3339());
+    }
+
+    fn is_due(&self, now: DateTime<Local>) -> bool {
+        self.enabled
+            && self
+                .next_run
+                .as_deref()
+                .and_then(|next| DateTime::parse_from_rfc3339(next).ok())
+                .is_some_and(|next| next <= now)
+    }
+
+    /// Why the run can't start now, if anything
+    fn blocked_reason(&self) -> Option<String> {
+        if self.require_ac_power && system::on_ac_power() == Some(false) {
+            return Some("Waiting for AC power".to_string());
+        }
+        if self.require_unmetered && system::on_metered_connection() == Some(true) {
+            return Some("Waiting for an unmetered connection".to_string());
+        }
+        None
+    }
+}
+
+/// Outcome of a scheduled run, emitted as `scheduled-sync-finished`.
+/// The backend doesn't notify anyone itself, the frontend shows a system notification for it.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledRunResult {
+    pub schedule_id: u64,
+    pub name: String,
+    pub job_id: u64,
+    pub status: JobStatus,
+    pub error: Option<String>,
+    pub report: Option<DownloadReport>,
+}
+
+/// Schedules persisted between sessions
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ScheduleList {
+    next_id: u64,
+    schedules: Vec<Schedule>,
+}
+
+impl Default for ScheduleList {
+    fn default() -> Self {
+        Self {
+            next_id: 1,
+            schedules: Vec::new(),
+        }
+    }
+}
+
+impl ScheduleList {
+    fn get_mut(&mut self, id: u64) -> Result<&mut Schedule, String> {
+        self.schedules
+            .iter_mut()
+            .find(|schedule| schedule.id == id)
+            .ok_or(format!("Schedule {} not found", id))
+    }
+}
+
+/// Runs saved downloads on their schedules through the job queue
+#[derive(Default)]
+pub struct ScheduleManager {
+    list: Mutex<ScheduleList>,
+}
+
+impl ScheduleManager {
+    /// Load schedules from disk. Runs missed while the app was closed are still due and run once.
+    pub fn load(app: &AppHandle) -> Self {
+        let mut list: ScheduleList = app_data_file(app, SCHEDULES_FILE_NAME)
+            .and_then(|path| read_json_file(&path))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        let now = Local::now();
+        for schedule in &mut list.schedules {
+            if schedule.next_run.is_none() {
+                schedule.update_next_run(now);
+            }
+        }
+
+        Self {
+            list: Mutex::new(list),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, ScheduleList> {
+        self.list.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Apply a change to the schedules, then persist them and notify the frontend
+    fn update<R>(&self, app: &AppHandle, f: impl FnOnce(&mut ScheduleList) -> R) -> R {
+        let mut list = self.lock();
+        let result = f(&mut list);
+
+        if let Err(e) =
+            app_data_file(app, SCHEDULES_FILE_NAME).and_then(|path| write_json_file(&path, &*list))
+        {
+            log::error!("Failed to save schedules: {}", e);
+        }
+
+        let _ = app.emit("schedules-changed", &list.schedules);
+        result
+    }
+}
+
+/// Check for due schedules in the background for the lifetime of the app
+pub fn start_scheduler(app: &AppHandle) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        loop {
+            run_due_schedules(&app).await;
+            tokio::time::sleep(TICK_INTERVAL).await;
+        }
+    });
+}
+
+async fn run_due_schedules(app: &AppHandle) {
+    let now = Local::now();
+    let due: Vec<Schedule> = app
+        .state::<ScheduleManager>()
+        .lock()
+        .schedules
+        .iter()
+        .filter(|schedule| schedule.is_due(now))
+        .cloned()
+        .collect();
+
+    for schedule in due {
+        // Power and network checks may spawn processes
+        let checked = schedule.clone();
+        let blocked = tauri::async_runtime::spawn_blocking(move || checked.blocked_reason())
+            .await
+            .unwrap_or(None);
+
+        if let Some(reason) = blocked {
+            if schedule.waiting_reason.as_ref() != Some(&reason) {
+                let manager = app.state::<ScheduleManager>();
+                manager.update(app, |list| {
+                    if let Ok(current) = list.get_mut(schedule.id) {
+                        current.waiting_reason = Some(reason.clone());
+                    }
+                });
+                let _ = app.emit("scheduled-sync-waiting", (&schedule.name, &reason));
+            }
+            continue;
+        }
+
+        if let Err(e) = start_run(app, schedule.id, false) {
+            log::error!("Failed to run schedule {}: {}", schedule.name, e);
+        }
+    }
+}
+
+/// Queue the download of a schedule and report its outcome when it settles.
+/// Runs started by hand leave the next scheduled run as it is.
+fn start_run(app: &AppHandle, id: u64, manual: bool) -> Result<u64, String> {
+    let jobs = app.state::<JobManager>();
+    let profiles = app.state::<ProfileManager>();
+    let manager = app.state::<ScheduleManager>();
+
+    let (schedule, job_id) = manager.update(app, |list| {
+        let schedule = list.get_mut(id)?;
+        let now = Local::now();
+        if !manual {
+            schedule.update_next_run(now);
+            schedule.waiting_reason = None;
+        }
+
+        // Don't pile up runs behind one that is still going
+        let previous = schedule
+            .last_job_id
+            .and_then(|job_id| jobs.list().into_iter().find(|job| job.id == job_id));
+        if let Some(previous) = previous
+            && matches!(
+                previous.status,
+                JobStatus::Queued | JobStatus::Running | JobStatus::Paused
+            )
+        {
+            return Err(format!("Previous run of {} is still going", schedule.name));
+        }
+
+        let config = schedule.resolve_config(&profiles)?;
+        let job_id = jobs.enqueue(app, config);
+        schedule.last_run = Some(now.to_rfc3339());
+        schedule.last_job_id = Some(job_id);
+        schedule.last_status = Some(JobStatus::Queued);
+        schedule.last_error = None;
+        Ok((schedule.clone(), job_id))
+    })?;
+
+    dispatch_jobs(app);
+
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        finish_run(&app, schedule, job_id).await;
+    });
+    Ok(job_id)
+}
+
+async fn finish_run(app: &AppHandle, schedule: Schedule, job_id: u64) {
+    let job = match app.state::<JobManager>().wait(job_id).await {
+        Ok(job) => job,
+        Err(e) => {
+            log::error!(
+                "Failed to wait for scheduled run of {}: {}",
+                schedule.name,
+                e
+            );
+            return;
+        }
+    };
+
+    let manager = app.state::<ScheduleManager>();
+    manager.update(app, |list| {
+        if let Ok(current) = list.get_mut(schedule.id)
+            && current.last_job_id == Some(job_id)
+        {
+            current.last_status = Some(job.status);
+            current.last_error = job.error.clone();
+        }
+    });
+
+    let _ = app.emit(
+        "scheduled-sync-finished",
+        ScheduledRunResult {
+            schedule_id: schedule.id,
+            name: schedule.name,
+            job_id,
+            status: job.status,
+            error: job.error,
+            report: job.report,
+        },
+    );
+}
+
+#[tauri::command]
+pub async fn list_schedules(state: State<'_, ScheduleManager>) -> Result<Vec<Schedule>, String> {
+    Ok(state.lock().schedules.clone())
+}
+
+#[tauri::command]
+pub async fn create_schedule(
+    app: AppHandle,
+    state: State<'_, ScheduleManager>,
+    name: String,
+    trigger: ScheduleTrigger,
+    config: DownloadConfig,
+    profile_id: Option<u64>,
+    require_ac_power: bool,
+    require_unmetered: bool,
+) -> Result<Schedule, String> {
+    let mut schedule = Schedule {
+        id: 0,
+        name,
+        enabled: true,
+        trigger,
+        config,
+        profile_id,
+        require_ac_power,
+        require_unmetered,
+        next_run: None,
+        last_run: None,
+        last_job_id: None,
+        last_status: None,
+        last_error: None,
+        waiting_reason: None,
+    };
+    schedule.validate()?;
+    schedule.update_next_run(Local::now());
+
+    Ok(state.update(&app, |list| {
+        schedule.id = list.next_id;
+        list.next_id += 1;
+        list.schedules.push(schedule.clone());
+        schedule
+    }))
+}
+
+/// Replace the settings of a schedule, keeping its run history
+#[tauri::command]
+pub async fn update_schedule(
+    app: AppHandle,
+    state: State<'_, ScheduleManager>,
+    schedule: Schedule,
+) -> Result<Schedule, String> {
+    schedule.validate()?;
+
+    state.update(&app, |list| {
+        let current = list.get_mut(schedule.id)?;
+        let trigger_changed = current.trigger != schedule.trigger;
+
+        current.name = schedule.name;
+        current.enabled = schedule.enabled;
+        current.trigger = schedule.trigger;
+        current.config = schedule.config;
+        current.profile_id = schedule.profile_id;
+        current.require_ac_power = schedule.require_ac_power;
+        current.require_unmetered = schedule.require_unmetered;
+        if trigger_changed || current.next_run.is_none() {
+            current.update_next_run(Local::now());
+        }
+        Ok(current.clone())
+    })
+}
+
+#[tauri::command]
+pub async fn delete_schedule(
+    app: AppHandle,
+    state: State<'_, ScheduleManager>,
+    id: u64,
+) -> Result<(), String> {
+    state.update(&app, |list| {
+        let index = list
+            .schedules
+            .iter()
+            .position(|schedule| schedule.id == id)
+            .ok_or(format!("Schedule {} not found", id))?;
+        list.schedules.remove(index);
+        Ok(())
+    })
+}
+
+/// Run a schedule right away, returning the id of its download job
+#[tauri::command]
+pub async fn run_schedule_now(app: AppHandle, id: u64) -> Result<u64, String> {
+    start_run(&app, id, true)
+}
+
+/// Most runs `preview_schedule` lists, however many are asked for
+const MAX_PREVIEW_RUNS: usize = 50;
+
+/// Get the next times a trigger fires, to check it before saving
+#[tauri::command]
+pub async fn preview_schedule(
+    trigger: ScheduleTrigger,
+    count: Option<usize>,
+) -> Result<Vec<String>, String> {
+    let mut runs = Vec::new();
+    let mut after = Local::now();
+    for _ in 0..count.unwrap_or(5).min(MAX_PREVIEW_RUNS) {
+        after = trigger.next_run_after(after)?;
+        runs.push(after.to_rfc3339());
+    }
+    Ok(runs)
+}
@@ -1,4 +1,4 @@
-#[derive(serde::Serialize, Clone, Debug)]
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, PartialEq)]
 #[serde(rename_all = "camelCase")]
 pub struct GdriveFile {
     pub path: String,
@@ -6,4 +6,24 @@ pub struct GdriveFile {
     pub is_dir: bool,
     pub size: i64,
     pub mime_type: String,
+    /// Drive ID, to place changed files when refreshing a cached listing
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+}
+
+/// Immediate child of a Drive folder, with totals for folders when the whole
+/// listing is known
+#[derive(serde::Serialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct GdriveDirEntry {
+    pub path: String,
+    pub name: String,
+    pub is_dir: bool,
+    /// For folders, the size of every file inside them
+    pub size: Option<i64>,
+    pub mime_type: String,
+    /// Files and folders directly inside a folder
+    pub child_count: Option<usize>,
+    /// Files anywhere inside a folder
+    pub file_count: Option<usize>,
 }
@@ -22,3 +22,29 @@ pub fn parse_gdrive_id(source: &str) -> String {
     // Assume it's an ID if no known prefix found
     source.to_string()
 }
+
+/// Escape the characters rclone's filter globs treat specially, so a path in a filter
+/// rule only matches itself, e.g. `[Neuro] Song.mp3`
+pub fn escape_glob(path: &str) -> String {
+    let mut escaped = String::with_capacity(path.len());
+    for c in path.chars() {
+        if matches!(c, '\\' | '*' | '?' | '[' | ']' | '{' | '}') {
+            escaped.push('\\');
+        }
+        escaped.push(c);
+    }
+    escaped
+}
+
+/// Turn a local path into an rclone fs string.
+/// On Windows, use UNC paths so `C:` isn't taken for a remote.
+pub fn local_fs(path: &str) -> String {
+    if cfg!(windows) {
+        let path_str = path.replace("/", "\\");
+        if path_str.starts_with("\\\\?\\") {
+            return path_str;
+        }
+        return format!("\\\\?\\{}", path_str);
+    }
+    path.to_string()
+}
@@ -0,0 +1,214 @@
+use super::download::{DownloadConfig, SyncJobError, build_check_body, poll_job_output};
+use super::jobs::{JobManager, dispatch_jobs};
+use super::options::{CompareMode, TransferOptions};
+use crate::api::rclone::LogSpan;
+use crate::service::Host;
+use rclone_sdk::ClientInfo;
+use serde::Serialize;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// How often progress is reported while verifying
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Files checked so far, sent with `verify-progress` events
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyProgress {
+    pub checks: i64,
+    pub total_checks: i64,
+    pub errors: i64,
+    /// Seconds the check has been running
+    pub elapsed_time: f64,
+}
+
+/// Result of checking the destination against the Drive
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct VerifyReport {
+    /// Whether every file matched
+    pub success: bool,
+    /// Summary from rclone, e.g. `3 differences found`
+    pub status: String,
+    /// Hash the files were compared with, none if only sizes could be compared
+    pub hash_type: Option<String>,
+    pub matched: usize,
+    /// Files whose content differs from the Drive
+    pub mismatched: Vec<String>,
+    /// Files on the Drive missing from the destination
+    pub missing: Vec<String>,
+    /// Files in the destination that aren't on the Drive
+    pub extra: Vec<String>,
+    /// Files that couldn't be checked
+    pub errors: Vec<String>,
+    /// Download queued to replace the mismatched files
+    pub redownload_job: Option<u64>,
+}
+
+impl VerifyReport {
+    /// Read the output of `operations/check`, whose `combined` list prefixes each
+    /// path with `=` (match), `*` (differ), `-` (only on the Drive), `+` (only local) or `!` (error)
+    fn from_output(output: &serde_json::Value) -> Self {
+        let mut report = Self {
+            success: output["success"].as_bool().unwrap_or(false),
+            status: output["status"].as_str().unwrap_or_default().to_string(),
+            hash_type: output["hashType"]
+                .as_str()
+                .filter(|hash| !hash.is_empty() && *hash != "none")
+                .map(str::to_string),
+            ..Default::default()
+        };
+
+        let lines = output["combined"].as_array().into_iter().flatten();
+        for line in lines.filter_map(|line| line.as_str()) {
+            let Some((kind, path)) = line.split_once(' ') else {
+                continue;
+            };
+            let path = path.to_string();
+            match kind {
+                "=" => report.matched += 1,
+                "*" => report.mismatched.push(path),
+                "-" => report.missing.push(path),
+                "+" => report.extra.push(path),
+                "!" => report.errors.push(path),
+                _ => {}
+            }
+        }
+        report
+    }
+}
+
+/// Check the local archive against the MD5 hashes on the Drive without downloading anything.
+/// Reports progress with `verify-progress` events, and with `redownload_corrupted` queues
+/// a download of the files that differ.
+#[tauri::command]
+pub async fn verify_archive(
+    app: AppHandle,
+    source: String,
+    destination: String,
+    remote_config: Option<String>,
+    create_subfolder: bool,
+    selected_files: Option<Vec<String>>,
+    options: Option<TransferOptions>,
+    redownload_corrupted: Option<bool>,
+) -> Result<VerifyReport, String> {
+    let config = DownloadConfig::new(
+        source,
+        destination,
+        remote_config,
+        false,
+        create_subfolder,
+        selected_files,
+        false,
+        false,
+        false,
+        None,
+        options,
+    )?;
+
+    let mut report = verify(&app, &config, |progress| {
+        let _ = app.emit("verify-progress", progress);
+    })
+    .await?;
+
+    if redownload_corrupted.unwrap_or(false) && !report.mismatched.is_empty() {
+        report.redownload_job = Some(redownload(&app, &config, &report.mismatched));
+    }
+    Ok(report)
+}
+
+/// Run `operations/check` between the Drive and the destination of `config`
+pub async fn verify(
+    host: &impl Host,
+    config: &DownloadConfig,
+    on_progress: impl Fn(VerifyProgress),
+) -> Result<VerifyReport, String> {
+    let client = host.rc_client().await?;
+    let mut body = build_check_body(config)?;
+    body["_async"] = serde_json::json!(true);
+    // The check logs every difference, which would end up in the reports of running downloads
+    let _log = LogSpan::open(host).await;
+
+    let response = client
+        .client()
+        .post(format!("{}/operations/check", client.baseurl()))
+        .json(&body)
+        .send()
+        .await
+        .map_err(|e| format!("Verification failed to start: {}", e))?;
+    if !response.status().is_success() {
+        let err_text = response.text().await.unwrap_or_default();
+        return Err(format!("Verification failed to start: {}", err_text));
+    }
+    let jobid = response
+        .json::<serde_json::Value>()
+        .await
+        .map_err(|e| format!("Failed to parse check response: {}", e))?["jobid"]
+        .as_i64()
+        .ok_or("No jobid returned")?;
+
+    let output = tokio::select! {
+        output = poll_job_output(&client, jobid) => output,
+        _ = report_progress(&client, jobid, &on_progress) => unreachable!(),
+    };
+    let output = match output {
+        Ok(output) => output.unwrap_or_default(),
+        Err(SyncJobError::ServerStopped) => {
+            return Err("Verification interrupted (rclone stopped)".to_string());
+        }
+        Err(SyncJobError::Cancelled) => return Err("Verification cancelled".to_string()),
+        Err(SyncJobError::Failed(e)) => return Err(format!("Verification failed: {}", e)),
+    };
+
+    Ok(VerifyReport::from_output(&output))
+}
+
+/// Report how many files were checked until cancelled
+async fn report_progress(
+    client: &rclone_sdk::Client,
+    jobid: i64,
+    on_progress: &impl Fn(VerifyProgress),
+) {
+    loop {
+        tokio::time::sleep(PROGRESS_INTERVAL).await;
+        let response = client
+            .client()
+            .post(format!("{}/core/stats", client.baseurl()))
+            .json(&serde_json::json!({
+                "group": format!("job/{}", jobid)
+            }))
+            .send()
+            .await;
+        let Ok(response) = response else {
+            continue;
+        };
+        let Ok(stats) = response.json::<serde_json::Value>().await else {
+            continue;
+        };
+
+        on_progress(VerifyProgress {
+            checks: stats["checks"].as_i64().unwrap_or(0),
+            total_checks: stats["totalChecks"].as_i64().unwrap_or(0),
+            errors: stats["errors"].as_i64().unwrap_or(0),
+            elapsed_time: stats["elapsedTime"].as_f64().unwrap_or(0.0),
+        });
+    }
+}
+
+/// Download of just the given files of `config`, comparing checksums so files
+/// with the same size and modification time are replaced too
+pub fn redownload_config(config: &DownloadConfig, files: &[String]) -> DownloadConfig {
+    let mut config = config.clone();
+    config.sync_mode = false;
+    config.selected_files = Some(files.to_vec());
+    config.options.compare = CompareMode::Checksum;
+    config
+}
+
+/// Queue a download of just the given files
+fn redownload(app: &AppHandle, config: &DownloadConfig, files: &[String]) -> u64 {
+    let manager = app.state::<JobManager>();
+    let id = manager.enqueue(app, redownload_config(config, files));
+    dispatch_jobs(app);
+    id
+}
@@ -1,3 +1,21 @@
+mod library;
+mod tags;
+
+// Types
+pub use library::{
+    LibraryEntry, LibraryFacet, LibraryManager, LibraryPage, LibraryQuery, LibraryScanSummary,
+    LibrarySort, LibrarySummary,
+};
+pub use tags::AudioTags;
+
+// Command functions
+pub use library::{__cmd__get_library_summary, __cmd__index_library, __cmd__search_library};
+
+// Functions
+pub use library::{get_library_summary, index_library, search_library};
+pub use tags::{is_audio_file, read_tags};
+
+use crate::utils::walk_files;
 use std::path::Path;
 
 #[tauri::command]
@@ -7,24 +25,9 @@ pub fn scan_local_files(path: String) -> Result<Vec<String>, String> {
         return Err("Destination is not a valid directory".to_string());
     }
 
-    let mut files = Vec::new();
-    let mut stack = vec![root.to_path_buf()];
-
-    while let Some(dir) = stack.pop() {
-        let entries = std::fs::read_dir(&dir).map_err(|e| e.to_string())?;
-        for entry in entries {
-            let entry = entry.map_err(|e| e.to_string())?;
-            let path = entry.path();
-            if path.is_dir() {
-                stack.push(path);
-            } else {
-                if let Ok(relative) = path.strip_prefix(root) {
-                    let path_str = relative.to_string_lossy().replace('\\', "/");
-                    files.push(path_str);
-                }
-            }
-        }
-    }
-
-    Ok(files)
+    Ok(walk_files(root)
+        .files
+        .into_iter()
+        .map(|file| file.relative)
+        .collect())
 }
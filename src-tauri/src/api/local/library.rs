@@ -0,0 +1,509 @@
+use super::tags::{AudioTags, is_audio_file, read_tags};
+use crate::service::PathProvider;
+use crate::utils::{WalkedFile, app_data_file, read_json_file, walk_files, write_json_file};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::io::Read;
+use std::path::Path;
+use std::sync::atomic::{self, AtomicBool};
+use std::sync::{Mutex, MutexGuard};
+use tauri::{AppHandle, Emitter, Manager, State};
+
+const LIBRARY_FILE_NAME: &str = "library.json";
+/// Files indexed between progress events
+const PROGRESS_INTERVAL: usize = 50;
+
+/// A song in the local library
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryEntry {
+    /// Relative to the library root, with `/` separators
+    pub path: String,
+    pub size: u64,
+    /// Modification time in seconds since the Unix epoch
+    pub modified: i64,
+    /// SHA-256 of the content, hex encoded
+    pub sha256: String,
+    #[serde(flatten)]
+    pub tags: AudioTags,
+    /// Why the tags couldn't be read, the entry only has the file info then
+    pub error: Option<String>,
+}
+
+/// What a scan changed in the index
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryScanSummary {
+    pub root: String,
+    pub added: usize,
+    pub updated: usize,
+    pub removed: usize,
+    pub unchanged: usize,
+    /// Files that couldn't be read or whose tags couldn't be parsed
+    pub failed: usize,
+    /// Folders that couldn't be read, their songs stay in the index as they were
+    pub failed_dirs: usize,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryScanProgress {
+    scanned: usize,
+    total: usize,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LibrarySort {
+    #[default]
+    Path,
+    Title,
+    Artist,
+    Album,
+    Date,
+    Duration,
+    Bitrate,
+    Size,
+    Modified,
+}
+
+/// Filters for `search_library`, all of which must match
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct LibraryQuery {
+    /// Words to find in the title, artist, album or path, ignoring case
+    pub text: Option<String>,
+    /// One of the artists, ignoring case
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// Year the date starts with
+    pub year: Option<String>,
+    /// Seconds
+    pub min_duration: Option<f64>,
+    pub max_duration: Option<f64>,
+    pub sort: LibrarySort,
+    pub descending: bool,
+    pub offset: usize,
+    pub limit: Option<usize>,
+}
+
+/// A page of search results
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryPage {
+    /// Matches before `offset` and `limit` are applied
+    pub total: usize,
+    pub entries: Vec<LibraryEntry>,
+}
+
+/// A value to filter by and how many songs have it
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryFacet {
+    pub name: String,
+    pub count: usize,
+}
+
+/// Totals of the library and the values to offer as filters
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibrarySummary {
+    pub root: Option<String>,
+    pub scanned_at: Option<String>,
+    pub file_count: usize,
+    pub total_size: u64,
+    /// Seconds
+    pub total_duration: f64,
+    pub artists: Vec<LibraryFacet>,
+    pub albums: Vec<LibraryFacet>,
+    pub years: Vec<LibraryFacet>,
+}
+
+/// Index persisted between sessions
+#[derive(Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LibraryIndex {
+    /// Folder the entries are relative to, a scan of another folder replaces the index
+    root: Option<String>,
+    scanned_at: Option<String>,
+    entries: Vec<LibraryEntry>,
+}
+
+/// Keeps the index of the songs in the download destination
+#[derive(Default)]
+pub struct LibraryManager {
+    index: Mutex<LibraryIndex>,
+    scanning: AtomicBool,
+}
+
+/// Clears the scanning flag when a scan ends, even by panicking
+struct ScanGuard<'a>(&'a AtomicBool);
+
+impl Drop for ScanGuard<'_> {
+    fn drop(&mut self) {
+        self.0.store(false, atomic::Ordering::SeqCst);
+    }
+}
+
+impl LibraryManager {
+    pub fn load(paths: &impl PathProvider) -> Self {
+        let index = app_data_file(paths, LIBRARY_FILE_NAME)
+            .and_then(|path| read_json_file(&path))
+            .inspect_err(|e| log::error!("Failed to load library index: {}", e))
+            .ok()
+            .flatten()
+            .unwrap_or_default();
+
+        Self {
+            index: Mutex::new(index),
+            scanning: AtomicBool::new(false),
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, LibraryIndex> {
+        self.index.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Entries of the previous scan of `root`, by path
+    fn previous_entries(&self, root: &str) -> HashMap<String, LibraryEntry> {
+        let index = self.lock();
+        if index.root.as_deref() != Some(root) {
+            return HashMap::new();
+        }
+        index
+            .entries
+            .iter()
+            .map(|entry| (entry.path.clone(), entry.clone()))
+            .collect()
+    }
+
+    /// Index `root`, reading only files that are new or changed since the last scan
+    pub fn scan(
+        &self,
+        paths: &impl PathProvider,
+        root: &Path,
+        on_progress: impl Fn(usize, usize),
+    ) -> Result<LibraryScanSummary, String> {
+        if self.scanning.swap(true, atomic::Ordering::SeqCst) {
+            return Err("A library scan is already running".to_string());
+        }
+        let _scanning = ScanGuard(&self.scanning);
+        self.run_scan(paths, root, on_progress)
+    }
+
+    fn run_scan(
+        &self,
+        paths: &impl PathProvider,
+        root: &Path,
+        on_progress: impl Fn(usize, usize),
+    ) -> Result<LibraryScanSummary, String> {
+        if !root.is_dir() {
+            return Err("Destination is not a valid directory".to_string());
+        }
+        let root_str = root.to_string_lossy().to_string();
+        let mut previous = self.previous_entries(&root_str);
+        let walked = walk_files(root);
+        let files: Vec<WalkedFile> = walked
+            .files
+            .into_iter()
+            .filter(|file| is_audio_file(&file.path))
+            .collect();
+
+        let mut summary = LibraryScanSummary {
+            root: root_str.clone(),
+            failed_dirs: walked.failed_dirs.len(),
+            ..Default::default()
+        };
+        let mut entries = Vec::with_capacity(files.len());
+        for (i, WalkedFile { path, relative, .. }) in files.iter().enumerate() {
+            if i % PROGRESS_INTERVAL == 0 {
+                on_progress(i, files.len());
+            }
+
+            let Ok(metadata) = std::fs::metadata(path) else {
+                summary.failed += 1;
+                continue;
+            };
+            let size = metadata.len();
+            let modified = metadata
+                .modified()
+                .ok()
+                .and_then(|time| time.duration_since(std::time::UNIX_EPOCH).ok())
+                .map_or(0, |d| d.as_secs() as i64);
+
+            let known = previous.remove(relative);
+            if let Some(entry) = known
+                .as_ref()
+                .filter(|e| e.size == size && e.modified == modified)
+            {
+                summary.unchanged += 1;
+                entries.push(entry.clone());
+                continue;
+            }
+
+            let sha256 = match hash_file(path) {
+                Ok(hash) => hash,
+                Err(e) => {
+                    log::warn!("{}", e);
+                    summary.failed += 1;
+                    continue;
+                }
+            };
+            // A bug in a tag reader shouldn't take the whole scan down
+            let read = std::panic::catch_unwind(|| read_tags(path))
+                .unwrap_or_else(|_| Err(format!("Failed to read tags of {}", path.display())));
+            let (tags, error) = match read {
+                Ok(tags) => (tags, None),
+                Err(e) => {
+                    summary.failed += 1;
+                    (AudioTags::default(), Some(e))
+                }
+            };
+
+            if known.is_some() {
+                summary.updated += 1;
+            } else {
+                summary.added += 1;
+            }
+            entries.push(LibraryEntry {
+                path: relative.clone(),
+                size,
+                modified,
+                sha256,
+                tags,
+                error,
+            });
+        }
+        on_progress(files.len(), files.len());
+
+        // Songs in folders that couldn't be read may well still be there
+        let (kept, removed): (Vec<_>, Vec<_>) = previous.into_values().partition(|entry| {
+            walked
+                .failed_dirs
+                .iter()
+                .any(|dir| dir.is_empty() || entry.path.starts_with(&format!("{}/", dir)))
+        });
+        summary.removed = removed.len();
+        entries.extend(kept);
+        entries.sort_by(|a, b| a.path.cmp(&b.path));
+
+        let mut index = self.lock();
+        *index = LibraryIndex {
+            root: Some(root_str),
+            scanned_at: Some(chrono::Local::now().to_rfc3339()),
+            entries,
+        };
+        app_data_file(paths, LIBRARY_FILE_NAME).and_then(|path| write_json_file(&path, &*index))?;
+        Ok(summary)
+    }
+
+    /// Songs matching the query, sorted and paged
+    pub fn search(&self, query: &LibraryQuery) -> LibraryPage {
+        let index = self.lock();
+        let words: Vec<String> = query
+            .text
+            .as_deref()
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_lowercase)
+            .collect();
+
+        let mut matches: Vec<&LibraryEntry> = index
+            .entries
+            .iter()
+            .filter(|entry| words.iter().all(|word| entry.matches_word(word)))
+            .filter(|entry| {
+                query
+                    .artist
+                    .as_deref()
+                    .is_none_or(|artist| entry.artists().any(|a| a.eq_ignore_ascii_case(artist)))
+            })
+            .filter(|entry| {
+                query.album.as_deref().is_none_or(
+                    |album| matches!(&entry.tags.album, Some(a) if a.eq_ignore_ascii_case(album)),
+                )
+            })
+            .filter(|entry| {
+                query
+                    .year
+                    .as_deref()
+                    .is_none_or(|year| entry.year() == Some(year))
+            })
+            .filter(|entry| {
+                let duration = entry.tags.duration.unwrap_or(0.0);
+                query.min_duration.is_none_or(|min| duration >= min)
+                    && query.max_duration.is_none_or(|max| duration <= max)
+            })
+            .collect();
+
+        matches.sort_by(|a, b| {
+            let ordering = compare(a, b, query.sort).then_with(|| a.path.cmp(&b.path));
+            if query.descending {
+                ordering.reverse()
+            } else {
+                ordering
+            }
+        });
+
+        let total = matches.len();
+        let entries = matches
+            .into_iter()
+            .skip(query.offset)
+            .take(query.limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        LibraryPage { total, entries }
+    }
+
+    pub fn summary(&self) -> LibrarySummary {
+        let index = self.lock();
+        let mut artists = BTreeMap::new();
+        let mut albums = BTreeMap::new();
+        let mut years = BTreeMap::new();
+        for entry in &index.entries {
+            for artist in entry.artists() {
+                *artists.entry(artist.to_string()).or_insert(0) += 1;
+            }
+            if let Some(album) = &entry.tags.album {
+                *albums.entry(album.clone()).or_insert(0) += 1;
+            }
+            if let Some(year) = entry.year() {
+                *years.entry(year.to_string()).or_insert(0) += 1;
+            }
+        }
+
+        LibrarySummary {
+            root: index.root.clone(),
+            scanned_at: index.scanned_at.clone(),
+            file_count: index.entries.len(),
+            total_size: index.entries.iter().map(|e| e.size).sum(),
+            total_duration: index.entries.iter().filter_map(|e| e.tags.duration).sum(),
+            artists: facets(artists),
+            albums: facets(albums),
+            years: facets(years),
+        }
+    }
+}
+
+impl LibraryEntry {
+    /// Artists of a song tagged with several, e.g. a duet
+    fn artists(&self) -> impl Iterator<Item = &str> {
+        self.tags
+            .artist
+            .as_deref()
+            .unwrap_or_default()
+            .split("; ")
+            .filter(|artist| !artist.is_empty())
+    }
+
+    fn year(&self) -> Option<&str> {
+        self.tags
+            .date
+            .as_deref()
+            .and_then(|date| date.get(..4))
+            .filter(|year| year.chars().all(|c| c.is_ascii_digit()))
+    }
+
+    /// Whether a lowercase word appears in the tags or path
+    fn matches_word(&self, word: &str) -> bool {
+        [
+            self.tags.title.as_deref(),
+            self.tags.artist.as_deref(),
+            self.tags.album.as_deref(),
+            Some(self.path.as_str()),
+        ]
+        .into_iter()
+        .flatten()
+        .any(|field| field.to_lowercase().contains(word))
+    }
+}
+
+/// Order by a field, songs without it last
+fn compare(a: &LibraryEntry, b: &LibraryEntry, sort: LibrarySort) -> Ordering {
+    fn by<T: PartialOrd>(a: Option<T>, b: Option<T>) -> Ordering {
+        match (a, b) {
+            (Some(a), Some(b)) => a.partial_cmp(&b).unwrap_or(Ordering::Equal),
+            (Some(_), None) => Ordering::Less,
+            (None, Some(_)) => Ordering::Greater,
+            (None, None) => Ordering::Equal,
+        }
+    }
+    let text = |value: &Option<String>| value.as_deref().map(str::to_lowercase);
+
+    match sort {
+        LibrarySort::Path => a.path.to_lowercase().cmp(&b.path.to_lowercase()),
+        LibrarySort::Title => by(text(&a.tags.title), text(&b.tags.title)),
+        LibrarySort::Artist => by(text(&a.tags.artist), text(&b.tags.artist)),
+        LibrarySort::Album => by(text(&a.tags.album), text(&b.tags.album)),
+        LibrarySort::Date => by(a.tags.date.as_ref(), b.tags.date.as_ref()),
+        LibrarySort::Duration => by(a.tags.duration, b.tags.duration),
+        LibrarySort::Bitrate => by(a.tags.bitrate, b.tags.bitrate),
+        LibrarySort::Size => a.size.cmp(&b.size),
+        LibrarySort::Modified => a.modified.cmp(&b.modified),
+    }
+}
+
+/// Most common values first
+fn facets(counts: BTreeMap<String, usize>) -> Vec<LibraryFacet> {
+    let mut facets: Vec<LibraryFacet> = counts
+        .into_iter()
+        .map(|(name, count)| LibraryFacet { name, count })
+        .collect();
+    facets.sort_by_key(|facet| std::cmp::Reverse(facet.count));
+    facets
+}
+
+fn hash_file(path: &Path) -> Result<String, String> {
+    let mut file = std::fs::File::open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0; 64 * 1024];
+    loop {
+        let read = file
+            .read(&mut buf)
+            .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// Index the audio files in a folder, only reading those new or changed since the last scan.
+/// Reports progress with `library-scan-progress` events.
+#[tauri::command]
+pub async fn index_library(app: AppHandle, path: String) -> Result<LibraryScanSummary, String> {
+    let handle = app.clone();
+    let summary = tauri::async_runtime::spawn_blocking(move || {
+        let state = handle.state::<LibraryManager>();
+        state.scan(&handle, Path::new(&path), |scanned, total| {
+            let _ = handle.emit(
+                "library-scan-progress",
+                LibraryScanProgress { scanned, total },
+            );
+        })
+    })
+    .await
+    .map_err(|e| format!("Library scan failed: {}", e))??;
+
+    let _ = app.emit("library-changed", &summary);
+    Ok(summary)
+}
+
+#[tauri::command]
+pub async fn search_library(
+    state: State<'_, LibraryManager>,
+    query: LibraryQuery,
+) -> Result<LibraryPage, String> {
+    Ok(state.search(&query))
+}
+
+#[tauri::command]
+pub async fn get_library_summary(
+    state: State<'_, LibraryManager>,
+) -> Result<LibrarySummary, String> {
+    Ok(state.summary())
+}
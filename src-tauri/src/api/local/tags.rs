@@ -0,0 +1,756 @@
+//! Minimal readers for the tags and stream info of the audio formats in the archive:
+//! MP3 (ID3v1/ID3v2), FLAC and Ogg Vorbis/Opus (Vorbis comments) and MP4/M4A (iTunes atoms).
+
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, Read, Seek, SeekFrom};
+use std::path::Path;
+
+/// Extensions of the files read by `read_tags`
+pub const AUDIO_EXTENSIONS: [&str; 7] = ["mp3", "flac", "ogg", "oga", "opus", "m4a", "mp4"];
+
+/// How far past the ID3v2 tag to look for the first MPEG frame
+const MPEG_SYNC_SEARCH_BYTES: usize = 64 * 1024;
+/// How much of the end of an Ogg file to search for the last page
+const OGG_TAIL_BYTES: u64 = 64 * 1024;
+/// Upper bound for a single atom or page read into memory, e.g. a `moov` with cover art
+const MAX_READ_BYTES: u64 = 64 * 1024 * 1024;
+
+/// Tags and stream info of an audio file, any of which may be missing
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioTags {
+    pub title: Option<String>,
+    pub artist: Option<String>,
+    pub album: Option<String>,
+    /// As tagged, e.g. `2023` or `2023-05-01`
+    pub date: Option<String>,
+    /// Seconds
+    pub duration: Option<f64>,
+    /// Average bitrate in kbit/s
+    pub bitrate: Option<u32>,
+}
+
+impl AudioTags {
+    /// Set a field from a Vorbis comment or equivalent key, keeping the first value
+    /// and joining repeated artists
+    fn set(&mut self, key: &str, value: &str) {
+        let value = value.trim_matches(|c: char| c == '\0' || c.is_whitespace());
+        if value.is_empty() {
+            return;
+        }
+        let field = match key.to_ascii_uppercase().as_str() {
+            "TITLE" => &mut self.title,
+            "ARTIST" => {
+                match &mut self.artist {
+                    Some(artist) => {
+                        artist.push_str("; ");
+                        artist.push_str(value);
+                    }
+                    None => self.artist = Some(value.to_string()),
+                }
+                return;
+            }
+            "ALBUM" => &mut self.album,
+            "DATE" | "YEAR" => &mut self.date,
+            _ => return,
+        };
+        if field.is_none() {
+            *field = Some(value.to_string());
+        }
+    }
+
+    /// Fill in the bitrate from the size of the audio data, when the stream doesn't say
+    fn set_average_bitrate(&mut self, audio_bytes: u64) {
+        if self.bitrate.is_none()
+            && let Some(duration) = self.duration.filter(|d| *d > 0.0)
+        {
+            self.bitrate = Some((audio_bytes as f64 * 8.0 / duration / 1000.0).round() as u32);
+        }
+    }
+}
+
+/// Whether `read_tags` can read the file, judging by its extension
+pub fn is_audio_file(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .is_some_and(|ext| AUDIO_EXTENSIONS.contains(&ext.to_ascii_lowercase().as_str()))
+}
+
+/// Read the tags and stream info of an audio file
+pub fn read_tags(path: &Path) -> Result<AudioTags, String> {
+    let file = File::open(path).map_err(|e| format!("Failed to open {}: {}", path.display(), e))?;
+    let size = file.metadata().map(|m| m.len()).unwrap_or(0);
+    let mut reader = BufReader::new(file);
+
+    let ext = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_ascii_lowercase();
+    let result = match ext.as_str() {
+        "mp3" => read_mp3(&mut reader, size),
+        "flac" => read_flac(&mut reader, size),
+        "ogg" | "oga" | "opus" => read_ogg(&mut reader, size),
+        "m4a" | "mp4" => read_mp4(&mut reader, size),
+        _ => return Err(format!("Unsupported audio format: {}", path.display())),
+    };
+    result.map_err(|e| format!("Failed to read tags of {}: {}", path.display(), e))
+}
+
+type Reader = BufReader<File>;
+
+fn read_exact_at(reader: &mut Reader, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+    if len > MAX_READ_BYTES {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!("{} bytes is too large to read", len),
+        ));
+    }
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut buf = vec![0; len as usize];
+    reader.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+/// Read up to `len` bytes, fewer at the end of the file
+fn read_at_most(reader: &mut Reader, offset: u64, len: u64) -> std::io::Result<Vec<u8>> {
+    reader.seek(SeekFrom::Start(offset))?;
+    let mut buf = Vec::new();
+    reader.take(len).read_to_end(&mut buf)?;
+    Ok(buf)
+}
+
+fn u32_be(b: &[u8]) -> u32 {
+    u32::from_be_bytes([b[0], b[1], b[2], b[3]])
+}
+
+fn u64_be(b: &[u8]) -> u64 {
+    u64::from_be_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+}
+
+fn u32_le(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+/// 28-bit integer stored in four bytes with the top bit of each cleared
+fn syncsafe(b: &[u8]) -> u32 {
+    b[..4]
+        .iter()
+        .fold(0, |n, byte| (n << 7) | u32::from(byte & 0x7F))
+}
+
+fn latin1(b: &[u8]) -> String {
+    b.iter().map(|&c| char::from(c)).collect()
+}
+
+fn utf16(b: &[u8], big_endian: bool) -> String {
+    let units: Vec<u16> = b
+        .chunks_exact(2)
+        .map(|c| {
+            if big_endian {
+                u16::from_be_bytes([c[0], c[1]])
+            } else {
+                u16::from_le_bytes([c[0], c[1]])
+            }
+        })
+        .collect();
+    String::from_utf16_lossy(&units)
+}
+
+// MP3
+
+/// Read an MP3's ID3 tags and work out its duration from the MPEG frames
+fn read_mp3(reader: &mut Reader, size: u64) -> std::io::Result<AudioTags> {
+    let mut tags = AudioTags::default();
+
+    let header = read_at_most(reader, 0, 10)?;
+    let mut audio_start = 0;
+    if header.len() == 10 && &header[..3] == b"ID3" {
+        let footer = if header[5] & 0x10 != 0 { 10 } else { 0 };
+        let tag_size = u64::from(syncsafe(&header[6..10]));
+        let tag = read_exact_at(reader, 10, tag_size.min(size.saturating_sub(10)))?;
+        read_id3v2(&mut tags, header[3], header[5], &tag);
+        audio_start = 10 + tag_size + footer;
+    }
+
+    // ID3v1 at the end fills in what ID3v2 didn't have
+    let mut audio_end = size;
+    if size >= 128 {
+        let v1 = read_exact_at(reader, size - 128, 128)?;
+        if &v1[..3] == b"TAG" {
+            audio_end -= 128;
+            let field = |range: std::ops::Range<usize>| {
+                latin1(&v1[range]).trim_end_matches(['\0', ' ']).to_string()
+            };
+            tags.set("TITLE", &field(3..33));
+            if tags.artist.is_none() {
+                tags.set("ARTIST", &field(33..63));
+            }
+            tags.set("ALBUM", &field(63..93));
+            tags.set("YEAR", &field(93..97));
+        }
+    }
+
+    let search = read_at_most(reader, audio_start, MPEG_SYNC_SEARCH_BYTES as u64)?;
+    if let Some((offset, frame)) = find_mpeg_frame(&search) {
+        let audio_bytes = audio_end.saturating_sub(audio_start + offset as u64);
+        frame.fill(&mut tags, &search[offset..], audio_bytes);
+    }
+    Ok(tags)
+}
+
+fn read_id3v2(tags: &mut AudioTags, version: u8, flags: u8, tag: &[u8]) {
+    // Tag-wide unsynchronisation, only used before v2.4
+    let data = if version < 4 && flags & 0x80 != 0 {
+        unsynchronise(tag)
+    } else {
+        tag.to_vec()
+    };
+
+    let mut pos = 0;
+    if flags & 0x40 != 0 && data.len() >= 4 {
+        // Extended header, v2.4 counts its own size field
+        pos = match version {
+            3 => u32_be(&data) as usize + 4,
+            4 => syncsafe(&data) as usize,
+            _ => 0,
+        };
+    }
+
+    let (id_len, header_len) = if version == 2 { (3, 6) } else { (4, 10) };
+    let mut length_ms = None;
+    while pos + header_len <= data.len() {
+        let header = &data[pos..pos + header_len];
+        if header[0] == 0 {
+            // Padding
+            break;
+        }
+        let id = latin1(&header[..id_len]);
+        let frame_size = match version {
+            2 => {
+                (usize::from(header[3]) << 16)
+                    | (usize::from(header[4]) << 8)
+                    | usize::from(header[5])
+            }
+            3 => u32_be(&header[4..8]) as usize,
+            _ => syncsafe(&header[4..8]) as usize,
+        };
+        let start = pos + header_len;
+        let end = (start + frame_size).min(data.len());
+        pos = start + frame_size;
+
+        let mut body = data[start..end].to_vec();
+        if version == 4 {
+            let format_flags = header[9];
+            // Compressed or encrypted
+            if format_flags & 0x0C != 0 {
+                continue;
+            }
+            if format_flags & 0x02 != 0 {
+                body = unsynchronise(&body);
+            }
+            // Data length indicator
+            if format_flags & 0x01 != 0 && body.len() >= 4 {
+                body.drain(..4);
+            }
+        } else if version == 3 && header[9] & 0xC0 != 0 {
+            continue;
+        }
+
+        let key = match id.as_str() {
+            "TIT2" | "TT2" => "TITLE",
+            "TPE1" | "TP1" => "ARTIST",
+            "TALB" | "TAL" => "ALBUM",
+            "TDRC" | "TYER" | "TYE" => "DATE",
+            "TLEN" | "TLE" => {
+                length_ms = id3_text(&body)
+                    .first()
+                    .and_then(|s| s.trim().parse::<f64>().ok());
+                continue;
+            }
+            _ => continue,
+        };
+        for value in id3_text(&body) {
+            tags.set(key, &value);
+        }
+    }
+
+    if let Some(ms) = length_ms.filter(|ms| *ms > 0.0) {
+        tags.duration = Some(ms / 1000.0);
+    }
+}
+
+/// Undo ID3 unsynchronisation, which inserts a zero byte after every 0xFF
+fn unsynchronise(data: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut previous = 0;
+    for &byte in data {
+        if !(previous == 0xFF && byte == 0) {
+            out.push(byte);
+        }
+        previous = byte;
+    }
+    out
+}
+
+/// Decode the values of an ID3 text frame
+fn id3_text(body: &[u8]) -> Vec<String> {
+    let Some((&encoding, text)) = body.split_first() else {
+        return Vec::new();
+    };
+    let text = match encoding {
+        0 => latin1(text),
+        1 => match text {
+            [0xFE, 0xFF, rest @ ..] => utf16(rest, true),
+            [0xFF, 0xFE, rest @ ..] => utf16(rest, false),
+            _ => utf16(text, false),
+        },
+        2 => utf16(text, true),
+        _ => String::from_utf8_lossy(text).into_owned(),
+    };
+    // Values are separated by nulls, UTF-16 ones may carry a BOM each
+    text.split('\0')
+        .map(|value| value.trim_start_matches('\u{FEFF}').trim().to_string())
+        .filter(|value| !value.is_empty())
+        .collect()
+}
+
+struct MpegFrame {
+    mpeg1: bool,
+    layer: u8,
+    /// kbit/s
+    bitrate: u32,
+    sample_rate: u32,
+    mono: bool,
+}
+
+impl MpegFrame {
+    fn parse(h: &[u8]) -> Option<Self> {
+        if h.len() < 4 || h[0] != 0xFF || h[1] & 0xE0 != 0xE0 {
+            return None;
+        }
+        let version = (h[1] >> 3) & 0x03;
+        let layer = match (h[1] >> 1) & 0x03 {
+            3 => 1,
+            2 => 2,
+            1 => 3,
+            _ => return None,
+        };
+        let bitrate_index = usize::from(h[2] >> 4);
+        let rate_index = usize::from((h[2] >> 2) & 0x03);
+        if version == 1 || bitrate_index == 0 || bitrate_index == 15 || rate_index == 3 {
+            return None;
+        }
+
+        const V1_L1: [u32; 15] = [
+            0, 32, 64, 96, 128, 160, 192, 224, 256, 288, 320, 352, 384, 416, 448,
+        ];
+        const V1_L2: [u32; 15] = [
+            0, 32, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320, 384,
+        ];
+        const V1_L3: [u32; 15] = [
+            0, 32, 40, 48, 56, 64, 80, 96, 112, 128, 160, 192, 224, 256, 320,
+        ];
+        const V2_L1: [u32; 15] = [
+            0, 32, 48, 56, 64, 80, 96, 112, 128, 144, 160, 176, 192, 224, 256,
+        ];
+        const V2_L23: [u32; 15] = [0, 8, 16, 24, 32, 40, 48, 56, 64, 80, 96, 112, 128, 144, 160];
+
+        let mpeg1 = version == 3;
+        let bitrate = match (mpeg1, layer) {
+            (true, 1) => V1_L1,
+            (true, 2) => V1_L2,
+            (true, _) => V1_L3,
+            (false, 1) => V2_L1,
+            (false, _) => V2_L23,
+        }[bitrate_index];
+        let sample_rate = [44100, 48000, 32000][rate_index]
+            >> match version {
+                3 => 0,
+                2 => 1,
+                _ => 2,
+            };
+
+        Some(Self {
+            mpeg1,
+            layer,
+            bitrate,
+            sample_rate,
+            mono: h[3] >> 6 == 3,
+        })
+    }
+
+    fn samples_per_frame(&self) -> u32 {
+        match (self.layer, self.mpeg1) {
+            (1, _) => 384,
+            (2, _) | (3, true) => 1152,
+            _ => 576,
+        }
+    }
+
+    /// Work out duration and bitrate from a Xing/Info or VBRI header in the first
+    /// frame, or from the frame's bitrate for constant bitrate files
+    fn fill(&self, tags: &mut AudioTags, frame: &[u8], audio_bytes: u64) {
+        let side_info = match (self.mpeg1, self.mono) {
+            (true, false) => 32,
+            (true, true) | (false, false) => 17,
+            (false, true) => 9,
+        };
+        let xing = 4 + side_info;
+        let mut frames = None;
+        let mut bytes = None;
+
+        if frame.len() >= xing + 16 && matches!(&frame[xing..xing + 4], b"Xing" | b"Info") {
+            let flags = u32_be(&frame[xing + 4..]);
+            let mut pos = xing + 8;
+            if flags & 0x01 != 0 {
+                frames = Some(u32_be(&frame[pos..]));
+                pos += 4;
+            }
+            if flags & 0x02 != 0 && frame.len() >= pos + 4 {
+                bytes = Some(u32_be(&frame[pos..]));
+            }
+        } else if frame.len() >= 36 + 18 && &frame[36..40] == b"VBRI" {
+            bytes = Some(u32_be(&frame[46..]));
+            frames = Some(u32_be(&frame[50..]));
+        }
+
+        match frames.filter(|f| *f > 0) {
+            Some(frames) => {
+                let duration = f64::from(frames) * f64::from(self.samples_per_frame())
+                    / f64::from(self.sample_rate);
+                tags.duration = Some(duration);
+                tags.bitrate = None;
+                tags.set_average_bitrate(bytes.map_or(audio_bytes, u64::from));
+            }
+            None => {
+                tags.bitrate = Some(self.bitrate);
+                if tags.duration.is_none() {
+                    tags.duration =
+                        Some(audio_bytes as f64 * 8.0 / (f64::from(self.bitrate) * 1000.0));
+                }
+            }
+        }
+    }
+}
+
+/// Find the first MPEG audio frame header, skipping junk after the tag
+fn find_mpeg_frame(data: &[u8]) -> Option<(usize, MpegFrame)> {
+    (0..data.len().saturating_sub(4))
+        .find_map(|offset| MpegFrame::parse(&data[offset..]).map(|frame| (offset, frame)))
+}
+
+// Vorbis comments, used by FLAC and Ogg
+
+fn read_vorbis_comments(tags: &mut AudioTags, data: &[u8]) {
+    let mut pos: usize = 0;
+    let mut next = move |len: usize| {
+        let end = pos.checked_add(len)?;
+        let slice = data.get(pos..end)?;
+        pos = end;
+        Some(slice)
+    };
+
+    let Some(vendor_len) = next(4).map(u32_le) else {
+        return;
+    };
+    if next(vendor_len as usize).is_none() {
+        return;
+    }
+    let Some(count) = next(4).map(u32_le) else {
+        return;
+    };
+    for _ in 0..count {
+        let Some(len) = next(4).map(u32_le) else {
+            return;
+        };
+        let Some(comment) = next(len as usize) else {
+            return;
+        };
+        if let Some((key, value)) = String::from_utf8_lossy(comment).split_once('=') {
+            tags.set(key, value);
+        }
+    }
+}
+
+// FLAC
+
+fn read_flac(reader: &mut Reader, size: u64) -> std::io::Result<AudioTags> {
+    let mut tags = AudioTags::default();
+
+    // Some encoders put an ID3v2 tag in front
+    let mut pos = 0;
+    let header = read_at_most(reader, 0, 10)?;
+    if header.len() == 10 && &header[..3] == b"ID3" {
+        pos = 10 + u64::from(syncsafe(&header[6..10]));
+    }
+    if read_exact_at(reader, pos, 4)? != b"fLaC" {
+        return Err(invalid("not a FLAC file"));
+    }
+    pos += 4;
+
+    loop {
+        let block_header = read_exact_at(reader, pos, 4)?;
+        let last = block_header[0] & 0x80 != 0;
+        let block_type = block_header[0] & 0x7F;
+        let len = u64::from(u32_be(&[
+            0,
+            block_header[1],
+            block_header[2],
+            block_header[3],
+        ]));
+        pos += 4;
+
+        match block_type {
+            0 => {
+                let info = read_exact_at(reader, pos, len)?;
+                if info.len() >= 18 {
+                    let sample_rate = (u32::from(info[10]) << 12)
+                        | (u32::from(info[11]) << 4)
+                        | (u32::from(info[12]) >> 4);
+                    let samples =
+                        (u64::from(info[13] & 0x0F) << 32) | u64::from(u32_be(&info[14..18]));
+                    if sample_rate > 0 && samples > 0 {
+                        tags.duration = Some(samples as f64 / f64::from(sample_rate));
+                    }
+                }
+            }
+            4 => read_vorbis_comments(&mut tags, &read_exact_at(reader, pos, len)?),
+            _ => {}
+        }
+
+        pos += len;
+        if last || pos >= size {
+            break;
+        }
+    }
+
+    tags.set_average_bitrate(size.saturating_sub(pos));
+    Ok(tags)
+}
+
+// Ogg
+
+struct OggPage {
+    serial: u32,
+    /// Segment lengths, a segment shorter than 255 ends a packet
+    segments: Vec<u8>,
+    data: Vec<u8>,
+}
+
+fn read_ogg_page(reader: &mut Reader, offset: u64) -> std::io::Result<OggPage> {
+    let header = read_exact_at(reader, offset, 27)?;
+    if &header[..4] != b"OggS" {
+        return Err(invalid("missing Ogg page"));
+    }
+    let segment_count = u64::from(header[26]);
+    let segments = read_exact_at(reader, offset + 27, segment_count)?;
+    let data_len = segments.iter().map(|&s| u64::from(s)).sum();
+    let data = read_exact_at(reader, offset + 27 + segment_count, data_len)?;
+    Ok(OggPage {
+        serial: u32_le(&header[14..18]),
+        segments,
+        data,
+    })
+}
+
+enum OggCodec {
+    Vorbis { sample_rate: u32 },
+    Opus { pre_skip: u16 },
+}
+
+fn read_ogg(reader: &mut Reader, size: u64) -> std::io::Result<AudioTags> {
+    let mut tags = AudioTags::default();
+
+    // The first two packets of the first stream are its identification and comment headers
+    let mut packets: Vec<Vec<u8>> = Vec::new();
+    let mut packet = Vec::new();
+    let mut offset = 0;
+    let mut serial = None;
+    while packets.len() < 2 && offset < size {
+        let page = read_ogg_page(reader, offset)?;
+        offset += 27 + page.segments.len() as u64 + page.data.len() as u64;
+        if *serial.get_or_insert(page.serial) != page.serial {
+            continue;
+        }
+        let mut pos = 0;
+        for &segment in &page.segments {
+            packet.extend_from_slice(&page.data[pos..pos + usize::from(segment)]);
+            pos += usize::from(segment);
+            if segment < 255 {
+                packets.push(std::mem::take(&mut packet));
+            }
+        }
+    }
+    let (Some(ident), Some(comments)) = (packets.first(), packets.get(1)) else {
+        return Err(invalid("missing Ogg headers"));
+    };
+
+    let codec = if ident.starts_with(b"\x01vorbis") && ident.len() >= 28 {
+        let nominal = u32_le(&ident[20..]) as i32;
+        if nominal > 0 {
+            tags.bitrate = Some((nominal as f64 / 1000.0).round() as u32);
+        }
+        OggCodec::Vorbis {
+            sample_rate: u32_le(&ident[12..]),
+        }
+    } else if ident.starts_with(b"OpusHead") && ident.len() >= 12 {
+        OggCodec::Opus {
+            pre_skip: u16::from_le_bytes([ident[10], ident[11]]),
+        }
+    } else {
+        return Err(invalid("unsupported Ogg codec"));
+    };
+
+    if let Some(body) = comments.strip_prefix(b"\x03vorbis".as_slice()) {
+        read_vorbis_comments(&mut tags, body);
+    } else if let Some(body) = comments.strip_prefix(b"OpusTags".as_slice()) {
+        read_vorbis_comments(&mut tags, body);
+    }
+
+    // The granule position of the last page is the stream length in samples
+    let tail_start = size.saturating_sub(OGG_TAIL_BYTES);
+    let tail = read_at_most(reader, tail_start, OGG_TAIL_BYTES)?;
+    let last_granule = (0..tail.len().saturating_sub(27))
+        .rev()
+        .filter(|&i| &tail[i..i + 4] == b"OggS")
+        .find(|&i| Some(u32_le(&tail[i + 14..])) == serial)
+        .map(|i| i64::from_le_bytes(tail[i + 6..i + 14].try_into().unwrap_or_default()));
+
+    if let Some(granule) = last_granule.filter(|g| *g > 0) {
+        let duration = match codec {
+            OggCodec::Vorbis { sample_rate } if sample_rate > 0 => {
+                Some(granule as f64 / f64::from(sample_rate))
+            }
+            // Opus always counts at 48 kHz
+            OggCodec::Opus { pre_skip } => Some((granule - i64::from(pre_skip)) as f64 / 48000.0),
+            _ => None,
+        };
+        tags.duration = duration.filter(|d| *d > 0.0);
+    }
+
+    tags.set_average_bitrate(size.saturating_sub(offset));
+    Ok(tags)
+}
+
+// MP4
+
+struct Atom {
+    kind: [u8; 4],
+    /// Offset of the atom's content
+    start: u64,
+    /// Offset just past the atom
+    end: u64,
+}
+
+/// Read the atom header at `offset`, `None` at the end of `limit`
+fn read_atom(reader: &mut Reader, offset: u64, limit: u64) -> std::io::Result<Option<Atom>> {
+    if offset.saturating_add(8) > limit {
+        return Ok(None);
+    }
+    let header = read_exact_at(reader, offset, 8)?;
+    let kind = [header[4], header[5], header[6], header[7]];
+    let (start, size) = match u32_be(&header) {
+        // Extends to the end
+        0 => (offset + 8, limit - offset),
+        1 => (offset + 16, u64_be(&read_exact_at(reader, offset + 8, 8)?)),
+        size => (offset + 8, u64::from(size)),
+    };
+    // Sizes come from the file, a 64-bit one can point anywhere
+    let end = offset
+        .checked_add(size)
+        .filter(|end| size >= start - offset && *end >= start)
+        .ok_or_else(|| invalid("invalid atom size"))?;
+    Ok(Some(Atom {
+        kind,
+        start,
+        end: end.min(limit),
+    }))
+}
+
+/// Find the child atoms of the given kinds among the atoms in `data`
+fn child_atoms<'a>(data: &'a [u8], kind: &[u8; 4]) -> impl Iterator<Item = &'a [u8]> {
+    let mut pos = 0;
+    std::iter::from_fn(move || {
+        while pos + 8 <= data.len() {
+            let size = (u32_be(&data[pos..]) as usize).max(8);
+            let end = (pos + size).min(data.len());
+            let matches = &data[pos + 4..pos + 8] == kind;
+            let content = &data[pos + 8..end];
+            pos = end;
+            if matches {
+                return Some(content);
+            }
+        }
+        None
+    })
+}
+
+fn child_atom<'a>(data: &'a [u8], kind: &[u8; 4]) -> Option<&'a [u8]> {
+    child_atoms(data, kind).next()
+}
+
+fn read_mp4(reader: &mut Reader, size: u64) -> std::io::Result<AudioTags> {
+    let mut tags = AudioTags::default();
+
+    let mut moov = None;
+    let mut media_bytes = 0;
+    let mut offset = 0;
+    while let Some(atom) = read_atom(reader, offset, size)? {
+        match &atom.kind {
+            b"moov" => {
+                let len = atom.end.saturating_sub(atom.start);
+                moov = Some(read_exact_at(reader, atom.start, len)?);
+            }
+            b"mdat" => media_bytes += atom.end.saturating_sub(atom.start),
+            _ => {}
+        }
+        offset = atom.end;
+    }
+    let moov = moov.ok_or_else(|| invalid("missing moov atom"))?;
+
+    if let Some(mvhd) = child_atom(&moov, b"mvhd") {
+        let (timescale, duration) = match mvhd.first() {
+            Some(1) if mvhd.len() >= 32 => (u32_be(&mvhd[20..]), u64_be(&mvhd[24..])),
+            Some(_) if mvhd.len() >= 20 => (u32_be(&mvhd[12..]), u64::from(u32_be(&mvhd[16..]))),
+            _ => (0, 0),
+        };
+        if timescale > 0 && duration > 0 {
+            tags.duration = Some(duration as f64 / f64::from(timescale));
+        }
+    }
+
+    let meta = child_atom(&moov, b"udta").and_then(|udta| child_atom(udta, b"meta"));
+    if let Some(meta) = meta {
+        // iTunes files give `meta` a version and flags, QuickTime ones don't
+        let meta = if meta.len() >= 8 && &meta[4..8] == b"hdlr" {
+            meta
+        } else {
+            meta.get(4..).unwrap_or_default()
+        };
+        if let Some(ilst) = child_atom(meta, b"ilst") {
+            for (kind, key) in [
+                (b"\xA9nam", "TITLE"),
+                (b"\xA9ART", "ARTIST"),
+                (b"\xA9alb", "ALBUM"),
+                (b"\xA9day", "DATE"),
+            ] {
+                let values = child_atoms(ilst, kind).filter_map(|item| child_atom(item, b"data"));
+                for data in values {
+                    // Type 1 is UTF-8 text, after the type and locale
+                    if data.len() > 8 && u32_be(data) & 0x00FF_FFFF == 1 {
+                        tags.set(key, &String::from_utf8_lossy(&data[8..]));
+                    }
+                }
+            }
+        }
+    }
+
+    tags.set_average_bitrate(if media_bytes > 0 { media_bytes } else { size });
+    Ok(tags)
+}
+
+fn invalid(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message.to_string())
+}
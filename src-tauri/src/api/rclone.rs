@@ -4,7 +4,10 @@ use tauri_plugin_shell::process::Command;
 
 pub mod logs;
 pub mod server;
+pub mod standalone;
 pub mod stats;
+pub mod supervisor;
+pub mod tail;
 
 pub fn get_rclone_command(app: &AppHandle) -> Result<Command, String> {
     #[cfg(target_os = "android")]
@@ -38,10 +41,21 @@ pub fn get_rclone_command(app: &AppHandle) -> Result<Command, String> {
 }
 
 // Command functions
+pub use logs::__cmd__export_logs;
 pub use server::__cmd__stop_rc_server;
 pub use stats::__cmd__get_stats;
+pub use supervisor::__cmd__get_last_rclone_crash;
+pub use tail::{__cmd__get_log_backlog, __cmd__set_log_stream_level};
 
 // Functions
-pub use logs::LogManager;
-pub use server::{get_sdk_client, is_server_running, stop_rc_server};
+pub use logs::{
+    FileChanges, FileError, LogEvent, LogFilter, LogLevel, LogManager, LogSpan, RenamedFile,
+    SkippedAction, export_logs,
+};
+pub use server::{
+    RcCredentials, RcServerState, ensure_server, get_sdk_client, is_server_running, stop_rc_server,
+};
+pub use standalone::StandaloneServer;
 pub use stats::get_stats;
+pub use supervisor::{RcCrash, get_last_rclone_crash};
+pub use tail::{LogBatch, LogTailer, get_log_backlog, set_log_stream_level};
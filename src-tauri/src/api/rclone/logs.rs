@@ -1,33 +1,249 @@
-use regex::Regex;
-use std::path::PathBuf;
-use std::sync::OnceLock;
-use tauri::{AppHandle, Manager};
+use crate::service::PathProvider;
+use crate::utils::parse_size;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{LazyLock, Mutex};
+use tauri::AppHandle;
 use tokio::io::{AsyncBufReadExt, AsyncSeekExt};
 
+/// Number of previous sessions kept as `rclone.log.1`, `rclone.log.2`, ...
+const MAX_ROTATED_LOGS: usize = 5;
+/// Combined size above which the oldest rotated logs are removed
+const MAX_ROTATED_LOGS_SIZE: u64 = 50 * 1024 * 1024;
+
+/// Open log spans, and whether another job wrote to the log while each was open
+static OPEN_SPANS: LazyLock<Mutex<HashMap<u64, bool>>> = LazyLock::new(Default::default);
+static NEXT_SPAN_ID: AtomicU64 = AtomicU64::new(0);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum LogLevel {
+    Debug,
+    Info,
+    Notice,
+    Warning,
+    Error,
+    Critical,
+}
+
+impl LogLevel {
+    fn parse(level: &str) -> Self {
+        match level.to_ascii_lowercase().as_str() {
+            "debug" => Self::Debug,
+            "notice" => Self::Notice,
+            "warning" | "warn" => Self::Warning,
+            "error" => Self::Error,
+            "critical" | "alert" | "emergency" | "fatal" => Self::Critical,
+            _ => Self::Info,
+        }
+    }
+}
+
+/// A single record from the rclone JSON log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogEvent {
+    pub level: LogLevel,
+    /// The file or directory the record is about, if any
+    pub object: Option<String>,
+    pub message: String,
+    pub timestamp: String,
+}
+
+/// Record as written by `--use-json-log`
+#[derive(Deserialize)]
+struct RawLogRecord {
+    #[serde(default)]
+    time: String,
+    #[serde(default)]
+    level: String,
+    #[serde(default)]
+    msg: String,
+    object: Option<String>,
+}
+
+impl LogEvent {
+    /// Parse a line of the log file, skipping anything that isn't a JSON record
+    pub fn parse(line: &str) -> Option<Self> {
+        let raw: RawLogRecord = serde_json::from_str(line.trim()).ok()?;
+        Some(Self {
+            level: LogLevel::parse(&raw.level),
+            object: raw.object.filter(|o| !o.is_empty()),
+            message: raw.msg.trim_end().to_string(),
+            timestamp: raw.time,
+        })
+    }
+}
+
+/// The part of the log written while an rclone job runs, from `offset` on.
+/// rclone's JSON log doesn't say which job wrote a record, so the records only
+/// belong to the job if no other job ran at the same time.
+pub struct LogSpan {
+    id: u64,
+    pub offset: u64,
+}
+
+impl LogSpan {
+    /// Open a span at the end of the log, before the job starts
+    pub async fn open(paths: &impl PathProvider) -> Self {
+        let offset = LogManager::get_current_offset(paths).await;
+        let id = NEXT_SPAN_ID.fetch_add(1, Ordering::Relaxed);
+
+        let mut spans = OPEN_SPANS.lock().unwrap_or_else(|e| e.into_inner());
+        let shared = !spans.is_empty();
+        for other in spans.values_mut() {
+            *other |= shared;
+        }
+        spans.insert(id, shared);
+
+        Self { id, offset }
+    }
+
+    /// Whether only this span's job wrote to the log since it was opened
+    pub fn is_exclusive(&self) -> bool {
+        let spans = OPEN_SPANS.lock().unwrap_or_else(|e| e.into_inner());
+        spans.get(&self.id).is_some_and(|shared| !shared)
+    }
+}
+
+impl Drop for LogSpan {
+    fn drop(&mut self) {
+        let mut spans = OPEN_SPANS.lock().unwrap_or_else(|e| e.into_inner());
+        spans.remove(&self.id);
+    }
+}
+
+/// Which log events to keep. rclone's JSON log doesn't say which job wrote a
+/// record, so events can't be filtered by job.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogFilter {
+    pub min_level: Option<LogLevel>,
+}
+
+impl LogFilter {
+    pub fn matches(&self, event: &LogEvent) -> bool {
+        self.min_level.is_none_or(|min| event.level >= min)
+    }
+}
+
+/// A file that was renamed during a sync
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RenamedFile {
+    pub from: String,
+    pub to: String,
+}
+
+/// An error rclone reported for a single file
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileError {
+    pub path: String,
+    pub error: String,
+}
+
+/// An action a dry run reported it would have taken
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SkippedAction {
+    pub path: String,
+    /// What rclone would have done, e.g. `copy`, `delete` or `move`
+    pub action: String,
+    pub size: Option<u64>,
+}
+
+/// Files changed by a sync, as reported in the log
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileChanges {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub deleted: Vec<String>,
+    pub renamed: Vec<RenamedFile>,
+    pub errors: Vec<FileError>,
+}
+
 pub struct LogManager;
 
 impl LogManager {
     /// Get the path to the rclone log file.
-    pub fn get_log_path(app: &AppHandle) -> Result<PathBuf, String> {
-        Ok(app
-            .path()
-            .app_local_data_dir()
-            .map_err(|e| format!("Failed to get app data dir: {}", e))?
-            .join("rclone.log"))
-    }
-
-    /// Clear the log file (e.g., on server startup).
-    pub async fn clear(app: &AppHandle) -> Result<(), String> {
-        let path = Self::get_log_path(app)?;
-        if path.exists() {
-            let _ = tokio::fs::remove_file(&path).await;
+    pub fn get_log_path(paths: &impl PathProvider) -> Result<PathBuf, String> {
+        paths.rclone_log_path()
+    }
+
+    /// Get the path to a rotated log file, `1` being the previous session.
+    fn get_rotated_log_path(paths: &impl PathProvider, index: usize) -> Result<PathBuf, String> {
+        let path = Self::get_log_path(paths)?;
+        Ok(path.with_extension(format!("log.{}", index)))
+    }
+
+    /// Get the current and rotated log files that exist, newest first, followed by the app log.
+    fn get_log_files(paths: &impl PathProvider) -> Result<Vec<PathBuf>, String> {
+        let mut files = vec![Self::get_log_path(paths)?];
+        for index in 1..=MAX_ROTATED_LOGS {
+            files.push(Self::get_rotated_log_path(paths, index)?);
+        }
+        files.push(crate::logging::get_log_path(paths)?);
+        Ok(files.into_iter().filter(|path| path.exists()).collect())
+    }
+
+    /// Move the current log aside as `rclone.log.1` (e.g., on server startup),
+    /// dropping the oldest sessions past the count and size limits.
+    pub async fn rotate(paths: &impl PathProvider) -> Result<(), String> {
+        let path = Self::get_log_path(paths)?;
+        if !path.exists() {
+            return Ok(());
         }
+
+        let _ = tokio::fs::remove_file(Self::get_rotated_log_path(paths, MAX_ROTATED_LOGS)?).await;
+        for index in (1..MAX_ROTATED_LOGS).rev() {
+            let from = Self::get_rotated_log_path(paths, index)?;
+            if from.exists() {
+                let to = Self::get_rotated_log_path(paths, index + 1)?;
+                tokio::fs::rename(&from, &to)
+                    .await
+                    .map_err(|e| format!("Failed to rotate {}: {}", from.display(), e))?;
+            }
+        }
+        tokio::fs::rename(&path, Self::get_rotated_log_path(paths, 1)?)
+            .await
+            .map_err(|e| format!("Failed to rotate log file: {}", e))?;
+
+        // Always keep the previous session, even if it is over the limit on its own
+        let mut total_size = 0;
+        for index in 1..=MAX_ROTATED_LOGS {
+            let rotated = Self::get_rotated_log_path(paths, index)?;
+            let Ok(metadata) = tokio::fs::metadata(&rotated).await else {
+                continue;
+            };
+            total_size += metadata.len();
+            if index > 1 && total_size > MAX_ROTATED_LOGS_SIZE {
+                let _ = tokio::fs::remove_file(&rotated).await;
+            }
+        }
+
         Ok(())
     }
 
+    /// Bundle the current and rotated logs into a zip archive at `destination`.
+    pub async fn export(paths: &impl PathProvider, destination: PathBuf) -> Result<(), String> {
+        let files = Self::get_log_files(paths)?;
+        if files.is_empty() {
+            return Err("No logs to export".to_string());
+        }
+
+        tokio::task::spawn_blocking(move || write_zip(&destination, &files))
+            .await
+            .map_err(|e| e.to_string())?
+    }
+
     /// Get the current size of the log file to use as an offset.
-    pub async fn get_current_offset(app: &AppHandle) -> u64 {
-        match Self::get_log_path(app) {
+    pub async fn get_current_offset(paths: &impl PathProvider) -> u64 {
+        match Self::get_log_path(paths) {
             Ok(path) if path.exists() => tokio::fs::metadata(&path)
                 .await
                 .map(|m| m.len())
@@ -36,12 +252,12 @@ impl LogManager {
         }
     }
 
-    /// Parse the log file from a given offset for deleted files.
-    pub async fn parse_deleted_files(
-        app: &AppHandle,
+    /// Read the log events written after a given offset.
+    pub async fn read_events(
+        paths: &impl PathProvider,
         start_offset: u64,
-    ) -> Result<Vec<String>, String> {
-        let log_path = Self::get_log_path(app)?;
+    ) -> Result<Vec<LogEvent>, String> {
+        let log_path = Self::get_log_path(paths)?;
         if !log_path.exists() {
             return Ok(vec![]);
         }
@@ -58,34 +274,131 @@ impl LogManager {
 
         let reader = tokio::io::BufReader::new(file);
         let mut lines = reader.lines();
-        let mut deleted_files = Vec::new();
+        let mut events = Vec::new();
 
         while let Some(line) = lines
             .next_line()
             .await
             .map_err(|e| format!("Failed to read log line: {}", e))?
         {
-            if line.contains("Skipped delete as --dry-run is set") {
-                if let Some(file_path) = Self::extract_deleted_file_path(&line) {
-                    deleted_files.push(file_path);
+            if let Some(event) = LogEvent::parse(&line) {
+                events.push(event);
+            }
+        }
+
+        Ok(events)
+    }
+
+    /// Read the log events written after a given offset that match a filter.
+    pub async fn read_filtered_events(
+        paths: &impl PathProvider,
+        start_offset: u64,
+        filter: &LogFilter,
+    ) -> Result<Vec<LogEvent>, String> {
+        let mut events = Self::read_events(paths, start_offset).await?;
+        events.retain(|event| filter.matches(event));
+        Ok(events)
+    }
+
+    /// Parse the log file from a given offset for actions skipped by a dry run.
+    pub async fn parse_dry_run_actions(
+        paths: &impl PathProvider,
+        start_offset: u64,
+    ) -> Result<Vec<SkippedAction>, String> {
+        let mut actions = Vec::new();
+
+        for event in Self::read_events(paths, start_offset).await? {
+            let Some(path) = event.object else {
+                continue;
+            };
+            let Some(message) = event.message.strip_prefix("Skipped ") else {
+                continue;
+            };
+            let Some((action, details)) = message.split_once(" as --dry-run is set") else {
+                continue;
+            };
+
+            let size = details
+                .trim()
+                .strip_prefix("(size ")
+                .and_then(|s| s.strip_suffix(')'))
+                .and_then(parse_size);
+
+            actions.push(SkippedAction {
+                path,
+                action: action.to_string(),
+                size,
+            });
+        }
+
+        Ok(actions)
+    }
+
+    /// Parse the log file from a given offset for files added, updated, deleted or renamed.
+    pub async fn parse_file_changes(
+        paths: &impl PathProvider,
+        start_offset: u64,
+    ) -> Result<FileChanges, String> {
+        let mut changes = FileChanges::default();
+
+        for event in Self::read_events(paths, start_offset).await? {
+            let Some(path) = event.object else {
+                continue;
+            };
+
+            match event.message.as_str() {
+                "Copied (new)" => changes.added.push(path),
+                "Copied (replaced existing)" => changes.updated.push(path),
+                "Deleted" => changes.deleted.push(path),
+                // Moved without a new name, i.e. into the backup directory
+                "Moved (server-side)" => changes.deleted.push(path),
+                message => {
+                    if let Some(to) = message.strip_prefix("Moved (server-side) to: ") {
+                        changes.renamed.push(RenamedFile {
+                            from: path,
+                            to: to.to_string(),
+                        });
+                    } else if event.level >= LogLevel::Error {
+                        changes.errors.push(FileError {
+                            path,
+                            error: message.to_string(),
+                        });
+                    }
                 }
             }
         }
 
-        Ok(deleted_files)
+        Ok(changes)
     }
+}
+
+/// Write the given files into a new zip archive, flattened to their file names
+fn write_zip(destination: &Path, files: &[PathBuf]) -> Result<(), String> {
+    let file = std::fs::File::create(destination)
+        .map_err(|e| format!("Failed to create {}: {}", destination.display(), e))?;
+    let mut zip = zip::ZipWriter::new(file);
+    let options = zip::write::SimpleFileOptions::default()
+        .compression_method(zip::CompressionMethod::Deflated);
 
-    fn extract_deleted_file_path(line: &str) -> Option<String> {
-        static RE: OnceLock<Regex> = OnceLock::new();
-        let re = RE.get_or_init(|| {
-            // Pattern: date time LEVEL: filename: message
-            // Capture the filename part between LEVEL: and the next colon
-            Regex::new(r"^\d{4}/\d{2}/\d{2} \d{2}:\d{2}:\d{2} (?:NOTICE|INFO|ERROR):\s+([^:]+):")
-                .unwrap()
-        });
+    for path in files {
+        let name = path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .ok_or_else(|| format!("Invalid log path: {}", path.display()))?;
+        let content =
+            std::fs::read(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
 
-        re.captures(line)
-            .and_then(|caps| caps.get(1))
-            .map(|m| m.as_str().trim().to_string())
+        zip.start_file(name, options).map_err(|e| e.to_string())?;
+        zip.write_all(&content).map_err(|e| e.to_string())?;
     }
+
+    zip.finish()
+        .map_err(|e| format!("Failed to write {}: {}", destination.display(), e))?;
+    Ok(())
+}
+
+/// Export the current and previous rclone logs as a zip bundle for bug reports
+#[tauri::command]
+pub async fn export_logs(app: AppHandle, destination: String) -> Result<(), String> {
+    LogManager::export(&app, PathBuf::from(destination)).await
 }
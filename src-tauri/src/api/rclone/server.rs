@@ -1,66 +1,280 @@
 use super::LogManager;
+use super::supervisor::{self, RcCrash};
+use rand::Rng;
+use rand::distr::Alphanumeric;
 use rclone_sdk::Client;
+use std::collections::HashMap;
+use std::path::Path;
 use std::sync::LazyLock;
-use std::time::Duration;
+use std::sync::atomic::AtomicU32;
+use std::time::{Duration, Instant};
 use tauri::{AppHandle, Manager};
 use tokio::sync::Mutex;
 
-pub const RC_PORT: u16 = 5572;
-pub const RC_URL: &str = "http://localhost:5572";
+const CREDENTIAL_LENGTH: usize = 32;
+/// Ports tried before giving up when foreign processes keep answering
+const MAX_START_ATTEMPTS: usize = 3;
 
+pub(super) static STARTUP_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
 static SHUTDOWN_LOCK: LazyLock<Mutex<()>> = LazyLock::new(|| Mutex::new(()));
 
-/// Helper to check if the RC server is listening
-pub async fn is_server_running() -> bool {
-    let client = Client::new(RC_URL);
+/// Address and login of the RC server started by this app instance
+#[derive(Clone)]
+pub struct RcCredentials {
+    pub port: u16,
+    pub user: String,
+    pub pass: String,
+}
+
+impl RcCredentials {
+    /// Pick a free port and generate a random login
+    pub(super) fn generate() -> Result<Self, String> {
+        // Let the OS choose a free port, then release it for rclone
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")
+            .map_err(|e| format!("Failed to find a free port: {}", e))?;
+        let port = listener
+            .local_addr()
+            .map_err(|e| format!("Failed to find a free port: {}", e))?
+            .port();
+
+        Ok(Self {
+            port,
+            user: random_string(),
+            pass: random_string(),
+        })
+    }
+
+    pub fn url(&self) -> String {
+        format!("http://127.0.0.1:{}", self.port)
+    }
+
+    /// Build an SDK client sending the login with every request.
+    /// reqwest moves the user info in the base URL into a Basic auth header.
+    pub fn client(&self) -> Client {
+        Client::new(&format!(
+            "http://{}:{}@127.0.0.1:{}",
+            self.user, self.pass, self.port
+        ))
+    }
+}
+
+fn random_string() -> String {
+    rand::rng()
+        .sample_iter(&Alphanumeric)
+        .take(CREDENTIAL_LENGTH)
+        .map(char::from)
+        .collect()
+}
+
+/// The rcd process started by this app instance
+#[derive(Clone)]
+pub(super) struct RcSession {
+    credentials: RcCredentials,
+    pub(super) pid: u32,
+    pub(super) child: crate::SharedChild,
+    pub(super) started_at: Instant,
+    /// Whether the server answered once, a session exiting before that failed to start
+    ready: bool,
+}
+
+/// Session of the running RC server, if one was started
+#[derive(Default)]
+pub struct RcServerState {
+    session: std::sync::Mutex<Option<RcSession>>,
+    pub(super) last_crash: std::sync::Mutex<Option<RcCrash>>,
+    /// Crashes in a row without the server staying up for a while in between
+    pub(super) consecutive_crashes: AtomicU32,
+    /// Crash counts of dead sessions replaced before the supervisor handled their exit, by pid
+    pub(super) replaced_crashes: std::sync::Mutex<HashMap<u32, u32>>,
+}
+
+impl RcServerState {
+    pub fn credentials(&self) -> Option<RcCredentials> {
+        self.session().map(|session| session.credentials)
+    }
+
+    fn session(&self) -> Option<RcSession> {
+        self.session.lock().ok().and_then(|s| s.clone())
+    }
+
+    fn set_session(&self, session: RcSession) {
+        if let Ok(mut lock) = self.session.lock() {
+            *lock = Some(session);
+        }
+    }
+
+    fn take_session(&self) -> Option<RcSession> {
+        self.session.lock().ok().and_then(|mut s| s.take())
+    }
+
+    fn mark_ready(&self) {
+        if let Ok(mut lock) = self.session.lock()
+            && let Some(session) = lock.as_mut()
+        {
+            session.ready = true;
+        }
+    }
+
+    /// Take the session of a process that exited while it was still serving,
+    /// i.e. one that wasn't stopped, replaced or still starting
+    pub(super) fn take_crashed_session(&self, pid: u32) -> Option<RcSession> {
+        let mut lock = self.session.lock().ok()?;
+        if lock.as_ref().is_some_and(|s| s.pid == pid && s.ready) {
+            lock.take()
+        } else {
+            None
+        }
+    }
+
+    /// Take the current session if it was serving, for a launch about to replace it
+    pub(super) fn take_serving_session(&self) -> Option<RcSession> {
+        let mut lock = self.session.lock().ok()?;
+        if lock.as_ref().is_some_and(|s| s.ready) {
+            lock.take()
+        } else {
+            None
+        }
+    }
+
+    /// Kill the server and forget it, so its exit isn't treated as a crash (e.g. on app exit)
+    pub fn end_session(&self) {
+        if let Some(session) = self.take_session() {
+            session.child.kill();
+        }
+    }
+}
+
+/// What is answering on the port of our RC session
+enum ServerStatus {
+    /// Our own sidecar
+    Running,
+    /// Nothing is listening (yet)
+    Stopped,
+    /// Some other process, e.g. an rclone left over from a crashed session
+    Foreign,
+}
+
+/// Check that the RC server answering on our port is the process we spawned
+async fn server_status(app: &AppHandle) -> ServerStatus {
+    let Some(session) = app.state::<RcServerState>().session() else {
+        return ServerStatus::Stopped;
+    };
+
     // core/pid is a lightweight check
-    client.core_pid(None, None).await.is_ok()
+    match session.credentials.client().core_pid(None, None).await {
+        Ok(response) if response.pid == i64::from(session.pid) => ServerStatus::Running,
+        Ok(_) => ServerStatus::Foreign,
+        Err(rclone_sdk::Error::CommunicationError(_)) => ServerStatus::Stopped,
+        // Rejected our login or isn't rclone at all
+        Err(_) => ServerStatus::Foreign,
+    }
+}
+
+/// Helper to check if our RC server is listening
+pub async fn is_server_running(app: &AppHandle) -> bool {
+    matches!(server_status(app).await, ServerStatus::Running)
 }
 
 /// Starts the rclone RC server in the background
 pub async fn start_rc_server(app: &AppHandle) -> Result<(), String> {
-    // Clear log file on startup
-    LogManager::clear(app).await?;
+    // Keep the previous session's log around for bug reports
+    LogManager::rotate(app).await?;
     let log_file = LogManager::get_log_path(app)?;
 
+    let credentials = RcCredentials::generate()?;
     let sidecar_command = super::get_rclone_command(app)?;
+    // Job ids start over on the new server
+    crate::api::gdrive::forget_stopped_jobs();
 
-    let (mut _rx, child) = sidecar_command
-        .args(&[
-            "rcd",
-            "--rc-no-auth",
-            &format!("--rc-addr=localhost:{}", RC_PORT),
-            "--log-file",
-            &log_file.to_string_lossy().to_string(),
-            "--log-level",
-            "INFO",
-        ])
+    // Pass the login through the environment so it doesn't show up in the process list
+    let (rx, child) = sidecar_command
+        .env("RCLONE_RC_USER", &credentials.user)
+        .env("RCLONE_RC_PASS", &credentials.pass)
+        .args(rcd_args(&credentials, &log_file))
         .spawn()
         .map_err(|e| format!("Failed to spawn rclone rcd: {}", e))?;
 
+    let pid = child.pid();
     let manager = app.state::<crate::SidecarManager>();
-    manager.add(child);
+    let child = manager.add(child);
+    app.state::<RcServerState>().set_session(RcSession {
+        credentials,
+        pid,
+        child,
+        started_at: Instant::now(),
+        ready: false,
+    });
+    supervisor::watch(app, rx, pid);
 
     Ok(())
 }
 
+/// Arguments starting `rclone rcd` on the port of `credentials`, logging to `log_file`
+pub(super) fn rcd_args(credentials: &RcCredentials, log_file: &Path) -> Vec<String> {
+    vec![
+        "rcd".to_string(),
+        format!("--rc-addr=127.0.0.1:{}", credentials.port),
+        "--log-file".to_string(),
+        log_file.to_string_lossy().to_string(),
+        "--log-level".to_string(),
+        "INFO".to_string(),
+        "--use-json-log".to_string(),
+    ]
+}
+
 /// Waits for the RC server to become available
-pub async fn wait_for_server() -> Result<(), String> {
+async fn wait_for_server(app: &AppHandle) -> Result<(), ServerStatus> {
     for _ in 0..20 {
         // 10 seconds total
-        if is_server_running().await {
-            return Ok(());
+        match server_status(app).await {
+            ServerStatus::Running => {
+                app.state::<RcServerState>().mark_ready();
+                return Ok(());
+            }
+            ServerStatus::Foreign => return Err(ServerStatus::Foreign),
+            ServerStatus::Stopped => {}
         }
         tokio::time::sleep(Duration::from_millis(500)).await;
     }
-    Err("Timed out waiting for rclone rc server".to_string())
+    Err(ServerStatus::Stopped)
+}
+
+/// Start the RC server, moving to another port if a foreign process answers on the chosen one
+pub(super) async fn launch_rc_server(app: &AppHandle) -> Result<(), String> {
+    supervisor::replace_dead_session(app).await?;
+
+    for _ in 0..MAX_START_ATTEMPTS {
+        start_rc_server(app).await?;
+
+        let Err(status) = wait_for_server(app).await else {
+            return Ok(());
+        };
+
+        // Our rclone most likely failed to bind the port, don't leave it behind
+        let session = app.state::<RcServerState>().session();
+        if let Some(session) = &session {
+            session.child.kill();
+        }
+
+        match status {
+            ServerStatus::Foreign => log::warn!(
+                "Another process is answering on port {}, retrying on a new port",
+                session.map(|s| s.credentials.port).unwrap_or_default()
+            ),
+            _ => return Err("Timed out waiting for rclone rc server".to_string()),
+        }
+    }
+    Err(format!(
+        "Failed to start rclone rc server: another process took the port {} times in a row",
+        MAX_START_ATTEMPTS
+    ))
 }
 
 /// Waits for the RC server to stop
-pub async fn wait_for_server_shutdown() -> Result<(), String> {
+async fn wait_for_server_shutdown(client: &Client) -> Result<(), String> {
     for _ in 0..20 {
         // 10 seconds total
-        if !is_server_running().await {
+        if client.core_pid(None, None).await.is_err() {
             return Ok(());
         }
         tokio::time::sleep(Duration::from_millis(500)).await;
@@ -68,28 +282,43 @@ pub async fn wait_for_server_shutdown() -> Result<(), String> {
     Err("Timed out waiting for rclone rc server to stop".to_string())
 }
 
+/// Start the RC server if it isn't running and return how to reach it
+pub async fn ensure_server(app: &AppHandle) -> Result<RcCredentials, String> {
+    if !is_server_running(app).await {
+        // Lock so concurrent callers don't each spawn a server
+        let _guard = STARTUP_LOCK.lock().await;
+        if !is_server_running(app).await {
+            launch_rc_server(app).await?;
+        }
+    }
+    app.state::<RcServerState>()
+        .credentials()
+        .ok_or_else(|| "rclone rc server is not running".to_string())
+}
+
 /// Returns an authenticated SDK Client, ensuring the server is running.
 pub async fn get_sdk_client(app: &AppHandle) -> Result<Client, String> {
-    if !is_server_running().await {
-        start_rc_server(app).await?;
-        wait_for_server().await?;
-    }
-    Ok(Client::new(RC_URL))
+    Ok(ensure_server(app).await?.client())
 }
 
 #[tauri::command]
-pub async fn stop_rc_server() -> Result<(), String> {
+pub async fn stop_rc_server(app: AppHandle) -> Result<(), String> {
     // Lock to prevent concurrent shutdowns
     let _guard = SHUTDOWN_LOCK.lock().await;
 
-    if is_server_running().await {
-        let client = Client::new(RC_URL);
-        client
-            .core_quit(None, None, None)
-            .await
-            .map_err(|e| format!("Failed to stop rclone: {}", e))?;
+    if !is_server_running(&app).await {
+        return Ok(());
+    }
 
-        wait_for_server_shutdown().await?;
+    // Forget the session first so the supervisor doesn't restart it
+    let Some(session) = app.state::<RcServerState>().take_session() else {
+        return Ok(());
+    };
+    let client = session.credentials.client();
+    if let Err(e) = client.core_quit(None, None, None).await {
+        session.child.kill();
+        return Err(format!("Failed to stop rclone: {}", e));
     }
-    Ok(())
+
+    wait_for_server_shutdown(&client).await
 }
@@ -0,0 +1,159 @@
+//! rclone run as a plain child process, for hosts without the Tauri runtime like the CLI.
+//! Unlike the app's sidecar it isn't supervised, a crash fails the jobs running on it.
+
+use super::LogManager;
+use super::server::{RcCredentials, rcd_args};
+use crate::service::{PathProvider, RcloneLauncher};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::time::Duration;
+use tokio::process::{Child, Command};
+use tokio::sync::Mutex;
+
+/// Log of the standalone server, kept apart from the app's `rclone.log` so starting one
+/// doesn't rotate away the log of an app session that is still running
+const LOG_FILE_NAME: &str = "rclone-cli.log";
+
+/// How long `stop` waits for rclone to quit before killing it
+const QUIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+struct Session {
+    credentials: RcCredentials,
+    child: Child,
+}
+
+impl Session {
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+}
+
+/// Starts an rclone RC server on first use and keeps it until `stop`
+pub struct StandaloneServer {
+    binary: PathBuf,
+    data_dir: PathBuf,
+    session: Mutex<Option<Session>>,
+}
+
+impl StandaloneServer {
+    /// Run `binary`, logging to its own rclone log in `data_dir`
+    pub fn new(binary: PathBuf, data_dir: PathBuf) -> Self {
+        Self {
+            binary,
+            data_dir,
+            session: Mutex::new(None),
+        }
+    }
+
+    /// The rclone bundled with the app, installed next to its executable
+    pub fn bundled_binary() -> Result<PathBuf, String> {
+        let exe =
+            std::env::current_exe().map_err(|e| format!("Failed to find the executable: {}", e))?;
+        let binary = exe
+            .parent()
+            .ok_or("Failed to find the executable's directory")?
+            .join(format!("rclone{}", std::env::consts::EXE_SUFFIX));
+
+        if !binary.is_file() {
+            return Err(format!("rclone not found at {}", binary.display()));
+        }
+        Ok(binary)
+    }
+
+    async fn start(&self) -> Result<Session, String> {
+        // Keep the previous session's log around for bug reports
+        LogManager::rotate(self).await?;
+        let log_file = LogManager::get_log_path(self)?;
+        let credentials = RcCredentials::generate()?;
+        // Job ids start over on the new server
+        crate::api::gdrive::forget_stopped_jobs();
+
+        // Pass the login through the environment so it doesn't show up in the process list
+        let mut child = Command::new(&self.binary)
+            .env("RCLONE_RC_USER", &credentials.user)
+            .env("RCLONE_RC_PASS", &credentials.pass)
+            .args(rcd_args(&credentials, &log_file))
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| format!("Failed to spawn rclone rcd: {}", e))?;
+        let pid = child.id().ok_or("rclone rcd exited right away")?;
+
+        let client = credentials.client();
+        for _ in 0..20 {
+            // 10 seconds total
+            if let Ok(Some(status)) = child.try_wait() {
+                return Err(format!("rclone rcd exited with {}", status));
+            }
+            match client.core_pid(None, None).await {
+                Ok(response) if response.pid == i64::from(pid) => {
+                    return Ok(Session { credentials, child });
+                }
+                Ok(_) => {
+                    let _ = child.kill().await;
+                    return Err(format!(
+                        "Another process is answering on port {}",
+                        credentials.port
+                    ));
+                }
+                Err(_) => tokio::time::sleep(Duration::from_millis(500)).await,
+            }
+        }
+
+        let _ = child.kill().await;
+        Err("Timed out waiting for rclone rc server".to_string())
+    }
+
+    /// Ask rclone to quit, killing it if it doesn't
+    pub async fn stop(&self) {
+        let Some(mut session) = self.session.lock().await.take() else {
+            return;
+        };
+
+        let _ = session
+            .credentials
+            .client()
+            .core_quit(None, None, None)
+            .await;
+        if tokio::time::timeout(QUIT_TIMEOUT, session.child.wait())
+            .await
+            .is_err()
+        {
+            let _ = session.child.kill().await;
+        }
+    }
+}
+
+impl PathProvider for StandaloneServer {
+    fn data_dir(&self) -> Result<PathBuf, String> {
+        Ok(self.data_dir.clone())
+    }
+
+    fn rclone_log_path(&self) -> Result<PathBuf, String> {
+        Ok(self.data_dir.join(LOG_FILE_NAME))
+    }
+}
+
+impl RcloneLauncher for StandaloneServer {
+    async fn ensure_running(&self) -> Result<RcCredentials, String> {
+        let mut session = self.session.lock().await;
+        if let Some(current) = session.as_mut()
+            && current.is_alive()
+        {
+            return Ok(current.credentials.clone());
+        }
+
+        let started = self.start().await?;
+        let credentials = started.credentials.clone();
+        *session = Some(started);
+        Ok(credentials)
+    }
+
+    async fn running(&self) -> Option<RcCredentials> {
+        let mut session = self.session.lock().await;
+        let current = session.as_mut()?;
+        current.is_alive().then(|| current.credentials.clone())
+    }
+}
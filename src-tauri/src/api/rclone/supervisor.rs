@@ -0,0 +1,174 @@
+use super::logs::{LogEvent, LogFilter};
+use super::server::{self, RcServerState, RcSession, STARTUP_LOCK};
+use super::tail::LogTailer;
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use tauri::async_runtime::Receiver;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tauri_plugin_shell::process::{CommandEvent, TerminatedPayload};
+
+/// Lines of rclone stdout/stderr kept for crash reports, e.g. a Go panic
+const MAX_OUTPUT_LINES: usize = 50;
+/// Log events included in crash reports
+const MAX_CRASH_EVENTS: usize = 50;
+/// Consecutive crashes after which the server is no longer restarted automatically
+const MAX_RESTARTS: u32 = 5;
+const RESTART_BASE_DELAY: Duration = Duration::from_secs(1);
+const RESTART_MAX_DELAY: Duration = Duration::from_secs(30);
+/// A server up for this long resets the backoff when it crashes
+const STABLE_UPTIME: Duration = Duration::from_secs(300);
+
+/// Details of an unexpected rclone exit, emitted as `rclone-crashed`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RcCrash {
+    pub code: Option<i32>,
+    pub signal: Option<i32>,
+    /// Last lines rclone wrote to stdout/stderr
+    pub output: Vec<String>,
+    /// Last records from the rclone log
+    pub recent_events: Vec<LogEvent>,
+    pub crashed_at: String,
+    /// Whether the server is being restarted
+    pub restarting: bool,
+}
+
+/// Follow the output of a spawned rcd process and handle it exiting
+pub(super) fn watch(app: &AppHandle, mut rx: Receiver<CommandEvent>, pid: u32) {
+    let app = app.clone();
+    tauri::async_runtime::spawn(async move {
+        let mut output = VecDeque::with_capacity(MAX_OUTPUT_LINES);
+        while let Some(event) = rx.recv().await {
+            match event {
+                CommandEvent::Stdout(line) | CommandEvent::Stderr(line) => {
+                    if output.len() == MAX_OUTPUT_LINES {
+                        output.pop_front();
+                    }
+                    output.push_back(String::from_utf8_lossy(&line).trim_end().to_string());
+                }
+                CommandEvent::Terminated(payload) => {
+                    handle_exit(&app, pid, payload, output.into()).await;
+                    break;
+                }
+                _ => {}
+            }
+        }
+    });
+}
+
+/// Report and restart the server if it went away without being asked to
+async fn handle_exit(app: &AppHandle, pid: u32, payload: TerminatedPayload, output: Vec<String>) {
+    let state = app.state::<RcServerState>();
+    let (crashes, replaced) = if let Some(session) = state.take_crashed_session(pid) {
+        (count_crash(&state, &session), false)
+    } else if let Some(crashes) = state
+        .replaced_crashes
+        .lock()
+        .ok()
+        .and_then(|mut replaced| replaced.remove(&pid))
+    {
+        // Counted, and started again, when a new server replaced it
+        (crashes, true)
+    } else {
+        return;
+    };
+
+    let recent_events = app
+        .state::<LogTailer>()
+        .recent(MAX_CRASH_EVENTS, &LogFilter::default());
+    let crash = RcCrash {
+        code: payload.code,
+        signal: payload.signal,
+        output,
+        recent_events,
+        crashed_at: chrono::Local::now().to_rfc3339(),
+        restarting: crashes <= MAX_RESTARTS,
+    };
+    log::error!(
+        "rclone exited unexpectedly (code {:?}, signal {:?})",
+        crash.code,
+        crash.signal
+    );
+
+    if let Ok(mut last_crash) = state.last_crash.lock() {
+        *last_crash = Some(crash.clone());
+    }
+    let _ = app.emit("rclone-crashed", &crash);
+
+    if crash.restarting && !replaced {
+        restart(app, crashes).await;
+    }
+}
+
+/// Count a crash, starting over if the server had been up for a while
+fn count_crash(state: &RcServerState, session: &RcSession) -> u32 {
+    if session.started_at.elapsed() >= STABLE_UPTIME {
+        state.consecutive_crashes.store(0, Ordering::SeqCst);
+    }
+    state.consecutive_crashes.fetch_add(1, Ordering::SeqCst) + 1
+}
+
+/// Delay before restarting after `crashes` crashes in a row
+fn restart_delay(crashes: u32) -> Duration {
+    RESTART_BASE_DELAY
+        .saturating_mul(1 << (crashes - 1).min(31))
+        .min(RESTART_MAX_DELAY)
+}
+
+/// Count the crash of a server about to be replaced while its exit wasn't handled
+/// yet, e.g. when a job noticed it first and asks for a new one, and back off like
+/// a restart would. Its exit is reported once it arrives.
+pub(super) async fn replace_dead_session(app: &AppHandle) -> Result<(), String> {
+    let state = app.state::<RcServerState>();
+    let Some(session) = state.take_serving_session() else {
+        return Ok(());
+    };
+    // It no longer answers, don't leave it running next to the new one
+    session.child.kill();
+
+    let crashes = count_crash(&state, &session);
+    if let Ok(mut replaced) = state.replaced_crashes.lock() {
+        replaced.insert(session.pid, crashes);
+    }
+    if crashes > MAX_RESTARTS {
+        return Err(format!(
+            "rclone crashed {} times in a row, not starting it again",
+            crashes
+        ));
+    }
+
+    tokio::time::sleep(restart_delay(crashes)).await;
+    Ok(())
+}
+
+/// Start the server again after a delay growing with each crash in a row
+async fn restart(app: &AppHandle, crashes: u32) {
+    // Hold the startup lock so callers wait for the restart instead of racing it
+    let _guard = STARTUP_LOCK.lock().await;
+
+    for attempt in crashes..=MAX_RESTARTS {
+        tokio::time::sleep(restart_delay(attempt)).await;
+
+        if server::is_server_running(app).await {
+            return;
+        }
+        match server::launch_rc_server(app).await {
+            Ok(()) => {
+                let _ = app.emit("rclone-restarted", ());
+                return;
+            }
+            Err(e) => log::error!("Failed to restart rclone (attempt {}): {}", attempt, e),
+        }
+    }
+}
+
+/// Get the details of the last unexpected rclone exit in this session
+#[tauri::command]
+pub async fn get_last_rclone_crash(
+    state: State<'_, RcServerState>,
+) -> Result<Option<RcCrash>, String> {
+    let last_crash = state.last_crash.lock().map_err(|e| e.to_string())?;
+    Ok(last_crash.clone())
+}
@@ -0,0 +1,179 @@
+use super::LogManager;
+use super::logs::{LogEvent, LogFilter, LogLevel};
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::Duration;
+use tauri::{AppHandle, Emitter, Manager, State};
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+
+/// How often the log file is checked for new records
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+/// Events kept in memory for `get_log_backlog`
+const BACKLOG_SIZE: usize = 2000;
+/// Most events sent to the frontend per poll, older ones are dropped beyond that
+const MAX_BATCH_SIZE: usize = 200;
+
+/// Events emitted to the frontend as `rclone-log`
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LogBatch {
+    pub events: Vec<LogEvent>,
+    /// Events skipped because the frontend couldn't keep up
+    pub dropped: usize,
+}
+
+/// Follows the rclone log file, keeping a backlog and streaming new events to the frontend
+pub struct LogTailer {
+    backlog: Mutex<VecDeque<LogEvent>>,
+    min_level: Mutex<LogLevel>,
+}
+
+impl Default for LogTailer {
+    fn default() -> Self {
+        Self {
+            backlog: Mutex::new(VecDeque::with_capacity(BACKLOG_SIZE)),
+            min_level: Mutex::new(LogLevel::Info),
+        }
+    }
+}
+
+impl LogTailer {
+    /// Start following the log file in the background for the lifetime of the app
+    pub fn start(app: &AppHandle) {
+        let app = app.clone();
+        tauri::async_runtime::spawn(async move {
+            let mut offset = 0;
+            let mut partial = Vec::new();
+            loop {
+                if let Err(e) = Self::poll(&app, &mut offset, &mut partial).await {
+                    log::warn!("Failed to follow rclone log: {}", e);
+                }
+                tokio::time::sleep(POLL_INTERVAL).await;
+            }
+        });
+    }
+
+    /// Read the records appended since the last poll and publish them
+    async fn poll(app: &AppHandle, offset: &mut u64, partial: &mut Vec<u8>) -> Result<(), String> {
+        let path = LogManager::get_log_path(app)?;
+        let len = match tokio::fs::metadata(&path).await {
+            Ok(metadata) => metadata.len(),
+            Err(_) => 0,
+        };
+
+        // The log was cleared or rotated, start over
+        if len < *offset {
+            *offset = 0;
+            partial.clear();
+        }
+        if len == *offset {
+            return Ok(());
+        }
+
+        let mut file = tokio::fs::File::open(&path)
+            .await
+            .map_err(|e| format!("Failed to open log file: {}", e))?;
+        file.seek(std::io::SeekFrom::Start(*offset))
+            .await
+            .map_err(|e| format!("Failed to seek log file: {}", e))?;
+
+        let read = file
+            .take(len - *offset)
+            .read_to_end(partial)
+            .await
+            .map_err(|e| format!("Failed to read log file: {}", e))?;
+        *offset += read as u64;
+
+        // Keep an unterminated last line for the next poll, it may end in the middle
+        // of a multibyte character
+        let Some(end) = partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(());
+        };
+        let complete: Vec<u8> = partial.drain(..=end).collect();
+        let events: Vec<LogEvent> = String::from_utf8_lossy(&complete)
+            .lines()
+            .filter_map(LogEvent::parse)
+            .collect();
+
+        let tailer = app.state::<LogTailer>();
+        tailer.publish(app, events);
+        Ok(())
+    }
+
+    /// Get the most recent events in the backlog matching a filter, oldest first
+    pub fn recent(&self, limit: usize, filter: &LogFilter) -> Vec<LogEvent> {
+        let Ok(backlog) = self.backlog.lock() else {
+            return Vec::new();
+        };
+
+        let mut events: Vec<LogEvent> = backlog
+            .iter()
+            .rev()
+            .filter(|event| filter.matches(event))
+            .take(limit)
+            .cloned()
+            .collect();
+        events.reverse();
+        events
+    }
+
+    /// Add events to the backlog and emit the ones passing the level filter
+    fn publish(&self, app: &AppHandle, events: Vec<LogEvent>) {
+        if events.is_empty() {
+            return;
+        }
+
+        let min_level = self.min_level.lock().map(|l| *l).unwrap_or(LogLevel::Info);
+        let mut emitted: Vec<LogEvent> = events
+            .iter()
+            .filter(|event| event.level >= min_level)
+            .cloned()
+            .collect();
+
+        if let Ok(mut backlog) = self.backlog.lock() {
+            for event in events {
+                if backlog.len() == BACKLOG_SIZE {
+                    backlog.pop_front();
+                }
+                backlog.push_back(event);
+            }
+        }
+
+        if emitted.is_empty() {
+            return;
+        }
+
+        let dropped = emitted.len().saturating_sub(MAX_BATCH_SIZE);
+        emitted.drain(..dropped);
+        let _ = app.emit(
+            "rclone-log",
+            LogBatch {
+                events: emitted,
+                dropped,
+            },
+        );
+    }
+}
+
+/// Get the most recent log events kept in memory, oldest first
+#[tauri::command]
+pub async fn get_log_backlog(
+    state: State<'_, LogTailer>,
+    limit: Option<usize>,
+    filter: Option<LogFilter>,
+) -> Result<Vec<LogEvent>, String> {
+    let filter = filter.unwrap_or_default();
+    Ok(state.recent(limit.unwrap_or(BACKLOG_SIZE), &filter))
+}
+
+/// Set the lowest level of events streamed to the frontend
+#[tauri::command]
+pub async fn set_log_stream_level(
+    state: State<'_, LogTailer>,
+    level: LogLevel,
+) -> Result<(), String> {
+    let mut min_level = state.min_level.lock().map_err(|e| e.to_string())?;
+    *min_level = level;
+    Ok(())
+}
@@ -0,0 +1,71 @@
+/// Whether the machine is on AC power, `None` when it can't be told
+pub fn on_ac_power() -> Option<bool> {
+    #[cfg(target_os = "linux")]
+    {
+        let supplies = std::fs::read_dir("/sys/class/power_supply").ok()?;
+        let mut has_battery = false;
+        for supply in supplies.flatten() {
+            let path = supply.path();
+            let kind = std::fs::read_to_string(path.join("type")).unwrap_or_default();
+            match kind.trim() {
+                "Mains" => {
+                    let online = std::fs::read_to_string(path.join("online")).ok()?;
+                    if online.trim() == "1" {
+                        return Some(true);
+                    }
+                }
+                "Battery" => has_battery = true,
+                _ => {}
+            }
+        }
+        // Desktops without a battery are always plugged in
+        Some(!has_battery)
+    }
+    #[cfg(target_os = "macos")]
+    {
+        let output = std::process::Command::new("pmset")
+            .args(["-g", "batt"])
+            .output()
+            .ok()?;
+        let output = String::from_utf8_lossy(&output.stdout);
+        if output.contains("'AC Power'") {
+            Some(true)
+        } else if output.contains("'Battery Power'") {
+            Some(false)
+        } else {
+            None
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "macos")))]
+    {
+        None
+    }
+}
+
+/// Whether the network connection is metered, `None` when it can't be told
+pub fn on_metered_connection() -> Option<bool> {
+    #[cfg(target_os = "linux")]
+    {
+        // NetworkManager's global Metered property, see NMMetered
+        let output = std::process::Command::new("busctl")
+            .args([
+                "get-property",
+                "org.freedesktop.NetworkManager",
+                "/org/freedesktop/NetworkManager",
+                "org.freedesktop.NetworkManager",
+                "Metered",
+            ])
+            .output()
+            .ok()?;
+        let output = String::from_utf8_lossy(&output.stdout);
+        match output.trim() {
+            "u 1" | "u 3" => Some(true),
+            "u 2" | "u 4" => Some(false),
+            _ => None,
+        }
+    }
+    #[cfg(not(target_os = "linux"))]
+    {
+        None
+    }
+}
@@ -0,0 +1,413 @@
+//! Headless mode, e.g. `unofficial-neuro-kar-manager sync --profile NAS --dry-run`,
+//! for running archive syncs from cron without opening the window. Runs without the
+//! Tauri app, so it also works on machines without a display.
+
+use crate::api::gdrive::{
+    self, DownloadReport, DryRunResult, JobManager, JobStatus, Profile, ProfileManager,
+};
+use crate::api::rclone::{RcCredentials, StandaloneServer};
+use crate::logging;
+use crate::service::{EventSink, JobHost, PathProvider, RcClientFactory, RcloneLauncher};
+use crate::utils::format_bytes;
+use serde::Serialize;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// The download completed without errors
+pub const EXIT_OK: i32 = 0;
+/// The download or dry run failed
+pub const EXIT_FAILED: i32 = 1;
+/// Unknown command or invalid arguments
+pub const EXIT_USAGE: i32 = 2;
+/// The profile doesn't exist or can no longer run
+pub const EXIT_CONFIG: i32 = 3;
+/// The download completed but some files failed to transfer
+pub const EXIT_PARTIAL: i32 = 4;
+/// A sync would delete files and `--allow-deletes` wasn't given
+pub const EXIT_DELETES_BLOCKED: i32 = 5;
+/// Interrupted with Ctrl-C
+pub const EXIT_CANCELLED: i32 = 130;
+
+/// How often progress is printed while downloading
+const PROGRESS_INTERVAL: Duration = Duration::from_secs(5);
+
+const USAGE: &str = "\
+Usage:
+  unofficial-neuro-kar-manager sync --profile <name or id> [--dry-run] [--allow-deletes]
+  unofficial-neuro-kar-manager profiles
+  unofficial-neuro-kar-manager help
+
+Commands:
+  sync       Run the download saved in a profile
+  profiles   List the saved profiles
+
+Options:
+  --profile <name or id>  Profile to run
+  --dry-run               Only print what the download would change
+  --allow-deletes         Let a sync delete local files not on the Drive
+
+Exit codes:
+  0    Success
+  1    The download or dry run failed
+  2    Invalid arguments
+  3    The profile doesn't exist or can no longer run
+  4    Completed, but some files failed to transfer
+  5    The sync would delete files and --allow-deletes wasn't given
+  130  Interrupted";
+
+enum Command {
+    Sync {
+        profile: String,
+        dry_run: bool,
+        allow_deletes: bool,
+    },
+    Profiles,
+    Help,
+}
+
+impl Command {
+    /// Parse the arguments after the binary name.
+    /// Returns `None` when they don't name a CLI command, to start the app instead.
+    fn parse(args: &[String]) -> Option<Result<Self, String>> {
+        let (name, rest) = args.split_first()?;
+        let command = match name.as_str() {
+            "sync" => Self::parse_sync(rest),
+            "profiles" => Ok(Self::Profiles),
+            "help" | "--help" | "-h" => Ok(Self::Help),
+            _ => return None,
+        };
+        Some(command)
+    }
+
+    fn parse_sync(args: &[String]) -> Result<Self, String> {
+        let mut profile = None;
+        let mut dry_run = false;
+        let mut allow_deletes = false;
+
+        let mut args = args.iter();
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--profile" | "-p" => {
+                    profile = Some(args.next().ok_or("--profile needs a value")?.clone());
+                }
+                "--dry-run" | "-n" => dry_run = true,
+                "--allow-deletes" => allow_deletes = true,
+                other => match other.strip_prefix("--profile=") {
+                    Some(value) => profile = Some(value.to_string()),
+                    None => return Err(format!("Unknown option {}", other)),
+                },
+            }
+        }
+
+        Ok(Self::Sync {
+            profile: profile.ok_or("sync needs --profile")?,
+            dry_run,
+            allow_deletes,
+        })
+    }
+}
+
+/// Run a CLI command if the arguments name one, returning its exit code
+pub fn run(args: &[String]) -> Option<i32> {
+    let command = match Command::parse(args)? {
+        Ok(Command::Help) => {
+            println!("{}", USAGE);
+            return Some(EXIT_OK);
+        }
+        Ok(command) => command,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return Some(EXIT_USAGE);
+        }
+    };
+
+    logging::init_stderr_logger();
+    let runtime = match tokio::runtime::Runtime::new() {
+        Ok(runtime) => runtime,
+        Err(e) => {
+            eprintln!("Failed to start: {}", e);
+            return Some(EXIT_FAILED);
+        }
+    };
+    // Jobs spawn their tasks through Tauri's runtime, make it this one
+    tauri::async_runtime::set(runtime.handle().clone());
+
+    Some(runtime.block_on(async {
+        let host = match CliHost::new() {
+            Ok(host) => host,
+            Err(e) => {
+                eprintln!("Failed to start: {}", e);
+                return EXIT_FAILED;
+            }
+        };
+        gdrive::start_bwlimit_schedule(&host);
+
+        let code = execute(&host, command).await;
+
+        // Leave nothing running behind
+        host.0.server.stop().await;
+        code
+    }))
+}
+
+/// Runs the CLI commands with a plain rclone child process instead of the app,
+/// so they work without a display
+#[derive(Clone)]
+struct CliHost(Arc<CliState>);
+
+struct CliState {
+    data_dir: PathBuf,
+    server: StandaloneServer,
+    jobs: JobManager,
+    profiles: ProfileManager,
+}
+
+impl CliHost {
+    fn new() -> Result<Self, String> {
+        // The same directory the app keeps its profiles and logs in
+        let data_dir = dirs::data_local_dir()
+            .ok_or("Failed to get app data dir")?
+            .join(&crate::context().config().identifier);
+        let server = StandaloneServer::new(StandaloneServer::bundled_binary()?, data_dir.clone());
+
+        Ok(Self(Arc::new(CliState {
+            jobs: JobManager::detached(&data_dir),
+            profiles: ProfileManager::load(&data_dir),
+            server,
+            data_dir,
+        })))
+    }
+}
+
+impl PathProvider for CliHost {
+    fn data_dir(&self) -> Result<PathBuf, String> {
+        Ok(self.0.data_dir.clone())
+    }
+
+    fn rclone_log_path(&self) -> Result<PathBuf, String> {
+        self.0.server.rclone_log_path()
+    }
+}
+
+impl RcloneLauncher for CliHost {
+    async fn ensure_running(&self) -> Result<RcCredentials, String> {
+        self.0.server.ensure_running().await
+    }
+
+    async fn running(&self) -> Option<RcCredentials> {
+        self.0.server.running().await
+    }
+}
+
+/// Progress is printed instead
+impl EventSink for CliHost {
+    fn send_event<S: Serialize + Clone>(&self, _event: &str, _payload: S) {}
+}
+
+impl JobHost for CliHost {
+    fn job_manager(&self) -> &JobManager {
+        &self.0.jobs
+    }
+}
+
+async fn execute(host: &CliHost, command: Command) -> i32 {
+    match command {
+        Command::Sync {
+            profile,
+            dry_run,
+            allow_deletes,
+        } => sync(host, &profile, dry_run, allow_deletes).await,
+        Command::Profiles => {
+            for profile in host.0.profiles.list() {
+                println!(
+                    "{}\t{}\t{} -> {}",
+                    profile.id, profile.name, profile.config.source, profile.config.destination
+                );
+            }
+            EXIT_OK
+        }
+        Command::Help => EXIT_OK,
+    }
+}
+
+async fn sync(host: &CliHost, name: &str, dry_run: bool, allow_deletes: bool) -> i32 {
+    let Some(profile) = host.0.profiles.find(name) else {
+        eprintln!("Profile '{}' not found", name);
+        return EXIT_CONFIG;
+    };
+
+    let validation = gdrive::check_profile(host, &profile).await;
+    if !validation.errors.is_empty() {
+        for error in validation.errors {
+            eprintln!("{}", error);
+        }
+        return EXIT_CONFIG;
+    }
+
+    println!(
+        "Profile: {}\nSource: {}\nDestination: {}\nMode: {}",
+        profile.name,
+        profile.config.source,
+        profile.config.destination,
+        if profile.config.sync_mode {
+            "sync"
+        } else {
+            "copy"
+        }
+    );
+
+    // Like the app, check what a sync would delete before running it
+    if dry_run || profile.config.sync_mode {
+        println!("\nChecking what would change...");
+        let result = match gdrive::dry_run(host, profile.config.clone()).await {
+            Ok(result) => result,
+            Err(e) => {
+                eprintln!("Dry run failed: {}", e);
+                return EXIT_FAILED;
+            }
+        };
+        print_plan(&profile, &result);
+
+        if dry_run {
+            return EXIT_OK;
+        }
+        if result.would_delete && !allow_deletes {
+            eprintln!("\nThe sync would delete files, run again with --allow-deletes to proceed");
+            return EXIT_DELETES_BLOCKED;
+        }
+    }
+
+    download(host, profile).await
+}
+
+async fn download(host: &CliHost, profile: Profile) -> i32 {
+    println!("\nDownloading...");
+    let manager = host.job_manager();
+    let id = manager.enqueue(host, profile.config);
+    gdrive::dispatch_jobs(host);
+
+    let job = tokio::select! {
+        job = manager.wait(id) => job,
+        _ = print_progress(host) => unreachable!(),
+        _ = tokio::signal::ctrl_c() => {
+            eprintln!("\nCancelling...");
+            if let Err(e) = gdrive::cancel_queued_job(host, id).await {
+                eprintln!("Failed to cancel the download: {}", e);
+            }
+            return EXIT_CANCELLED;
+        }
+    };
+
+    let job = match job {
+        Ok(job) => job,
+        Err(e) => {
+            eprintln!("{}", e);
+            return EXIT_FAILED;
+        }
+    };
+    let status = job.status;
+    match job.into_result() {
+        Ok(report) => {
+            print_report(&report);
+            if report.stats.errors > 0 {
+                EXIT_PARTIAL
+            } else {
+                EXIT_OK
+            }
+        }
+        // Nothing to tell about the files, but the download itself went through
+        Err(e) if status == JobStatus::Completed => {
+            eprintln!("\n{}", e);
+            EXIT_OK
+        }
+        Err(e) => {
+            eprintln!("\nDownload failed: {}", e);
+            if status == JobStatus::Cancelled {
+                EXIT_CANCELLED
+            } else {
+                EXIT_FAILED
+            }
+        }
+    }
+}
+
+/// Print transfer progress until cancelled
+async fn print_progress(host: &CliHost) {
+    loop {
+        tokio::time::sleep(PROGRESS_INTERVAL).await;
+        let Some(client) = host.running_rc_client().await else {
+            continue;
+        };
+        let Ok(stats) = client.core_stats(None, None, None, None).await else {
+            continue;
+        };
+        let stats = stats.into_inner();
+
+        let eta = stats
+            .eta
+            .map(|eta| format!(", ETA {}s", eta.round()))
+            .unwrap_or_default();
+        println!(
+            "{} / {}, {}/s, {}/{} files{}",
+            format_bytes(stats.bytes),
+            format_bytes(stats.total_bytes),
+            format_bytes(stats.speed),
+            stats.transfers,
+            stats.total_transfers,
+            eta
+        );
+    }
+}
+
+fn print_plan(profile: &Profile, result: &DryRunResult) {
+    let plan = &result.plan;
+    println!(
+        "{} new, {} changed, {} renamed, {} to download",
+        plan.new_files.len(),
+        plan.changed_files.len(),
+        plan.renamed_files.len(),
+        format_bytes(plan.bytes_to_transfer as f64)
+    );
+
+    // Copies never delete, the dry run always runs a sync
+    if !profile.config.sync_mode {
+        return;
+    }
+    println!("{} deleted", plan.deleted_files.len());
+    for file in &result.deleted_files {
+        println!("  delete {}", file);
+    }
+}
+
+fn print_report(report: &DownloadReport) {
+    let stats = &report.stats;
+    println!(
+        "\nDownload completed.\nTransferred {} in {:.1}s ({}/s)",
+        format_bytes(stats.bytes as f64),
+        stats.elapsed_time,
+        format_bytes(report.average_speed)
+    );
+    match &report.changes {
+        Some(changes) => {
+            println!(
+                "Added: {}, Updated: {}, Deleted: {}, Renamed: {}, Errors: {}",
+                changes.added.len(),
+                changes.updated.len(),
+                changes.deleted.len(),
+                changes.renamed.len(),
+                stats.errors
+            );
+            for error in &changes.errors {
+                eprintln!("Error: {}: {}", error.path, error.error);
+            }
+        }
+        None => println!(
+            "Transfers: {}, Deletes: {}, Renames: {}, Errors: {} (other downloads ran at the same time, see the log for the files)",
+            stats.transfers, stats.deletes, stats.renames, stats.errors
+        ),
+    }
+    if let Some(backup_dir) = &report.backup_dir {
+        println!("Backup: {}", backup_dir);
+    }
+}
@@ -1,4 +1,7 @@
 pub mod api;
+pub mod cli;
+pub mod logging;
+pub mod service;
 pub mod utils;
 
 use std::sync::{Arc, Mutex};
@@ -45,6 +48,35 @@ async fn check_rclone(app: tauri::AppHandle) -> bool {
     crate::api::rclone::get_rclone_command(&app).is_ok()
 }
 
+/// Context generated from tauri.conf.json, the CLI reads the app's identifier from it
+fn context() -> tauri::Context {
+    tauri::generate_context!()
+}
+
+/// State used by the rclone and download APIs
+fn manage_state(builder: tauri::Builder<tauri::Wry>) -> tauri::Builder<tauri::Wry> {
+    builder
+        .manage(SidecarManager::default())
+        .manage(api::gdrive::GdriveAuthState::default())
+        .manage(api::rclone::LogTailer::default())
+        .manage(api::rclone::RcServerState::default())
+}
+
+/// Stop rclone and every other sidecar, called when the app exits
+fn kill_sidecars(app_handle: &tauri::AppHandle) {
+    // Don't let the supervisor restart rclone while exiting
+    app_handle
+        .state::<api::rclone::RcServerState>()
+        .end_session();
+
+    let state = app_handle.state::<SidecarManager>();
+    if let Ok(processes) = state.processes.lock() {
+        for child in processes.iter() {
+            child.kill();
+        }
+    }
+}
+
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     #[allow(unused_mut)]
@@ -58,37 +90,87 @@ pub fn run() {
                 .set_focus();
         }));
     }
-    builder
+    builder = builder
         .plugin(tauri_plugin_store::Builder::default().build())
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_process::init())
         .plugin(tauri_plugin_dialog::init())
-        .plugin(tauri_plugin_opener::init())
-        .manage(SidecarManager::default())
-        .manage(api::gdrive::GdriveAuthState::default())
+        .plugin(tauri_plugin_opener::init());
+    manage_state(builder)
+        .setup(|app| {
+            logging::init_app_logger(app.handle());
+            app.manage(api::gdrive::JobManager::load(app.handle()));
+            app.manage(api::gdrive::ProfileManager::load(app.handle()));
+            app.manage(api::gdrive::ScheduleManager::load(app.handle()));
+            app.manage(api::local::LibraryManager::load(app.handle()));
+            api::rclone::LogTailer::start(app.handle());
+            api::gdrive::start_bwlimit_schedule(app.handle());
+            api::gdrive::start_scheduler(app.handle());
+            Ok(())
+        })
         .invoke_handler(tauri::generate_handler![
             check_rclone,
             api::gdrive::get_gdrive_remotes,
             api::gdrive::create_gdrive_remote,
             api::gdrive::cancel_gdrive_auth,
             api::gdrive::list_gdrive_files,
+            api::gdrive::list_gdrive_dir,
+            api::gdrive::compare_local_remote,
+            api::gdrive::verify_archive,
             api::gdrive::download_gdrive,
             api::gdrive::check_dry_run,
+            api::gdrive::cancel_download,
+            api::gdrive::queue_download,
+            api::gdrive::list_jobs,
+            api::gdrive::wait_for_job,
+            api::gdrive::cancel_job,
+            api::gdrive::pause_job,
+            api::gdrive::resume_job,
+            api::gdrive::reorder_job,
+            api::gdrive::set_job_concurrency,
+            api::gdrive::get_job_concurrency,
+            api::gdrive::set_resume_after_crash,
+            api::gdrive::get_resume_after_crash,
+            api::gdrive::set_global_bwlimit,
+            api::gdrive::get_global_bwlimit,
+            api::gdrive::set_job_bwlimit,
+            api::gdrive::list_profiles,
+            api::gdrive::get_profile,
+            api::gdrive::create_profile,
+            api::gdrive::update_profile,
+            api::gdrive::delete_profile,
+            api::gdrive::validate_profile,
+            api::gdrive::export_profiles,
+            api::gdrive::import_profiles,
+            api::gdrive::list_schedules,
+            api::gdrive::create_schedule,
+            api::gdrive::update_schedule,
+            api::gdrive::delete_schedule,
+            api::gdrive::run_schedule_now,
+            api::gdrive::preview_schedule,
+            api::gdrive::get_download_history,
+            api::gdrive::get_history_entry,
+            api::gdrive::find_file_history,
+            api::gdrive::list_backups,
+            api::gdrive::restore_backup,
+            api::gdrive::delete_backup,
+            api::gdrive::prune_backups,
             api::rclone::get_stats,
             api::rclone::stop_rc_server,
-            api::local::scan_local_files
+            api::rclone::get_log_backlog,
+            api::rclone::export_logs,
+            api::rclone::get_last_rclone_crash,
+            api::rclone::set_log_stream_level,
+            api::local::scan_local_files,
+            api::local::index_library,
+            api::local::search_library,
+            api::local::get_library_summary
         ])
-        .build(tauri::generate_context!())
+        .build(context())
         .expect("error building tauri application")
         .run(|app_handle, event| {
             if let tauri::RunEvent::Exit = event {
-                // Kill all sidecars
-                let state = app_handle.state::<SidecarManager>();
-                if let Ok(processes) = state.processes.lock() {
-                    for child in processes.iter() {
-                        child.kill();
-                    }
-                }
+                kill_sidecars(app_handle);
             }
         });
 }
@@ -0,0 +1,143 @@
+//! Where background work reports problems it has no caller to return them to,
+//! e.g. failing to save the job queue. Code logs through the `log` macros; the app
+//! appends the records to `app.log` next to the rclone log and forwards them to the
+//! frontend as `app-log` events, the CLI prints them to stderr.
+
+use crate::service::PathProvider;
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use serde::Serialize;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use tauri::{AppHandle, Emitter};
+
+const LOG_FILE_NAME: &str = "app.log";
+/// Size above which the log is moved aside as `app.log.1` on startup
+const MAX_LOG_SIZE: u64 = 5 * 1024 * 1024;
+
+/// A record sent to the frontend as an `app-log` event
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AppLogRecord {
+    pub level: String,
+    /// Module that logged the record, e.g. `unofficial_neuro_kar_manager_lib::api::gdrive::jobs`
+    pub target: String,
+    pub message: String,
+    pub timestamp: String,
+}
+
+impl AppLogRecord {
+    fn new(record: &Record) -> Self {
+        Self {
+            level: record.level().to_string().to_lowercase(),
+            target: record.target().to_string(),
+            message: record.args().to_string(),
+            timestamp: chrono::Local::now().to_rfc3339(),
+        }
+    }
+}
+
+/// Path of the app log
+pub fn get_log_path(paths: &impl PathProvider) -> Result<PathBuf, String> {
+    Ok(paths.data_dir()?.join(LOG_FILE_NAME))
+}
+
+/// Log to `app.log` and the frontend, for the lifetime of the app
+pub fn init_app_logger(app: &AppHandle) {
+    let file = get_log_path(app).and_then(|path| open_log_file(&path)).ok();
+    install(AppLogger {
+        app: app.clone(),
+        file: Mutex::new(file),
+    });
+}
+
+/// Log to stderr, for the CLI
+pub fn init_stderr_logger() {
+    install(StderrLogger);
+}
+
+fn install(logger: impl Log + 'static) {
+    // Fails if a logger was already set, which then keeps receiving the records
+    if log::set_boxed_logger(Box::new(logger)).is_ok() {
+        log::set_max_level(LevelFilter::Info);
+    }
+}
+
+/// Open the log for appending, starting a new one if it grew too large
+fn open_log_file(path: &PathBuf) -> Result<File, String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+    if std::fs::metadata(path).is_ok_and(|metadata| metadata.len() > MAX_LOG_SIZE) {
+        let _ = std::fs::rename(path, path.with_extension("log.1"));
+    }
+
+    OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .map_err(|e| format!("Failed to open {}: {}", path.display(), e))
+}
+
+fn is_own_record(metadata: &Metadata) -> bool {
+    // Dependencies log through the same facade, only keep what this crate reports
+    metadata.target().starts_with(env!("CARGO_CRATE_NAME")) && metadata.level() <= Level::Info
+}
+
+struct AppLogger {
+    app: AppHandle,
+    file: Mutex<Option<File>>,
+}
+
+impl Log for AppLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        is_own_record(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let record = AppLogRecord::new(record);
+        if let Ok(mut file) = self.file.lock()
+            && let Some(file) = file.as_mut()
+        {
+            let _ = writeln!(
+                file,
+                "{} {:<5} {}: {}",
+                record.timestamp,
+                record.level.to_uppercase(),
+                record.target,
+                record.message
+            );
+        }
+        let _ = self.app.emit("app-log", &record);
+    }
+
+    fn flush(&self) {
+        if let Ok(mut file) = self.file.lock()
+            && let Some(file) = file.as_mut()
+        {
+            let _ = file.flush();
+        }
+    }
+}
+
+struct StderrLogger;
+
+impl Log for StderrLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        is_own_record(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("{}: {}", record.level(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
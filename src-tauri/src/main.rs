@@ -2,5 +2,11 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 fn main() {
+    // Commands like `sync` run headless instead of opening the window
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if let Some(code) = unofficial_neuro_kar_manager_lib::cli::run(&args) {
+        std::process::exit(code);
+    }
+
     unofficial_neuro_kar_manager_lib::run()
 }
@@ -0,0 +1,139 @@
+//! Services the download logic needs from whatever hosts it. The app implements
+//! them on `AppHandle`, tools and tests can run it against their own directory
+//! and an rclone RC server they started themselves.
+
+use crate::api::gdrive::JobManager;
+use crate::api::rclone::{self, RcCredentials};
+use rclone_sdk::Client;
+use serde::Serialize;
+use std::future::Future;
+use std::path::PathBuf;
+use tauri::{AppHandle, Emitter, Manager};
+
+/// Where persisted state and the rclone log live
+pub trait PathProvider: Send + Sync {
+    /// Directory for persisted state, e.g. the job queue and the rclone log
+    fn data_dir(&self) -> Result<PathBuf, String>;
+
+    /// The log the RC server writes to, which reports are read from
+    fn rclone_log_path(&self) -> Result<PathBuf, String> {
+        Ok(self.data_dir()?.join("rclone.log"))
+    }
+}
+
+/// Starts an rclone RC server, or finds the one already running
+pub trait RcloneLauncher: Send + Sync {
+    /// Make sure a server is running and return how to reach it
+    fn ensure_running(&self) -> impl Future<Output = Result<RcCredentials, String>> + Send;
+
+    /// How to reach the server if it's already running, without starting it
+    fn running(&self) -> impl Future<Output = Option<RcCredentials>> + Send;
+}
+
+/// Hands out clients for the RC API
+pub trait RcClientFactory: Send + Sync {
+    fn rc_client(&self) -> impl Future<Output = Result<Client, String>> + Send;
+
+    /// A client for the server if it's already running, without starting it
+    fn running_rc_client(&self) -> impl Future<Output = Option<Client>> + Send;
+}
+
+impl<T: RcloneLauncher> RcClientFactory for T {
+    async fn rc_client(&self) -> Result<Client, String> {
+        Ok(self.ensure_running().await?.client())
+    }
+
+    async fn running_rc_client(&self) -> Option<Client> {
+        Some(self.running().await?.client())
+    }
+}
+
+/// Tells whoever is watching about changes, e.g. the frontend
+pub trait EventSink: Send + Sync {
+    fn send_event<S: Serialize + Clone>(&self, event: &str, payload: S);
+}
+
+/// Everything the download logic needs from its host
+pub trait Host: PathProvider + RcClientFactory {}
+
+impl<T: PathProvider + RcClientFactory> Host for T {}
+
+/// A host running queued downloads in the background
+pub trait JobHost: Host + EventSink + Clone + 'static {
+    fn job_manager(&self) -> &JobManager;
+}
+
+impl PathProvider for AppHandle {
+    fn data_dir(&self) -> Result<PathBuf, String> {
+        self.path()
+            .app_local_data_dir()
+            .map_err(|e| format!("Failed to get app data dir: {}", e))
+    }
+}
+
+/// Starts the bundled rclone as a sidecar, supervised and restarted after crashes
+impl RcloneLauncher for AppHandle {
+    async fn ensure_running(&self) -> Result<RcCredentials, String> {
+        rclone::server::ensure_server(self).await
+    }
+
+    async fn running(&self) -> Option<RcCredentials> {
+        if !rclone::is_server_running(self).await {
+            return None;
+        }
+        self.state::<rclone::RcServerState>().credentials()
+    }
+}
+
+/// Emits events to the frontend
+impl EventSink for AppHandle {
+    fn send_event<S: Serialize + Clone>(&self, event: &str, payload: S) {
+        let _ = self.emit(event, payload);
+    }
+}
+
+impl JobHost for AppHandle {
+    fn job_manager(&self) -> &JobManager {
+        self.state::<JobManager>().inner()
+    }
+}
+
+/// A directory used as is, e.g. a temporary one in tests
+impl PathProvider for PathBuf {
+    fn data_dir(&self) -> Result<PathBuf, String> {
+        Ok(self.clone())
+    }
+}
+
+/// A server started outside of the app, e.g. `rclone rcd` run by hand
+impl RcloneLauncher for RcCredentials {
+    async fn ensure_running(&self) -> Result<RcCredentials, String> {
+        Ok(self.clone())
+    }
+
+    async fn running(&self) -> Option<RcCredentials> {
+        Some(self.clone())
+    }
+}
+
+/// Runs the download logic against an RC server started outside of the app
+pub struct ExternalServer {
+    pub data_dir: PathBuf,
+    pub credentials: RcCredentials,
+}
+
+impl PathProvider for ExternalServer {
+    fn data_dir(&self) -> Result<PathBuf, String> {
+        Ok(self.data_dir.clone())
+    }
+}
+
+impl RcloneLauncher for ExternalServer {
+    async fn ensure_running(&self) -> Result<RcCredentials, String> {
+        self.credentials.ensure_running().await
+    }
+
+    async fn running(&self) -> Option<RcCredentials> {
+        self.credentials.running().await
+    }
+}
@@ -1,3 +1,8 @@
+use crate::service::PathProvider;
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::path::{Path, PathBuf};
+
 /// Extract JSON content from text, finding the first '{' and last '}'
 pub fn extract_json(text: &str) -> Option<String> {
     let start = text.find('{')?;
@@ -8,3 +13,151 @@ pub fn extract_json(text: &str) -> Option<String> {
         None
     }
 }
+
+/// Get the path to a file inside the app local data directory
+pub fn app_data_file(paths: &impl PathProvider, name: &str) -> Result<PathBuf, String> {
+    Ok(paths.data_dir()?.join(name))
+}
+
+/// Read a JSON file, returning `None` if it does not exist
+pub fn read_json_file<T: DeserializeOwned>(path: &Path) -> Result<Option<T>, String> {
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read {}: {}", path.display(), e))?;
+    let value = serde_json::from_str(&content)
+        .map_err(|e| format!("Failed to parse {}: {}", path.display(), e))?;
+    Ok(Some(value))
+}
+
+/// Write a JSON file, replacing it atomically so a crash never leaves it half-written
+pub fn write_json_file<T: Serialize>(path: &Path, value: &T) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+    }
+
+    let content = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    let tmp_path = path.with_extension("tmp");
+    std::fs::write(&tmp_path, content)
+        .map_err(|e| format!("Failed to write {}: {}", tmp_path.display(), e))?;
+    std::fs::rename(&tmp_path, path)
+        .map_err(|e| format!("Failed to write {}: {}", path.display(), e))
+}
+
+/// Remove a file if it exists
+pub fn remove_file_if_exists(path: &Path) -> Result<(), String> {
+    if path.exists() {
+        std::fs::remove_file(path)
+            .map_err(|e| format!("Failed to remove {}: {}", path.display(), e))?;
+    }
+    Ok(())
+}
+
+/// A file found by `walk_files`
+pub struct WalkedFile {
+    pub path: PathBuf,
+    /// Relative to the root, with `/` separators
+    pub relative: String,
+    pub size: u64,
+}
+
+/// Files under a directory, and the directories that couldn't be read
+#[derive(Default)]
+pub struct WalkedFiles {
+    pub files: Vec<WalkedFile>,
+    /// Relative to the root like the files, empty for the root itself
+    pub failed_dirs: Vec<String>,
+}
+
+/// Every file under `root`, sorted by path. Symlinks are skipped like rclone does
+/// without `--links`, which also keeps links back up the tree from looping.
+/// Directories that can't be read are skipped and reported instead of failing the walk.
+pub fn walk_files(root: &Path) -> WalkedFiles {
+    let mut walked = WalkedFiles::default();
+    let relative = |path: &Path| {
+        path.strip_prefix(root)
+            .map(|relative| relative.to_string_lossy().replace('\\', "/"))
+            .ok()
+    };
+
+    let mut stack = vec![root.to_path_buf()];
+    while let Some(dir) = stack.pop() {
+        let entries = match std::fs::read_dir(&dir) {
+            Ok(entries) => entries,
+            Err(e) => {
+                log::warn!("Skipping {}: {}", dir.display(), e);
+                walked.failed_dirs.extend(relative(&dir));
+                continue;
+            }
+        };
+
+        for entry in entries.flatten() {
+            let path = entry.path();
+            // Doesn't follow symlinks, unlike `Path::is_dir`
+            let Ok(file_type) = entry.file_type() else {
+                continue;
+            };
+            if file_type.is_dir() {
+                stack.push(path);
+            } else if file_type.is_file()
+                && let Some(relative) = relative(&path)
+            {
+                let size = entry.metadata().map_or(0, |metadata| metadata.len());
+                walked.files.push(WalkedFile {
+                    path,
+                    relative,
+                    size,
+                });
+            }
+        }
+    }
+
+    walked.files.sort_by(|a, b| a.relative.cmp(&b.relative));
+    walked
+}
+
+/// Parse an rclone size like `64M`, `1.5Mi` or `512` into bytes, using binary units like rclone
+pub fn parse_size(size: &str) -> Option<u64> {
+    let size = size.trim();
+    let split = size
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .unwrap_or(size.len());
+    let (number, suffix) = size.split_at(split);
+    let number: f64 = number.parse().ok()?;
+
+    let multiplier: u64 = match suffix
+        .to_ascii_lowercase()
+        .trim_end_matches('b')
+        .trim_end_matches('i')
+    {
+        "" => 1,
+        "k" => 1 << 10,
+        "m" => 1 << 20,
+        "g" => 1 << 30,
+        "t" => 1 << 40,
+        "p" => 1 << 50,
+        _ => return None,
+    };
+
+    let bytes = (number * multiplier as f64).round();
+    (bytes.is_finite() && bytes < u64::MAX as f64).then_some(bytes as u64)
+}
+
+/// Format a byte count for display, e.g. `1.5 GiB`
+pub fn format_bytes(bytes: f64) -> String {
+    const UNITS: [&str; 5] = ["B", "KiB", "MiB", "GiB", "TiB"];
+    let mut value = bytes;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{:.0} {}", value, UNITS[unit])
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
@@ -0,0 +1,40 @@
+use chrono::{TimeZone, Utc};
+use unofficial_neuro_kar_manager_lib::api::gdrive::current_bwlimit_rate;
+
+#[test]
+fn plain_limit_is_sent_as_is() {
+    // A Wednesday
+    let now = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+
+    assert_eq!(current_bwlimit_rate("1M:256k", &now).unwrap(), "1M:256k");
+}
+
+#[test]
+fn timetable_resolves_to_the_current_slot() {
+    let limit = "08:00,512k 23:00,off";
+    let morning = Utc.with_ymd_and_hms(2024, 1, 3, 9, 30, 0).unwrap();
+    let night = Utc.with_ymd_and_hms(2024, 1, 3, 23, 30, 0).unwrap();
+    let early = Utc.with_ymd_and_hms(2024, 1, 3, 2, 0, 0).unwrap();
+
+    assert_eq!(current_bwlimit_rate(limit, &morning).unwrap(), "512k");
+    assert_eq!(current_bwlimit_rate(limit, &night).unwrap(), "off");
+    // Before the first slot of the day the previous day's last one still applies
+    assert_eq!(current_bwlimit_rate(limit, &early).unwrap(), "off");
+}
+
+#[test]
+fn weekly_timetable_carries_over_into_the_next_week() {
+    let limit = "Mon-00:00,1M Sat-10:00,off";
+    let wednesday = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+    let sunday = Utc.with_ymd_and_hms(2024, 1, 7, 12, 0, 0).unwrap();
+
+    assert_eq!(current_bwlimit_rate(limit, &wednesday).unwrap(), "1M");
+    assert_eq!(current_bwlimit_rate(limit, &sunday).unwrap(), "off");
+}
+
+#[test]
+fn invalid_timetable_is_rejected() {
+    let now = Utc.with_ymd_and_hms(2024, 1, 3, 12, 0, 0).unwrap();
+
+    assert!(current_bwlimit_rate("25:00,1M", &now).is_err());
+}
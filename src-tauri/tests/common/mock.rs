@@ -0,0 +1,242 @@
+//! A scriptable rclone RC server keeping remotes, files and jobs between calls
+
+use super::{FakeRcServer, RcRequest, append_log};
+use serde_json::{Map, Value, json};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, MutexGuard};
+use unofficial_neuro_kar_manager_lib::api::rclone::RcCredentials;
+
+/// PID reported by `core/pid`
+pub const MOCK_PID: i64 = 4242;
+
+/// How jobs started through `sync/sync`, `sync/copy` and `operations/check` behave
+#[derive(Debug, Clone)]
+pub struct JobScript {
+    /// `job/status` calls answered with "running" before the job finishes
+    pub polls: usize,
+    /// Error the job finishes with, if any
+    pub error: Option<String>,
+    /// Returned by `core/stats` for the job
+    pub stats: Value,
+    /// Output of the finished job, e.g. the result of `operations/check`
+    pub output: Option<Value>,
+}
+
+impl Default for JobScript {
+    fn default() -> Self {
+        Self {
+            polls: 0,
+            error: None,
+            stats: json!({
+                "bytes": 0,
+                "checks": 0,
+                "deletes": 0,
+                "errors": 0,
+                "renames": 0,
+                "transfers": 0,
+                "elapsedTime": 0.1
+            }),
+            output: None,
+        }
+    }
+}
+
+struct Job {
+    remaining_polls: usize,
+    script: JobScript,
+}
+
+#[derive(Default)]
+struct MockState {
+    remotes: Map<String, Value>,
+    files: Vec<Value>,
+    drive_changes: Vec<Value>,
+    script: JobScript,
+    jobs: HashMap<i64, Job>,
+    next_jobid: i64,
+    failures: HashMap<String, VecDeque<(u16, String)>>,
+    sync_log: Option<(PathBuf, Vec<Value>)>,
+}
+
+pub struct MockRcServer {
+    server: FakeRcServer,
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockRcServer {
+    pub async fn start() -> Self {
+        let state = Arc::new(Mutex::new(MockState {
+            next_jobid: 1,
+            ..Default::default()
+        }));
+
+        let handler_state = state.clone();
+        let server = FakeRcServer::start(move |path, params| {
+            let mut state = handler_state.lock().unwrap_or_else(|e| e.into_inner());
+            handle(&mut state, path, params)
+        })
+        .await;
+
+        Self { server, state }
+    }
+
+    fn lock(&self) -> MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn credentials(&self) -> RcCredentials {
+        self.server.credentials()
+    }
+
+    /// Requests received so far for one endpoint
+    pub fn requests_to(&self, path: &str) -> Vec<RcRequest> {
+        self.server.requests_to(path)
+    }
+
+    /// Add a remote to the config returned by `config/dump`
+    pub fn add_remote(&self, name: &str, remote_type: &str) {
+        self.lock()
+            .remotes
+            .insert(name.to_string(), json!({ "type": remote_type }));
+    }
+
+    /// Entries returned by `operations/list`, in rclone's format
+    pub fn set_files(&self, files: Vec<Value>) {
+        self.lock().files = files;
+    }
+
+    /// Files returned by the `query` backend command, in the Drive API's format
+    pub fn set_drive_changes(&self, changes: Vec<Value>) {
+        self.lock().drive_changes = changes;
+    }
+
+    /// Script the jobs started from now on
+    pub fn script_jobs(&self, script: JobScript) {
+        self.lock().script = script;
+    }
+
+    /// Answer the next call to `path` with an error instead
+    pub fn fail_next(&self, path: &str, status: u16, error: &str) {
+        self.lock()
+            .failures
+            .entry(path.to_string())
+            .or_default()
+            .push_back((status, error.to_string()));
+    }
+
+    /// Drop all jobs, like rclone does once they expire or the server restarts
+    pub fn forget_jobs(&self) {
+        self.lock().jobs.clear();
+    }
+
+    /// Append `records` to the rclone log in `data_dir` whenever a sync starts
+    pub fn log_syncs_to(&self, data_dir: PathBuf, records: Vec<Value>) {
+        self.lock().sync_log = Some((data_dir, records));
+    }
+}
+
+fn handle(state: &mut MockState, path: &str, params: &Value) -> (u16, Value) {
+    if let Some((status, error)) = state.failures.get_mut(path).and_then(VecDeque::pop_front) {
+        return (status, error_response(path, status, &error));
+    }
+
+    match path {
+        "/core/pid" => (200, json!({ "pid": MOCK_PID })),
+        "/sync/sync" | "/sync/copy" | "/operations/check" => {
+            if path != "/operations/check"
+                && let Some((data_dir, records)) = &state.sync_log
+            {
+                append_log(data_dir, records);
+            }
+
+            let jobid = state.next_jobid;
+            state.next_jobid += 1;
+            state.jobs.insert(
+                jobid,
+                Job {
+                    remaining_polls: state.script.polls,
+                    script: state.script.clone(),
+                },
+            );
+            (200, json!({ "jobid": jobid }))
+        }
+        "/job/status" => {
+            let jobid = int_param(params, "jobid").unwrap_or(0);
+            let Some(job) = state.jobs.get_mut(&jobid) else {
+                return (500, error_response(path, 500, "job not found"));
+            };
+
+            let finished = job.remaining_polls == 0;
+            job.remaining_polls = job.remaining_polls.saturating_sub(1);
+            let error = match (finished, &job.script.error) {
+                (true, Some(error)) => error.clone(),
+                _ => String::new(),
+            };
+            let mut status = json!({
+                "id": jobid,
+                "duration": 0.1,
+                "startTime": "2024-01-01T00:00:00Z",
+                "endTime": if finished { "2024-01-01T00:00:01Z" } else { "" },
+                "error": error,
+                "finished": finished,
+                "success": finished && error.is_empty()
+            });
+            if let (true, Some(output)) = (finished, &job.script.output) {
+                status["output"] = output.clone();
+            }
+            (200, status)
+        }
+        "/core/stats" => {
+            let job = params["group"]
+                .as_str()
+                .and_then(|group| group.strip_prefix("job/"))
+                .and_then(|id| id.parse().ok())
+                .and_then(|id: i64| state.jobs.get(&id));
+            let stats = job.map_or(&state.script.stats, |job| &job.script.stats);
+            (200, stats.clone())
+        }
+        "/operations/list" => (200, json!({ "list": state.files })),
+        "/backend/command" if params["command"] == "query" => {
+            (200, json!({ "result": state.drive_changes }))
+        }
+        "/config/dump" => (200, Value::Object(state.remotes.clone())),
+        "/config/create" => {
+            let Some(name) = params["name"].as_str() else {
+                return (400, error_response(path, 400, "name is required"));
+            };
+
+            // Parameters arrive as a JSON string in the query
+            let mut remote = match &params["parameters"] {
+                Value::String(s) => serde_json::from_str(s).unwrap_or_default(),
+                Value::Object(map) => map.clone(),
+                _ => Map::new(),
+            };
+            remote.insert("type".to_string(), params["type"].clone());
+            state
+                .remotes
+                .insert(name.to_string(), Value::Object(remote));
+            (200, json!({}))
+        }
+        _ => (404, error_response(path, 404, "couldn't find method")),
+    }
+}
+
+/// Error body in rclone's format
+fn error_response(path: &str, status: u16, error: &str) -> Value {
+    json!({
+        "error": error,
+        "input": {},
+        "path": path.trim_start_matches('/'),
+        "status": status
+    })
+}
+
+/// Read an integer parameter, sent as a number in bodies and a string in queries
+fn int_param(params: &Value, name: &str) -> Option<i64> {
+    match &params[name] {
+        Value::Number(n) => n.as_i64(),
+        Value::String(s) => s.parse().ok(),
+        _ => None,
+    }
+}
@@ -0,0 +1,200 @@
+//! Stand-in for an `rclone rcd` server, answering RC calls with canned JSON
+#![allow(dead_code)]
+
+pub mod mock;
+
+use serde_json::{Map, Value, json};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use unofficial_neuro_kar_manager_lib::api::gdrive::DownloadConfig;
+use unofficial_neuro_kar_manager_lib::api::rclone::RcCredentials;
+
+type Handler = dyn Fn(&str, &Value) -> (u16, Value) + Send + Sync;
+
+/// A request received by the fake server
+#[derive(Debug, Clone)]
+pub struct RcRequest {
+    pub path: String,
+    pub body: Value,
+}
+
+pub struct FakeRcServer {
+    pub port: u16,
+    requests: Arc<Mutex<Vec<RcRequest>>>,
+}
+
+impl FakeRcServer {
+    /// Serve RC calls with `handler`, which gets the endpoint (e.g. `/core/pid`)
+    /// and the parameters and returns the status code and JSON response
+    pub async fn start(
+        handler: impl Fn(&str, &Value) -> (u16, Value) + Send + Sync + 'static,
+    ) -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let requests = Arc::new(Mutex::new(Vec::new()));
+        let handler: Arc<Handler> = Arc::new(handler);
+
+        let received = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let handler = handler.clone();
+                let received = received.clone();
+                tokio::spawn(async move {
+                    let _ = serve(stream, handler, received).await;
+                });
+            }
+        });
+
+        Self { port, requests }
+    }
+
+    pub fn credentials(&self) -> RcCredentials {
+        RcCredentials {
+            port: self.port,
+            user: "user".to_string(),
+            pass: "pass".to_string(),
+        }
+    }
+
+    /// Requests received so far, oldest first
+    pub fn requests(&self) -> Vec<RcRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Requests received so far for one endpoint
+    pub fn requests_to(&self, path: &str) -> Vec<RcRequest> {
+        self.requests()
+            .into_iter()
+            .filter(|request| request.path == path)
+            .collect()
+    }
+}
+
+/// Answer a single HTTP request on the connection
+async fn serve(
+    stream: TcpStream,
+    handler: Arc<Handler>,
+    received: Arc<Mutex<Vec<RcRequest>>>,
+) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream);
+
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line).await?;
+    let target = request_line.split_whitespace().nth(1).unwrap_or("/");
+    let path = target.split('?').next().unwrap_or(target).to_string();
+
+    let mut content_length = 0;
+    loop {
+        let mut header = String::new();
+        reader.read_line(&mut header).await?;
+        let header = header.trim_end();
+        if header.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = header.split_once(':')
+            && name.eq_ignore_ascii_case("content-length")
+        {
+            content_length = value.trim().parse().unwrap_or(0);
+        }
+    }
+
+    let mut body = vec![0; content_length];
+    reader.read_exact(&mut body).await?;
+    let body = params(target, &body);
+
+    received.lock().unwrap().push(RcRequest {
+        path: path.clone(),
+        body: body.clone(),
+    });
+    let (status, response) = handler(&path, &body);
+
+    let response = response.to_string();
+    let head = format!(
+        "HTTP/1.1 {} Status\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        status,
+        response.len()
+    );
+    let mut stream = reader.into_inner();
+    stream.write_all(head.as_bytes()).await?;
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+/// Merge the JSON body and query string into one object, like rclone does.
+/// The SDK sends most parameters in the query string.
+fn params(target: &str, body: &[u8]) -> Value {
+    let mut params = match serde_json::from_slice(body) {
+        Ok(Value::Object(map)) => map,
+        _ => Map::new(),
+    };
+
+    if let Ok(url) = reqwest::Url::parse(&format!("http://localhost{}", target)) {
+        for (key, value) in url.query_pairs() {
+            params
+                .entry(key.to_string())
+                .or_insert_with(|| Value::String(value.to_string()));
+        }
+    }
+    Value::Object(params)
+}
+
+/// Create an empty directory for a test's app data
+pub fn temp_data_dir(name: &str) -> PathBuf {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let dir = std::env::temp_dir().join(format!(
+        "neuro-kar-test-{}-{}-{}",
+        name,
+        std::process::id(),
+        COUNTER.fetch_add(1, Ordering::SeqCst)
+    ));
+    let _ = std::fs::remove_dir_all(&dir);
+    std::fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+/// Append records in rclone's `--use-json-log` format to the log in `data_dir`
+pub fn append_log(data_dir: &std::path::Path, records: &[Value]) {
+    use std::io::Write;
+    let mut file = std::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(data_dir.join("rclone.log"))
+        .unwrap();
+    for record in records {
+        writeln!(file, "{}", record).unwrap();
+    }
+}
+
+/// `job/status` of a job that finished without errors
+pub fn finished_job() -> Value {
+    json!({
+        "id": 1,
+        "duration": 0.1,
+        "startTime": "2024-01-01T00:00:00Z",
+        "endTime": "2024-01-01T00:00:01Z",
+        "error": "",
+        "finished": true,
+        "success": true
+    })
+}
+
+/// Sync of the `abc123` folder on the `gdrive` remote into `destination`
+pub fn sync_config(destination: &str) -> DownloadConfig {
+    DownloadConfig::new(
+        "https://drive.google.com/drive/folders/abc123".to_string(),
+        destination.to_string(),
+        Some("gdrive".to_string()),
+        true,
+        false,
+        None,
+        false,
+        false,
+        false,
+        None,
+        None,
+    )
+    .unwrap()
+}
@@ -0,0 +1,130 @@
+mod common;
+
+use common::{FakeRcServer, finished_job, temp_data_dir};
+use serde_json::json;
+use unofficial_neuro_kar_manager_lib::api::gdrive::{self, FileComparison};
+use unofficial_neuro_kar_manager_lib::service::ExternalServer;
+
+#[tokio::test]
+async fn compares_local_files_with_drive() {
+    let local = temp_data_dir("compare");
+    std::fs::write(local.join("same.mp3"), b"abc").unwrap();
+    std::fs::write(local.join("changed.mp3"), b"short").unwrap();
+    std::fs::write(local.join("extra.mp3"), b"x").unwrap();
+
+    let server = FakeRcServer::start(|path, _| match path {
+        "/operations/list" => (
+            200,
+            json!({
+                "list": [
+                    { "Path": "same.mp3", "Name": "same.mp3", "IsDir": false, "Size": 3 },
+                    { "Path": "changed.mp3", "Name": "changed.mp3", "IsDir": false, "Size": 9 },
+                    { "Path": "missing.mp3", "Name": "missing.mp3", "IsDir": false, "Size": 4 }
+                ]
+            }),
+        ),
+        _ => (404, json!({ "error": "not found" })),
+    })
+    .await;
+
+    let host = ExternalServer {
+        data_dir: temp_data_dir("compare-host"),
+        credentials: server.credentials(),
+    };
+    let comparison = gdrive::compare_files(&host, "abc123", "gdrive", &local, false)
+        .await
+        .unwrap();
+
+    let statuses: Vec<_> = comparison
+        .files
+        .iter()
+        .map(|f| (f.path.as_str(), f.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("changed.mp3", FileComparison::SizeMismatch),
+            ("extra.mp3", FileComparison::ExtraLocally),
+            ("missing.mp3", FileComparison::MissingLocally),
+            ("same.mp3", FileComparison::Identical),
+        ]
+    );
+    assert!(server.requests_to("/operations/hashsum").is_empty());
+    assert!(!comparison.hashes_checked);
+}
+
+#[tokio::test]
+async fn hashes_only_files_of_the_same_size() {
+    let local = temp_data_dir("compare-hashes");
+    std::fs::write(local.join("same.mp3"), b"abc").unwrap();
+    std::fs::write(local.join("edited [v2].mp3"), b"abd").unwrap();
+    std::fs::write(local.join("changed.mp3"), b"short").unwrap();
+
+    let server = FakeRcServer::start(|path, params| match path {
+        "/operations/list" => (
+            200,
+            json!({
+                "list": [
+                    { "Path": "same.mp3", "Name": "same.mp3", "IsDir": false, "Size": 3 },
+                    { "Path": "edited [v2].mp3", "Name": "edited [v2].mp3", "IsDir": false, "Size": 3 },
+                    { "Path": "changed.mp3", "Name": "changed.mp3", "IsDir": false, "Size": 9 }
+                ]
+            }),
+        ),
+        // Job 1 hashes the Drive, job 2 the local files
+        "/operations/hashsum" => {
+            let remote = params["fs"].as_str().unwrap_or_default().starts_with("gdrive");
+            (200, json!({ "jobid": if remote { 1 } else { 2 } }))
+        }
+        "/job/status" => {
+            let edited = if params["jobid"] == 1 {
+                "11111111111111111111111111111111"
+            } else {
+                "22222222222222222222222222222222"
+            };
+            let mut status = finished_job();
+            status["output"] = json!({
+                "hashsum": [
+                    "900150983cd24fb0d6963f7d28e17f72  same.mp3",
+                    format!("{}  edited [v2].mp3", edited)
+                ]
+            });
+            (200, status)
+        }
+        _ => (404, json!({ "error": "not found" })),
+    })
+    .await;
+
+    let host = ExternalServer {
+        data_dir: temp_data_dir("compare-hashes-host"),
+        credentials: server.credentials(),
+    };
+    let comparison = gdrive::compare_files(&host, "abc123", "gdrive", &local, true)
+        .await
+        .unwrap();
+
+    let statuses: Vec<_> = comparison
+        .files
+        .iter()
+        .map(|f| (f.path.as_str(), f.status))
+        .collect();
+    assert_eq!(
+        statuses,
+        vec![
+            ("changed.mp3", FileComparison::SizeMismatch),
+            ("edited [v2].mp3", FileComparison::HashMismatch),
+            ("same.mp3", FileComparison::Identical),
+        ]
+    );
+    assert!(comparison.hashes_checked);
+
+    let requests = server.requests_to("/operations/hashsum");
+    assert_eq!(requests.len(), 2);
+    for request in requests {
+        assert_eq!(request.body["_async"], true);
+        assert_eq!(
+            request.body["_filter"]["IncludeRule"],
+            json!(["/edited \\[v2\\].mp3", "/same.mp3"])
+        );
+    }
+}
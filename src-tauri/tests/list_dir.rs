@@ -0,0 +1,58 @@
+mod common;
+
+use common::mock::MockRcServer;
+use serde_json::json;
+use unofficial_neuro_kar_manager_lib::api::gdrive::{self, GdriveFile};
+
+#[tokio::test]
+async fn lists_one_level_of_a_folder() {
+    let server = MockRcServer::start().await;
+    server.set_files(vec![
+        json!({ "Path": "2024/Song.mp3", "Name": "Song.mp3", "IsDir": false, "Size": 42 }),
+        json!({ "Path": "2024/Live", "Name": "Live", "IsDir": true, "Size": -1 }),
+    ]);
+
+    let entries = gdrive::list_dir(&server.credentials(), "abc123", "gdrive", "2024")
+        .await
+        .unwrap();
+
+    assert_eq!(entries[0].name, "Live");
+    assert_eq!(entries[0].size, None);
+    assert_eq!(entries[1].size, Some(42));
+    let requests = server.requests_to("/operations/list");
+    assert_eq!(requests[0].body["remote"], "2024");
+    assert!(requests[0].body.get("opt").is_none());
+}
+
+#[test]
+fn sums_up_folder_totals() {
+    let file = |path: &str, is_dir: bool, size: i64| GdriveFile {
+        path: path.to_string(),
+        name: path.rsplit('/').next().unwrap().to_string(),
+        is_dir,
+        size,
+        mime_type: String::new(),
+        id: None,
+    };
+    let files = vec![
+        file("2024", true, 0),
+        file("2024/Live", true, 0),
+        file("2024/Live/Set.mp3", false, 10),
+        file("2024/Song.mp3", false, 5),
+        file("2024/Notes", false, -1),
+        file("Cover.jpg", false, 7),
+    ];
+
+    let index = gdrive::dir_index(&files);
+    let root = &index[""];
+    assert_eq!(root.len(), 2);
+    assert_eq!(root[0].size, Some(15));
+    assert_eq!(root[0].child_count, Some(3));
+    assert_eq!(root[0].file_count, Some(3));
+    assert_eq!(root[1].child_count, None);
+
+    let year = &index["2024"];
+    assert_eq!(year.len(), 3);
+    assert_eq!(year[0].path, "2024/Live");
+    assert_eq!(year[0].child_count, Some(1));
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use common::mock::MockRcServer;
+use common::temp_data_dir;
+use serde_json::json;
+use unofficial_neuro_kar_manager_lib::api::gdrive;
+use unofficial_neuro_kar_manager_lib::service::ExternalServer;
+
+#[tokio::test]
+async fn refreshed_listing_reports_new_entries() {
+    let data_dir = temp_data_dir("listing-cache");
+    let server = MockRcServer::start().await;
+    server.set_files(vec![
+        json!({ "Path": "a.mp3", "Name": "a.mp3", "IsDir": false, "Size": 1 }),
+        json!({ "Path": "b.mp3", "Name": "b.mp3", "IsDir": false, "Size": 2 }),
+    ]);
+    let host = ExternalServer {
+        data_dir: data_dir.clone(),
+        credentials: server.credentials(),
+    };
+
+    let first = gdrive::refresh_listing(&host, "abc123", "gdrive", true)
+        .await
+        .unwrap();
+    assert_eq!(first.added.len(), 2);
+    assert!(gdrive::read_cached_listing(&data_dir, "abc123", "gdrive").is_some());
+
+    server.set_files(vec![
+        json!({ "Path": "a.mp3", "Name": "a.mp3", "IsDir": false, "Size": 10 }),
+        json!({ "Path": "c.mp3", "Name": "c.mp3", "IsDir": false, "Size": 3 }),
+    ]);
+    let second = gdrive::refresh_listing(&host, "abc123", "gdrive", true)
+        .await
+        .unwrap();
+
+    assert_eq!(second.added[0].path, "c.mp3");
+    assert_eq!(second.changed[0].path, "a.mp3");
+    assert_eq!(second.removed, vec!["b.mp3"]);
+
+    // Another remote for the same folder has its own listing
+    assert!(gdrive::read_cached_listing(&data_dir, "abc123", "other").is_none());
+}
+
+#[tokio::test]
+async fn refresh_applies_changes_since_last_listing() {
+    let data_dir = temp_data_dir("listing-changes");
+    let server = MockRcServer::start().await;
+    server.set_files(vec![
+        json!({ "Path": "2024", "Name": "2024", "IsDir": true, "Size": -1, "ID": "f2024" }),
+        json!({ "Path": "2024/a.mp3", "Name": "a.mp3", "IsDir": false, "Size": 1, "ID": "a" }),
+        json!({ "Path": "b.mp3", "Name": "b.mp3", "IsDir": false, "Size": 2, "ID": "b" }),
+    ]);
+    let host = ExternalServer {
+        data_dir: data_dir.clone(),
+        credentials: server.credentials(),
+    };
+    gdrive::refresh_listing(&host, "abc123", "gdrive", true)
+        .await
+        .unwrap();
+
+    server.set_drive_changes(vec![
+        json!({ "id": "live", "name": "Live", "mimeType": "application/vnd.google-apps.folder", "parents": ["f2024"] }),
+        json!({ "id": "c", "name": "c.mp3", "mimeType": "audio/mpeg", "parents": ["live"], "size": "3" }),
+        json!({ "id": "a", "name": "a.mp3", "mimeType": "audio/mpeg", "parents": ["abc123"], "size": "1" }),
+        json!({ "id": "b", "name": "b.mp3", "mimeType": "audio/mpeg", "parents": ["abc123"], "trashed": true }),
+        json!({ "id": "x", "name": "elsewhere.mp3", "mimeType": "audio/mpeg", "parents": ["other"] }),
+    ]);
+    let update = gdrive::refresh_listing(&host, "abc123", "gdrive", false)
+        .await
+        .unwrap();
+
+    let mut added: Vec<_> = update.added.iter().map(|f| f.path.as_str()).collect();
+    added.sort();
+    assert_eq!(added, vec!["2024/Live", "2024/Live/c.mp3", "a.mp3"]);
+    assert_eq!(update.removed, vec!["2024/a.mp3", "b.mp3"]);
+    assert!(update.changed.is_empty());
+
+    // Only the first refresh listed the folder, the new one was asked for afterwards
+    assert_eq!(server.requests_to("/operations/list").len(), 1);
+    let queries = server.requests_to("/backend/command");
+    assert_eq!(queries.len(), 2);
+    assert!(
+        queries[1].body["arg"][0]
+            .as_str()
+            .unwrap()
+            .starts_with("('live' in parents) and (modifiedTime > '")
+    );
+}
+
+#[tokio::test]
+async fn change_query_is_limited_to_the_listed_folders() {
+    let data_dir = temp_data_dir("listing-query-scope");
+    let server = MockRcServer::start().await;
+    server.set_files(vec![
+        json!({ "Path": "2024", "Name": "2024", "IsDir": true, "Size": -1, "ID": "f2024" }),
+        json!({ "Path": "2024/a.mp3", "Name": "a.mp3", "IsDir": false, "Size": 1, "ID": "a" }),
+        json!({ "Path": "keep.mp3", "Name": "keep.mp3", "IsDir": false, "Size": 2, "ID": "k" }),
+    ]);
+    let host = ExternalServer {
+        data_dir: data_dir.clone(),
+        credentials: server.credentials(),
+    };
+    gdrive::refresh_listing(&host, "abc123", "gdrive", true)
+        .await
+        .unwrap();
+
+    server.set_drive_changes(vec![
+        // Deleted into the trash, and files of the same name in someone else's folder
+        json!({ "id": "a", "name": "a.mp3", "mimeType": "audio/mpeg", "parents": ["f2024"], "size": "1", "trashed": true }),
+        json!({ "id": "y", "name": "keep.mp3", "mimeType": "audio/mpeg", "parents": ["elsewhere"], "size": "9" }),
+        json!({ "id": "z", "name": "2024", "mimeType": "application/vnd.google-apps.folder", "parents": ["elsewhere"] }),
+    ]);
+    let update = gdrive::refresh_listing(&host, "abc123", "gdrive", false)
+        .await
+        .unwrap();
+
+    assert!(update.added.is_empty());
+    assert!(update.changed.is_empty());
+    assert_eq!(update.removed, vec!["2024/a.mp3"]);
+
+    let queries = server.requests_to("/backend/command");
+    assert_eq!(queries.len(), 1);
+    let query = queries[0].body["arg"][0].as_str().unwrap();
+    assert!(query.starts_with("('f2024' in parents or 'abc123' in parents) and ("));
+    assert!(query.ends_with(" or trashed = true)"));
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use common::temp_data_dir;
+use unofficial_neuro_kar_manager_lib::utils::walk_files;
+
+#[test]
+fn walks_nested_files_in_order() {
+    let root = temp_data_dir("walk-nested");
+    std::fs::create_dir_all(root.join("b/c")).unwrap();
+    std::fs::write(root.join("b/c/song.mp3"), b"abc").unwrap();
+    std::fs::write(root.join("a.flac"), b"a").unwrap();
+
+    let walked = walk_files(&root);
+
+    let files: Vec<(&str, u64)> = walked
+        .files
+        .iter()
+        .map(|file| (file.relative.as_str(), file.size))
+        .collect();
+    assert_eq!(files, vec![("a.flac", 1), ("b/c/song.mp3", 3)]);
+    assert!(walked.failed_dirs.is_empty());
+}
+
+#[cfg(unix)]
+#[test]
+fn skips_symlinks_instead_of_looping() {
+    let root = temp_data_dir("walk-symlink-loop");
+    std::fs::create_dir_all(root.join("album")).unwrap();
+    std::fs::write(root.join("album/song.mp3"), b"abc").unwrap();
+    std::os::unix::fs::symlink(&root, root.join("album/loop")).unwrap();
+
+    let walked = walk_files(&root);
+
+    assert_eq!(walked.files.len(), 1);
+    assert_eq!(walked.files[0].relative, "album/song.mp3");
+}
+
+#[test]
+fn reports_unreadable_root() {
+    let root = temp_data_dir("walk-missing").join("missing");
+
+    let walked = walk_files(&root);
+
+    assert!(walked.files.is_empty());
+    assert_eq!(walked.failed_dirs, vec![String::new()]);
+}
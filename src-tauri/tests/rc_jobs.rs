@@ -0,0 +1,341 @@
+mod common;
+
+use common::mock::{JobScript, MOCK_PID, MockRcServer};
+use common::{sync_config, temp_data_dir};
+use serde::Serialize;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::Arc;
+use unofficial_neuro_kar_manager_lib::api::gdrive::{self, JobManager, SyncJobError};
+use unofficial_neuro_kar_manager_lib::api::rclone::{LogSpan, RcCredentials};
+use unofficial_neuro_kar_manager_lib::service::{
+    EventSink, ExternalServer, JobHost, PathProvider, RcloneLauncher,
+};
+
+/// Runs queued jobs against the mock server, without the app
+#[derive(Clone)]
+struct TestHost(Arc<(ExternalServer, JobManager)>);
+
+impl TestHost {
+    fn new(data_dir: PathBuf, credentials: RcCredentials) -> Self {
+        let jobs = JobManager::detached(&data_dir);
+        Self(Arc::new((
+            ExternalServer {
+                data_dir,
+                credentials,
+            },
+            jobs,
+        )))
+    }
+}
+
+impl PathProvider for TestHost {
+    fn data_dir(&self) -> Result<PathBuf, String> {
+        self.0.0.data_dir()
+    }
+}
+
+impl RcloneLauncher for TestHost {
+    async fn ensure_running(&self) -> Result<RcCredentials, String> {
+        self.0.0.ensure_running().await
+    }
+
+    async fn running(&self) -> Option<RcCredentials> {
+        self.0.0.running().await
+    }
+}
+
+impl EventSink for TestHost {
+    fn send_event<S: Serialize + Clone>(&self, _event: &str, _payload: S) {}
+}
+
+impl JobHost for TestHost {
+    fn job_manager(&self) -> &JobManager {
+        &self.0.1
+    }
+}
+
+fn copy_body() -> serde_json::Value {
+    json!({
+        "srcFs": "gdrive,root_folder_id=abc123:",
+        "dstFs": "/tmp/archive",
+        "_async": true
+    })
+}
+
+#[tokio::test]
+async fn answers_core_pid() {
+    let server = MockRcServer::start().await;
+
+    let response = server
+        .credentials()
+        .client()
+        .core_pid(None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(response.pid, MOCK_PID);
+}
+
+#[tokio::test]
+async fn sync_job_returns_final_stats() {
+    let server = MockRcServer::start().await;
+    server.script_jobs(JobScript {
+        stats: json!({ "bytes": 2048, "transfers": 2, "checks": 5, "errors": 0, "elapsedTime": 1.5 }),
+        ..Default::default()
+    });
+
+    let result = gdrive::start_sync_job(&server.credentials().client(), &copy_body(), "/sync/copy")
+        .await
+        .unwrap();
+
+    assert_eq!(result.bytes, 2048);
+    assert_eq!(result.transfers, 2);
+    assert_eq!(result.checks, 5);
+
+    let request = &server.requests_to("/core/stats")[0];
+    assert_eq!(request.body["group"], "job/1");
+}
+
+#[tokio::test]
+async fn sync_job_fails_to_start() {
+    let server = MockRcServer::start().await;
+    server.fail_next("/sync/sync", 500, "directory not found");
+
+    let error = gdrive::start_sync_job(&server.credentials().client(), &copy_body(), "/sync/sync")
+        .await
+        .unwrap_err();
+
+    assert!(error.contains("directory not found"), "{}", error);
+    assert!(server.requests_to("/job/status").is_empty());
+}
+
+#[tokio::test]
+async fn slow_job_is_polled_until_finished() {
+    let server = MockRcServer::start().await;
+    server.script_jobs(JobScript {
+        polls: 2,
+        ..Default::default()
+    });
+
+    let client = server.credentials().client();
+    let jobid = gdrive::submit_sync_job(&client, &copy_body(), "/sync/copy")
+        .await
+        .unwrap();
+    gdrive::poll_job_completion(&client, jobid).await.unwrap();
+
+    assert_eq!(server.requests_to("/job/status").len(), 3);
+}
+
+#[tokio::test]
+async fn failed_job_reports_its_error() {
+    let server = MockRcServer::start().await;
+    server.script_jobs(JobScript {
+        error: Some("quota exceeded".to_string()),
+        ..Default::default()
+    });
+
+    let client = server.credentials().client();
+    let jobid = gdrive::submit_sync_job(&client, &copy_body(), "/sync/copy")
+        .await
+        .unwrap();
+
+    match gdrive::poll_job_completion(&client, jobid).await {
+        Err(SyncJobError::Failed(e)) => assert!(e.contains("quota exceeded"), "{}", e),
+        other => panic!("expected a failed job, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn missing_job_counts_as_cancelled() {
+    let server = MockRcServer::start().await;
+
+    let client = server.credentials().client();
+    let jobid = gdrive::submit_sync_job(&client, &copy_body(), "/sync/copy")
+        .await
+        .unwrap();
+    server.forget_jobs();
+
+    assert!(matches!(
+        gdrive::poll_job_completion(&client, jobid).await,
+        Err(SyncJobError::Cancelled)
+    ));
+}
+
+#[tokio::test]
+async fn unreachable_server_counts_as_stopped() {
+    // Take a free port and release it so nothing is listening there
+    let port = std::net::TcpListener::bind("127.0.0.1:0")
+        .unwrap()
+        .local_addr()
+        .unwrap()
+        .port();
+    let credentials = RcCredentials {
+        port,
+        user: "user".to_string(),
+        pass: "pass".to_string(),
+    };
+
+    assert!(matches!(
+        gdrive::poll_job_completion(&credentials.client(), 1).await,
+        Err(SyncJobError::ServerStopped)
+    ));
+}
+
+#[tokio::test]
+async fn queued_download_runs_without_the_app() {
+    let data_dir = temp_data_dir("mock-run-download");
+    let destination = data_dir.join("archive");
+    std::fs::create_dir_all(&destination).unwrap();
+
+    let server = MockRcServer::start().await;
+    server.script_jobs(JobScript {
+        stats: json!({ "bytes": 4096, "transfers": 3, "checks": 1, "errors": 0, "elapsedTime": 2.0 }),
+        ..Default::default()
+    });
+
+    let host = TestHost::new(data_dir, server.credentials());
+    let report = gdrive::run_download(&host, sync_config(&destination.to_string_lossy()))
+        .await
+        .unwrap();
+
+    assert_eq!(report.stats.transfers, 3);
+    assert_eq!(server.requests_to("/sync/sync").len(), 1);
+    assert_eq!(
+        host.job_manager().list()[0].status,
+        gdrive::JobStatus::Completed
+    );
+}
+
+#[tokio::test]
+async fn report_leaves_out_changes_logged_by_other_jobs() {
+    let data_dir = temp_data_dir("mock-shared-log");
+    let destination = data_dir.join("archive");
+    std::fs::create_dir_all(&destination).unwrap();
+
+    let server = MockRcServer::start().await;
+    server.log_syncs_to(
+        data_dir.clone(),
+        vec![json!({ "level": "info", "msg": "Copied (new)", "object": "new.mp3" })],
+    );
+
+    // Another job writing to the same log while the download runs
+    let _other = LogSpan::open(&data_dir).await;
+
+    let host = TestHost::new(data_dir, server.credentials());
+    let report = gdrive::run_download(&host, sync_config(&destination.to_string_lossy()))
+        .await
+        .unwrap();
+
+    assert!(report.changes.is_none());
+}
+
+#[tokio::test]
+async fn created_remote_is_listed() {
+    let server = MockRcServer::start().await;
+    server.add_remote("photos", "s3");
+
+    let params = json!({ "token": { "access_token": "secret" } });
+    server
+        .credentials()
+        .client()
+        .config_create(
+            Some(true),
+            None,
+            "gdrive_unofficial_neuro_kar",
+            None,
+            &params.to_string(),
+            "drive",
+        )
+        .await
+        .unwrap();
+
+    let remotes = gdrive::list_drive_remotes(&server.credentials())
+        .await
+        .unwrap();
+    assert_eq!(remotes, vec!["gdrive_unofficial_neuro_kar"]);
+}
+
+#[tokio::test]
+async fn lists_files_of_a_folder() {
+    let server = MockRcServer::start().await;
+    server.set_files(vec![
+        json!({ "Path": "Song.mp3", "Name": "Song.mp3", "IsDir": false, "Size": 42 }),
+        json!({ "Path": "2024", "Name": "2024", "IsDir": true, "Size": -1 }),
+    ]);
+
+    let files = gdrive::list_files(&server.credentials(), "abc123", "gdrive")
+        .await
+        .unwrap();
+
+    assert_eq!(files.len(), 2);
+    assert!(files.iter().any(|f| f.path == "2024" && f.is_dir));
+}
+
+#[tokio::test]
+async fn listing_reports_rclone_errors() {
+    let server = MockRcServer::start().await;
+    server.fail_next("/operations/list", 500, "directory not found");
+
+    assert!(
+        gdrive::list_files(&server.credentials(), "abc123", "gdrive")
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn dry_run_reports_planned_deletes() {
+    let data_dir = temp_data_dir("mock-dry-run");
+    let destination = data_dir.join("archive");
+    std::fs::create_dir_all(&destination).unwrap();
+
+    let server = MockRcServer::start().await;
+    server.script_jobs(JobScript {
+        polls: 1,
+        stats: json!({ "checks": 4, "transfers": 0, "deletes": 1, "errors": 0 }),
+        ..Default::default()
+    });
+    server.log_syncs_to(
+        data_dir.clone(),
+        vec![json!({ "level": "notice", "msg": "Skipped delete as --dry-run is set (size 2Ki)", "object": "old.mp3" })],
+    );
+
+    let host = ExternalServer {
+        data_dir: data_dir.clone(),
+        credentials: server.credentials(),
+    };
+    let result = gdrive::dry_run(&host, sync_config(&destination.to_string_lossy()))
+        .await
+        .unwrap();
+
+    assert!(result.would_delete);
+    assert_eq!(result.deleted_files, vec!["old.mp3"]);
+    assert!(server.requests_to("/sync/copy").is_empty());
+}
+
+#[tokio::test]
+async fn dry_run_fails_when_the_job_fails() {
+    let data_dir = temp_data_dir("mock-dry-run-failed");
+    let destination = data_dir.join("archive");
+    std::fs::create_dir_all(&destination).unwrap();
+
+    let server = MockRcServer::start().await;
+    server.script_jobs(JobScript {
+        error: Some("couldn't list directory".to_string()),
+        ..Default::default()
+    });
+
+    let host = ExternalServer {
+        data_dir: data_dir.clone(),
+        credentials: server.credentials(),
+    };
+    let error = gdrive::dry_run(&host, sync_config(&destination.to_string_lossy()))
+        .await
+        .unwrap_err();
+
+    assert!(error.contains("couldn't list directory"), "{}", error);
+
+    // Failed dry runs are recorded too
+    assert!(data_dir.join("history.jsonl").exists());
+}
@@ -0,0 +1,137 @@
+mod common;
+
+use common::{FakeRcServer, append_log, finished_job, sync_config, temp_data_dir};
+use serde_json::json;
+use unofficial_neuro_kar_manager_lib::api::gdrive;
+use unofficial_neuro_kar_manager_lib::api::rclone::LogManager;
+use unofficial_neuro_kar_manager_lib::service::ExternalServer;
+
+#[tokio::test]
+async fn lists_only_drive_remotes() {
+    let server = FakeRcServer::start(|path, _| match path {
+        "/config/dump" => (
+            200,
+            json!({
+                "zeta": { "type": "drive" },
+                "photos": { "type": "s3" },
+                "alpha": { "type": "drive", "scope": "drive.readonly" }
+            }),
+        ),
+        _ => (404, json!({ "error": "not found" })),
+    })
+    .await;
+
+    let remotes = gdrive::list_drive_remotes(&server.credentials())
+        .await
+        .unwrap();
+
+    assert_eq!(remotes, vec!["alpha", "zeta"]);
+}
+
+#[tokio::test]
+async fn lists_files_with_missing_parent_folders() {
+    let server = FakeRcServer::start(|path, _| match path {
+        "/operations/list" => (
+            200,
+            json!({
+                "list": [
+                    { "Path": "2024/Song.mp3", "Name": "Song.mp3", "IsDir": false, "Size": 42, "MimeType": "audio/mpeg" },
+                    { "Path": "cover.png", "Name": "cover.png", "IsDir": false, "Size": 7 }
+                ]
+            }),
+        ),
+        _ => (404, json!({ "error": "not found" })),
+    })
+    .await;
+
+    let files = gdrive::list_files(&server.credentials(), "abc123", "gdrive")
+        .await
+        .unwrap();
+
+    let paths: Vec<_> = files.iter().map(|f| f.path.as_str()).collect();
+    assert_eq!(paths, vec!["2024", "2024/Song.mp3", "cover.png"]);
+    assert!(files[0].is_dir);
+
+    let request = &server.requests_to("/operations/list")[0];
+    assert_eq!(request.body["fs"], "gdrive,root_folder_id=abc123:");
+}
+
+#[tokio::test]
+async fn reports_server_errors() {
+    let server =
+        FakeRcServer::start(|_, _| (500, json!({ "error": "config file not found" }))).await;
+
+    assert!(
+        gdrive::list_drive_remotes(&server.credentials())
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn dry_run_builds_plan_from_log() {
+    let data_dir = temp_data_dir("dry-run");
+    let destination = data_dir.join("archive");
+    std::fs::create_dir_all(&destination).unwrap();
+
+    let log_dir = data_dir.clone();
+    let server = FakeRcServer::start(move |path, _| match path {
+        "/sync/sync" => {
+            // rclone logs what it skipped while running the dry run
+            append_log(
+                &log_dir,
+                &[
+                    json!({ "level": "notice", "msg": "Skipped copy as --dry-run is set (size 1Ki)", "object": "new.mp3" }),
+                    json!({ "level": "notice", "msg": "Skipped delete as --dry-run is set (size 2Ki)", "object": "old.mp3" }),
+                ],
+            );
+            (200, json!({ "jobid": 1 }))
+        }
+        "/job/status" => (200, finished_job()),
+        "/core/stats" => (200, json!({ "checks": 3, "transfers": 1, "deletes": 1, "errors": 0, "bytes": 0, "elapsedTime": 0.5 })),
+        _ => (404, json!({ "error": "not found" })),
+    })
+    .await;
+
+    let host = ExternalServer {
+        data_dir: data_dir.clone(),
+        credentials: server.credentials(),
+    };
+    let result = gdrive::dry_run(&host, sync_config(&destination.to_string_lossy()))
+        .await
+        .unwrap();
+
+    assert!(result.would_delete);
+    assert_eq!(result.deleted_files, vec!["old.mp3"]);
+    assert_eq!(result.plan.new_files.len(), 1);
+
+    let request = &server.requests_to("/sync/sync")[0];
+    assert_eq!(request.body["_config"]["DryRun"], true);
+
+    // Recorded in the history kept in the host's data directory
+    assert!(data_dir.join("history.jsonl").exists());
+}
+
+#[tokio::test]
+async fn reads_log_events_after_offset() {
+    let data_dir = temp_data_dir("log-offset");
+    append_log(
+        &data_dir,
+        &[json!({ "level": "info", "msg": "Copied (new)", "object": "before.mp3" })],
+    );
+
+    let offset = LogManager::get_current_offset(&data_dir).await;
+    append_log(
+        &data_dir,
+        &[
+            json!({ "level": "info", "msg": "Copied (new)", "object": "after.mp3" }),
+            json!({ "level": "error", "msg": "Failed to copy: quota exceeded", "object": "broken.mp3" }),
+        ],
+    );
+
+    let changes = LogManager::parse_file_changes(&data_dir, offset)
+        .await
+        .unwrap();
+    assert_eq!(changes.added, vec!["after.mp3"]);
+    assert_eq!(changes.errors[0].path, "broken.mp3");
+}
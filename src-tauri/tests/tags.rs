@@ -0,0 +1,232 @@
+mod common;
+
+use common::temp_data_dir;
+use std::path::PathBuf;
+use unofficial_neuro_kar_manager_lib::api::local::{AudioTags, read_tags};
+
+/// MPEG-1 Layer III, 128 kbit/s, 44.1 kHz, stereo
+const MPEG_FRAME_HEADER: [u8; 4] = [0xFF, 0xFB, 0x90, 0x00];
+const MPEG_FRAME_LEN: usize = 417;
+const MPEG_FRAMES: usize = 100;
+
+fn write_fixture(name: &str, data: &[u8]) -> PathBuf {
+    let path = temp_data_dir("tags").join(name);
+    std::fs::write(&path, data).unwrap();
+    path
+}
+
+fn syncsafe(n: u32) -> [u8; 4] {
+    [
+        (n >> 21) as u8 & 0x7F,
+        (n >> 14) as u8 & 0x7F,
+        (n >> 7) as u8 & 0x7F,
+        n as u8 & 0x7F,
+    ]
+}
+
+fn mp3_fixture() -> Vec<u8> {
+    let mut frames = Vec::new();
+    for (id, text) in [
+        (b"TIT2", "Song"),
+        (b"TPE1", "Neuro"),
+        (b"TALB", "Karaoke"),
+        (b"TYER", "2023"),
+    ] {
+        frames.extend_from_slice(id);
+        frames.extend_from_slice(&(text.len() as u32 + 1).to_be_bytes());
+        frames.extend_from_slice(&[0, 0, 0]);
+        frames.extend_from_slice(text.as_bytes());
+    }
+
+    let mut data = b"ID3\x03\x00\x00".to_vec();
+    data.extend_from_slice(&syncsafe(frames.len() as u32));
+    data.extend_from_slice(&frames);
+    for _ in 0..MPEG_FRAMES {
+        let mut frame = vec![0; MPEG_FRAME_LEN];
+        frame[..4].copy_from_slice(&MPEG_FRAME_HEADER);
+        data.extend_from_slice(&frame);
+    }
+    data
+}
+
+fn vorbis_comments(comments: &[&str]) -> Vec<u8> {
+    let vendor = b"test";
+    let mut data = (vendor.len() as u32).to_le_bytes().to_vec();
+    data.extend_from_slice(vendor);
+    data.extend_from_slice(&(comments.len() as u32).to_le_bytes());
+    for comment in comments {
+        data.extend_from_slice(&(comment.len() as u32).to_le_bytes());
+        data.extend_from_slice(comment.as_bytes());
+    }
+    data
+}
+
+fn flac_fixture() -> Vec<u8> {
+    let sample_rate: u32 = 44100;
+    let samples: u64 = 441_000;
+    let mut info = vec![0; 34];
+    info[10] = (sample_rate >> 12) as u8;
+    info[11] = (sample_rate >> 4) as u8;
+    // Sample rate, 2 channels, 16 bits per sample
+    info[12] = ((sample_rate & 0x0F) << 4) as u8 | (1 << 1);
+    info[13] = (15 << 4) | (samples >> 32) as u8;
+    info[14..18].copy_from_slice(&(samples as u32).to_be_bytes());
+
+    let comments = vorbis_comments(&["TITLE=Song", "ARTIST=Neuro", "ARTIST=Evil", "DATE=2023"]);
+
+    let mut data = b"fLaC".to_vec();
+    data.extend_from_slice(&[0, 0, 0, 34]);
+    data.extend_from_slice(&info);
+    data.push(0x80 | 4);
+    data.extend_from_slice(&(comments.len() as u32).to_be_bytes()[1..]);
+    data.extend_from_slice(&comments);
+    data.extend_from_slice(&[0; 1000]);
+    data
+}
+
+fn ogg_page(granule: i64, packet: &[u8]) -> Vec<u8> {
+    let mut segments = vec![255; packet.len() / 255];
+    segments.push((packet.len() % 255) as u8);
+
+    let mut page = b"OggS\x00\x00".to_vec();
+    page.extend_from_slice(&granule.to_le_bytes());
+    // Serial, sequence number and an unchecked CRC
+    page.extend_from_slice(&7u32.to_le_bytes());
+    page.extend_from_slice(&[0; 8]);
+    page.push(segments.len() as u8);
+    page.extend_from_slice(&segments);
+    page.extend_from_slice(packet);
+    page
+}
+
+fn ogg_vorbis_fixture() -> Vec<u8> {
+    let mut ident = b"\x01vorbis".to_vec();
+    ident.extend_from_slice(&0u32.to_le_bytes());
+    ident.push(2);
+    ident.extend_from_slice(&44100u32.to_le_bytes());
+    ident.extend_from_slice(&0i32.to_le_bytes());
+    ident.extend_from_slice(&160_000i32.to_le_bytes());
+    ident.extend_from_slice(&0i32.to_le_bytes());
+    ident.extend_from_slice(&[0xB8, 0x01]);
+
+    let mut comments = b"\x03vorbis".to_vec();
+    comments.extend_from_slice(&vorbis_comments(&["TITLE=Song", "ALBUM=Karaoke"]));
+    comments.push(1);
+
+    let mut data = ogg_page(0, &ident);
+    data.extend_from_slice(&ogg_page(0, &comments));
+    data.extend_from_slice(&ogg_page(44100 * 5, &[0; 2000]));
+    data
+}
+
+fn atom(kind: &[u8; 4], content: &[u8]) -> Vec<u8> {
+    let mut data = (content.len() as u32 + 8).to_be_bytes().to_vec();
+    data.extend_from_slice(kind);
+    data.extend_from_slice(content);
+    data
+}
+
+fn mp4_fixture() -> Vec<u8> {
+    // Version 0: version and flags, creation and modification time, timescale, duration
+    let mut mvhd = vec![0; 12];
+    mvhd.extend_from_slice(&1000u32.to_be_bytes());
+    mvhd.extend_from_slice(&90_000u32.to_be_bytes());
+    mvhd.extend_from_slice(&[0; 80]);
+
+    let item = |kind: &[u8; 4], text: &str| {
+        let mut data = 1u32.to_be_bytes().to_vec();
+        data.extend_from_slice(&[0; 4]);
+        data.extend_from_slice(text.as_bytes());
+        atom(kind, &atom(b"data", &data))
+    };
+    let ilst = [
+        item(b"\xA9nam", "Song"),
+        item(b"\xA9ART", "Neuro"),
+        item(b"\xA9day", "2023-05-01"),
+    ]
+    .concat();
+
+    let mut meta = vec![0; 4];
+    meta.extend_from_slice(&atom(b"hdlr", &[0; 25]));
+    meta.extend_from_slice(&atom(b"ilst", &ilst));
+    let moov = [atom(b"mvhd", &mvhd), atom(b"udta", &atom(b"meta", &meta))].concat();
+
+    [
+        atom(b"ftyp", b"M4A \x00\x00\x00\x00"),
+        atom(b"moov", &moov),
+        atom(b"mdat", &[0; 4000]),
+    ]
+    .concat()
+}
+
+#[test]
+fn reads_mp3_id3v2_and_stream_info() {
+    let tags = read_tags(&write_fixture("song.mp3", &mp3_fixture())).unwrap();
+
+    assert_eq!(tags.title.as_deref(), Some("Song"));
+    assert_eq!(tags.artist.as_deref(), Some("Neuro"));
+    assert_eq!(tags.album.as_deref(), Some("Karaoke"));
+    assert_eq!(tags.date.as_deref(), Some("2023"));
+    assert_eq!(tags.bitrate, Some(128));
+    let expected = (MPEG_FRAMES * MPEG_FRAME_LEN) as f64 * 8.0 / 128_000.0;
+    assert!((tags.duration.unwrap() - expected).abs() < 0.01);
+}
+
+#[test]
+fn reads_flac_vorbis_comments_and_stream_info() {
+    let tags = read_tags(&write_fixture("song.flac", &flac_fixture())).unwrap();
+
+    assert_eq!(tags.title.as_deref(), Some("Song"));
+    assert_eq!(tags.artist.as_deref(), Some("Neuro; Evil"));
+    assert_eq!(tags.date.as_deref(), Some("2023"));
+    assert_eq!(tags.duration, Some(10.0));
+}
+
+#[test]
+fn reads_ogg_vorbis_comments_and_length() {
+    let tags = read_tags(&write_fixture("song.ogg", &ogg_vorbis_fixture())).unwrap();
+
+    assert_eq!(tags.title.as_deref(), Some("Song"));
+    assert_eq!(tags.album.as_deref(), Some("Karaoke"));
+    assert_eq!(tags.bitrate, Some(160));
+    assert_eq!(tags.duration, Some(5.0));
+}
+
+#[test]
+fn reads_mp4_itunes_atoms() {
+    let tags = read_tags(&write_fixture("song.m4a", &mp4_fixture())).unwrap();
+
+    assert_eq!(tags.title.as_deref(), Some("Song"));
+    assert_eq!(tags.artist.as_deref(), Some("Neuro"));
+    assert_eq!(tags.date.as_deref(), Some("2023-05-01"));
+    assert_eq!(tags.duration, Some(90.0));
+}
+
+#[test]
+fn truncated_files_fail_without_panicking() {
+    for (name, data) in [
+        ("cut.mp3", mp3_fixture()),
+        ("cut.flac", flac_fixture()),
+        ("cut.ogg", ogg_vorbis_fixture()),
+        ("cut.m4a", mp4_fixture()),
+    ] {
+        let path = temp_data_dir("tags-truncated").join(name);
+        // Every length through the headers, then a sample of the rest
+        let lengths = (0..data.len().min(400)).chain((400..data.len()).step_by(97));
+        for len in lengths {
+            std::fs::write(&path, &data[..len]).unwrap();
+            let _: Result<AudioTags, String> = read_tags(&path);
+        }
+    }
+}
+
+#[test]
+fn mp4_with_oversized_atom_is_rejected() {
+    // A 64-bit atom size reaching past the end of the address space
+    let mut data = atom(b"ftyp", b"M4A ");
+    data.extend_from_slice(&1u32.to_be_bytes());
+    data.extend_from_slice(b"moov");
+    data.extend_from_slice(&u64::MAX.to_be_bytes());
+
+    assert!(read_tags(&write_fixture("huge.m4a", &data)).is_err());
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use common::mock::{JobScript, MockRcServer};
+use common::{sync_config, temp_data_dir};
+use serde_json::json;
+use unofficial_neuro_kar_manager_lib::api::gdrive;
+use unofficial_neuro_kar_manager_lib::service::ExternalServer;
+
+#[tokio::test]
+async fn verify_reports_check_results() {
+    let server = MockRcServer::start().await;
+    server.script_jobs(JobScript {
+        output: Some(json!({
+            "success": false,
+            "status": "3 differences found",
+            "hashType": "md5",
+            "combined": [
+                "= good.mp3",
+                "* corrupt.mp3",
+                "- missing.mp3",
+                "+ extra.mp3",
+                "= also good.mp3"
+            ]
+        })),
+        ..Default::default()
+    });
+
+    let host = ExternalServer {
+        data_dir: temp_data_dir("mock-verify"),
+        credentials: server.credentials(),
+    };
+    let report = gdrive::verify(&host, &sync_config("/tmp/archive"), |_| {})
+        .await
+        .unwrap();
+
+    assert!(!report.success);
+    assert_eq!(report.hash_type.as_deref(), Some("md5"));
+    assert_eq!(report.matched, 2);
+    assert_eq!(report.mismatched, vec!["corrupt.mp3"]);
+    assert_eq!(report.missing, vec!["missing.mp3"]);
+    assert_eq!(report.extra, vec!["extra.mp3"]);
+
+    let request = &server.requests_to("/operations/check")[0];
+    assert_eq!(request.body["download"], false);
+    assert_eq!(request.body["dstFs"], "/tmp/archive");
+}
+
+#[tokio::test]
+async fn redownload_matches_names_with_glob_characters() {
+    let server = MockRcServer::start().await;
+    server.script_jobs(JobScript {
+        output: Some(json!({
+            "success": false,
+            "hashType": "md5",
+            "combined": ["* [Neuro] Song {live}.mp3", "= good.mp3"]
+        })),
+        ..Default::default()
+    });
+
+    let host = ExternalServer {
+        data_dir: temp_data_dir("mock-verify-glob"),
+        credentials: server.credentials(),
+    };
+    let config = sync_config("/tmp/archive");
+    let report = gdrive::verify(&host, &config, |_| {}).await.unwrap();
+    assert_eq!(report.mismatched, vec!["[Neuro] Song {live}.mp3"]);
+
+    // Checking just the corrupted files builds the same filter as downloading them
+    let redownload = gdrive::redownload_config(&config, &report.mismatched);
+    gdrive::verify(&host, &redownload, |_| {}).await.unwrap();
+
+    let request = &server.requests_to("/operations/check")[1];
+    assert_eq!(
+        request.body["_filter"]["IncludeRule"][0],
+        "/\\[Neuro\\] Song \\{live\\}.mp3"
+    );
+}
@@ -1,6 +1,7 @@
 import { t } from "@lingui/core/macro";
 import { Plural, Trans } from "@lingui/react/macro";
 import { invoke } from "@tauri-apps/api/core";
+import { listen } from "@tauri-apps/api/event";
 import {
   ArrowLeft,
   Check,
@@ -12,7 +13,7 @@ import {
   Search,
   X,
 } from "lucide-react";
-import { useCallback, useEffect, useMemo, useState } from "react";
+import { useCallback, useEffect, useMemo, useRef, useState } from "react";
 import { Button } from "./ui/button";
 import { Card } from "./ui/card";
 import { Checkbox } from "./ui/checkbox";
@@ -27,6 +28,26 @@ interface GdriveFile {
   mimeType: string;
 }
 
+interface GdriveDirEntry {
+  path: string;
+  name: string;
+  isDir: boolean;
+  size: number | null;
+  mimeType: string;
+  childCount: number | null;
+  fileCount: number | null;
+}
+
+interface ListingUpdate {
+  source: string;
+  remoteConfig: string;
+  rootId: string;
+  added: GdriveFile[];
+  changed: GdriveFile[];
+  removed: string[];
+  files: GdriveFile[];
+}
+
 interface FileBrowserModalProps {
   isOpen: boolean;
   onClose: () => void;
@@ -37,6 +58,15 @@ interface FileBrowserModalProps {
   destination?: string;
 }
 
+// Paths of everything inside a folder
+const descendantsOf = (files: GdriveFile[], path: string) =>
+  files
+    .filter((file) => file.path.startsWith(`${path}/`))
+    .map((file) => file.path);
+
+const formatSize = (size: number | null) =>
+  `${((size ?? 0) / 1024 / 1024).toFixed(2)} MB`;
+
 export default function FileBrowserModal({
   isOpen,
   onClose,
@@ -49,6 +79,10 @@ export default function FileBrowserModal({
   const [currentPath, setCurrentPath] = useState("");
   const [history, setHistory] = useState<string[]>([]);
   const [allItems, setAllItems] = useState<GdriveFile[]>([]);
+  const [allLoaded, setAllLoaded] = useState(false);
+  const allFilesRequest = useRef<Promise<GdriveFile[]> | null>(null);
+  // Folders listed so far, by path
+  const [dirs, setDirs] = useState<Map<string, GdriveDirEntry[]>>(new Map());
   const [loading, setLoading] = useState(false);
   const [scanning, setScanning] = useState(false);
   const [error, setError] = useState<string | null>(null);
@@ -60,21 +94,58 @@ export default function FileBrowserModal({
   const [search, setSearch] = useState("");
   const [debouncedSearch, setDebouncedSearch] = useState("");
 
-  const loadAllFiles = useCallback(async () => {
-    setLoading(true);
-    setError(null);
-    try {
-      const result = await invoke<GdriveFile[]>("list_gdrive_files", {
+  const loadDir = useCallback(
+    async (path: string) => {
+      setLoading(true);
+      setError(null);
+      try {
+        const result = await invoke<GdriveDirEntry[]>("list_gdrive_dir", {
+          source,
+          remoteConfig,
+          path,
+        });
+        setDirs((prev) => new Map(prev).set(path, result));
+      } catch (err) {
+        console.error(err);
+        setError(`Failed to list files: ${err}`);
+      } finally {
+        setLoading(false);
+      }
+    },
+    [source, remoteConfig],
+  );
+
+  // The whole listing is only needed for searching and selecting folders,
+  // so it's fetched the first time one of them happens
+  const loadAllFiles = useCallback(() => {
+    if (!allFilesRequest.current) {
+      allFilesRequest.current = invoke<GdriveFile[]>("list_gdrive_files", {
         source,
         remoteConfig,
-      });
-      setAllItems(result);
-    } catch (err) {
-      console.error(err);
-      setError(`Failed to list files: ${err}`);
-    } finally {
-      setLoading(false);
+      }).then(
+        (result) => {
+          setAllItems(result);
+          setAllLoaded(true);
+          // Folders listed before it was cached are missing their sizes
+          setDirs(
+            (prev) =>
+              new Map(
+                [...prev].filter(([, entries]) =>
+                  entries.every((e) => !e.isDir || e.childCount !== null),
+                ),
+              ),
+          );
+          return result;
+        },
+        (err) => {
+          allFilesRequest.current = null;
+          console.error("Failed to list all files:", err);
+          setError(`Failed to list files: ${err}`);
+          throw err;
+        },
+      );
     }
+    return allFilesRequest.current;
   }, [source, remoteConfig]);
 
   useEffect(() => {
@@ -83,9 +154,48 @@ export default function FileBrowserModal({
       setHistory([]);
       setSearch("");
       setDebouncedSearch("");
-      loadAllFiles();
+      setDirs(new Map());
+      setAllItems([]);
+      setAllLoaded(false);
+      allFilesRequest.current = null;
+    }
+  }, [isOpen]);
+
+  useEffect(() => {
+    if (isOpen && debouncedSearch) {
+      loadAllFiles().catch(() => {});
+    }
+  }, [isOpen, debouncedSearch, loadAllFiles]);
+
+  useEffect(() => {
+    if (isOpen && !dirs.has(currentPath)) {
+      loadDir(currentPath);
     }
-  }, [isOpen, loadAllFiles]);
+  }, [isOpen, currentPath, dirs, loadDir]);
+
+  // The cached listing is shown first, take the refreshed one once it arrives
+  useEffect(() => {
+    if (!isOpen) return;
+    const unlistenPromise = listen<ListingUpdate>(
+      "gdrive-listing-updated",
+      (event) => {
+        const { payload } = event;
+        if (
+          payload.source === source &&
+          payload.remoteConfig === remoteConfig
+        ) {
+          allFilesRequest.current = Promise.resolve(payload.files);
+          setAllItems(payload.files);
+          setAllLoaded(true);
+          setDirs(new Map());
+        }
+      },
+    );
+
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, [isOpen, source, remoteConfig]);
 
   // Debounce search input (prevent excessive filtering and lag)
   useEffect(() => {
@@ -101,63 +211,41 @@ export default function FileBrowserModal({
     setCurrentPath(prev || "");
   };
 
-  // Memoize descendant map for performance
-  const descendantsMap = useMemo(() => {
-    const map = new Map<string, string[]>();
-    allItems.forEach((item) => {
-      if (item.isDir) {
-        const descendants = allItems
-          .filter(
-            (i) => i.path !== item.path && i.path.startsWith(`${item.path}/`),
-          )
-          .map((i) => i.path);
-        map.set(item.path, descendants);
+  const toggleSelection = async (path: string, isDir: boolean) => {
+    let descendants: string[] = [];
+    if (isDir) {
+      try {
+        descendants = descendantsOf(await loadAllFiles(), path);
+      } catch {
+        return;
       }
-    });
-    return map;
-  }, [allItems]);
+    }
 
-  const toggleSelection = (path: string, isDir: boolean) => {
     setSelection((prev) => {
       const next = new Set(prev);
-      const isSelected = next.has(path);
-
-      if (isDir) {
-        const descendants = descendantsMap.get(path) || [];
-        if (isSelected) {
-          next.delete(path);
-          descendants.forEach((d) => {
-            next.delete(d);
-          });
-        } else {
-          next.add(path);
-          descendants.forEach((d) => {
-            next.add(d);
-          });
-        }
+      if (next.has(path)) {
+        next.delete(path);
+        descendants.forEach((d) => {
+          next.delete(d);
+        });
       } else {
-        isSelected ? next.delete(path) : next.add(path);
+        next.add(path);
+        descendants.forEach((d) => {
+          next.add(d);
+        });
       }
-
       return next;
     });
   };
 
-  const displayItems = useMemo(() => {
-    if (!allItems.length) return [];
-
+  const displayItems = useMemo((): (GdriveFile | GdriveDirEntry)[] => {
     if (debouncedSearch) {
       const searchLower = debouncedSearch.toLowerCase();
       return allItems.filter((i) => i.name.toLowerCase().includes(searchLower));
     }
 
-    const prefix = currentPath ? `${currentPath}/` : "";
-    return allItems.filter((item) => {
-      if (currentPath && !item.path.startsWith(prefix)) return false;
-      const relative = currentPath ? item.path.slice(prefix.length) : item.path;
-      return !relative.includes("/");
-    });
-  }, [allItems, debouncedSearch, currentPath]);
+    return dirs.get(currentPath) ?? [];
+  }, [allItems, dirs, debouncedSearch, currentPath]);
 
   const sortedDisplayItems = useMemo(() => {
     return [...displayItems].sort((a, b) => {
@@ -172,13 +260,21 @@ export default function FileBrowserModal({
     sortedDisplayItems.length > 0 &&
     sortedDisplayItems.every((i) => selection.has(i.path));
 
-  const handleSelectAll = (checked: boolean) => {
+  const handleSelectAll = async (checked: boolean) => {
+    const items = sortedDisplayItems;
+    let files: GdriveFile[] = [];
+    if (items.some((item) => item.isDir)) {
+      try {
+        files = await loadAllFiles();
+      } catch {
+        return;
+      }
+    }
+
     setSelection((prev) => {
       const next = new Set(prev);
-      sortedDisplayItems.forEach((item) => {
-        const descendants = item.isDir
-          ? descendantsMap.get(item.path) || []
-          : [];
+      items.forEach((item) => {
+        const descendants = item.isDir ? descendantsOf(files, item.path) : [];
         if (checked) {
           next.add(item.path);
           descendants.forEach((d) => {
@@ -199,15 +295,18 @@ export default function FileBrowserModal({
     if (!destination) return;
     setScanning(true);
     try {
-      const existingFiles = await invoke<string[]>("scan_local_files", {
-        path: destination,
-      });
+      const [files, existingFiles] = await Promise.all([
+        loadAllFiles(),
+        invoke<string[]>("scan_local_files", {
+          path: destination,
+        }),
+      ]);
 
       const existingSet = new Set(existingFiles);
 
       setSelection((prev) => {
         const next = new Set(prev);
-        allItems.forEach((item) => {
+        files.forEach((item) => {
           if (!item.isDir && existingSet.has(item.path)) {
             next.add(item.path);
           }
@@ -279,7 +378,7 @@ export default function FileBrowserModal({
 
         {/* Content */}
         <ScrollArea className="flex-1 p-4">
-          {loading ? (
+          {loading || (debouncedSearch && !allLoaded) ? (
             <div className="flex flex-col items-center justify-center h-full py-20 text-muted-foreground">
               <Loader2 className="h-8 w-8 animate-spin mb-2" />
               <p>
@@ -339,9 +438,20 @@ export default function FileBrowserModal({
                         </p>
                         <p className="text-xs text-muted-foreground mt-1">
                           {item.isDir ? (
-                            <Trans>Folder</Trans>
+                            "childCount" in item && item.childCount !== null ? (
+                              <>
+                                <Plural
+                                  value={item.childCount}
+                                  one="# item"
+                                  other="# items"
+                                />
+                                {` · ${formatSize(item.size)}`}
+                              </>
+                            ) : (
+                              <Trans>Folder</Trans>
+                            )
                           ) : (
-                            `${(item.size / 1024 / 1024).toFixed(2)} MB`
+                            formatSize(item.size)
                           )}
                         </p>
                       </div>
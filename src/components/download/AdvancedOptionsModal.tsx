@@ -1,5 +1,7 @@
 import { Trans } from "@lingui/react/macro";
+import { invoke } from "@tauri-apps/api/core";
 import { Info, Settings, X } from "lucide-react";
+import { useEffect, useState } from "react";
 import { Button } from "@/components/ui/button";
 import { Card } from "@/components/ui/card";
 import { Checkbox } from "@/components/ui/checkbox";
@@ -8,6 +10,7 @@ import {
   HybridTooltipContent,
   HybridTooltipTrigger,
 } from "@/components/ui/hybrid-tooltip";
+import { Input } from "@/components/ui/input";
 import { Label } from "@/components/ui/label";
 
 interface AdvancedOptionsModalProps {
@@ -21,6 +24,8 @@ interface AdvancedOptionsModalProps {
   onDeleteExcludedChange: (checked: boolean) => void;
   trackRenames: boolean;
   onTrackRenamesChange: (checked: boolean) => void;
+  bwLimit: string;
+  onBwLimitChange: (limit: string) => void;
   disabled: boolean;
   hasFileSelection: boolean;
   syncMode: boolean;
@@ -37,10 +42,34 @@ export function AdvancedOptionsModal({
   onDeleteExcludedChange,
   trackRenames,
   onTrackRenamesChange,
+  bwLimit,
+  onBwLimitChange,
   disabled,
   hasFileSelection,
   syncMode,
 }: AdvancedOptionsModalProps) {
+  const [globalBwLimit, setGlobalBwLimit] = useState("");
+  const [bwLimitError, setBwLimitError] = useState<string | null>(null);
+
+  useEffect(() => {
+    if (!isOpen) return;
+    invoke<string | null>("get_global_bwlimit")
+      .then((limit) => setGlobalBwLimit(limit ?? ""))
+      .catch(console.error);
+  }, [isOpen]);
+
+  // Applied right away, including to running downloads
+  const saveGlobalBwLimit = async () => {
+    try {
+      await invoke("set_global_bwlimit", {
+        limit: globalBwLimit.trim() || null,
+      });
+      setBwLimitError(null);
+    } catch (err) {
+      setBwLimitError(String(err));
+    }
+  };
+
   if (!isOpen) return null;
 
   return (
@@ -178,6 +207,49 @@ export function AdvancedOptionsModal({
               </HybridTooltipContent>
             </HybridTooltip>
           </div>
+          <div className="space-y-2 pt-2">
+            <div className="flex items-center space-x-2">
+              <Label htmlFor="bwLimit" className="text-sm font-medium">
+                <Trans>Bandwidth limit for this download</Trans>
+              </Label>
+              <HybridTooltip>
+                <HybridTooltipTrigger asChild>
+                  <Info className="h-3 w-3 cursor-pointer text-muted-foreground hover:text-foreground transition-colors" />
+                </HybridTooltipTrigger>
+                <HybridTooltipContent className="w-80">
+                  <p className="text-sm">
+                    <Trans>
+                      Rate like 512k or 10M, or a timetable like "08:00,512k
+                      23:00,off" to throttle during work hours. Leave empty to
+                      use the global limit. Equivalent to --bwlimit.
+                    </Trans>
+                  </p>
+                </HybridTooltipContent>
+              </HybridTooltip>
+            </div>
+            <Input
+              id="bwLimit"
+              type="text"
+              placeholder="08:00,512k 23:00,off"
+              value={bwLimit}
+              onChange={(e) => onBwLimitChange(e.target.value)}
+              disabled={disabled}
+            />
+            <Label htmlFor="globalBwLimit" className="text-sm font-medium">
+              <Trans>Global bandwidth limit</Trans>
+            </Label>
+            <Input
+              id="globalBwLimit"
+              type="text"
+              placeholder="off"
+              value={globalBwLimit}
+              onChange={(e) => setGlobalBwLimit(e.target.value)}
+              onBlur={saveGlobalBwLimit}
+            />
+            {bwLimitError && (
+              <p className="text-sm text-destructive">{bwLimitError}</p>
+            )}
+          </div>
         </div>
 
         <div className="p-4 border-t bg-muted/30 flex justify-end">
@@ -26,6 +26,8 @@ interface DestinationSectionProps {
   onDeleteExcludedChange: (checked: boolean) => void;
   trackRenames: boolean;
   onTrackRenamesChange: (checked: boolean) => void;
+  bwLimit: string;
+  onBwLimitChange: (limit: string) => void;
   hasFileSelection: boolean;
   onSelectFolder: () => void;
   disabled: boolean;
@@ -44,6 +46,8 @@ export function DestinationSection({
   onDeleteExcludedChange,
   trackRenames,
   onTrackRenamesChange,
+  bwLimit,
+  onBwLimitChange,
   hasFileSelection,
   onSelectFolder,
   disabled,
@@ -63,6 +67,8 @@ export function DestinationSection({
         onDeleteExcludedChange={onDeleteExcludedChange}
         trackRenames={trackRenames}
         onTrackRenamesChange={onTrackRenamesChange}
+        bwLimit={bwLimit}
+        onBwLimitChange={onBwLimitChange}
         disabled={disabled}
         hasFileSelection={hasFileSelection}
         syncMode={syncMode}
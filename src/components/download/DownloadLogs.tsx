@@ -1,5 +1,8 @@
 import { t } from "@lingui/core/macro";
 import { Trans } from "@lingui/react/macro";
+import { invoke } from "@tauri-apps/api/core";
+import { save } from "@tauri-apps/plugin-dialog";
+import { Button } from "@/components/ui/button";
 import { Card, CardContent, CardHeader, CardTitle } from "@/components/ui/card";
 import { Textarea } from "@/components/ui/textarea";
 
@@ -10,6 +13,20 @@ interface DownloadLogsProps {
 }
 
 export function DownloadLogs({ log, status, loading }: DownloadLogsProps) {
+  const handleExportLogs = async () => {
+    try {
+      const destination = await save({
+        defaultPath: "rclone-logs.zip",
+        filters: [{ name: "Zip", extensions: ["zip"] }],
+      });
+      if (destination) {
+        await invoke("export_logs", { destination });
+      }
+    } catch (err) {
+      console.error("Failed to export logs:", err);
+    }
+  };
+
   return (
     <>
       {loading && (
@@ -21,10 +38,13 @@ export function DownloadLogs({ log, status, loading }: DownloadLogsProps) {
       )}
 
       <Card>
-        <CardHeader className="pb-2">
+        <CardHeader className="pb-2 flex flex-row items-center justify-between">
           <CardTitle className="text-sm font-bold">
             <Trans>Logs</Trans>
           </CardTitle>
+          <Button variant="outline" size="sm" onClick={handleExportLogs}>
+            <Trans>Export logs</Trans>
+          </Button>
         </CardHeader>
         <CardContent>
           <Textarea
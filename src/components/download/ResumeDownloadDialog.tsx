@@ -0,0 +1,65 @@
+import { Trans } from "@lingui/react/macro";
+import {
+  AlertDialog,
+  AlertDialogAction,
+  AlertDialogCancel,
+  AlertDialogContent,
+  AlertDialogDescription,
+  AlertDialogFooter,
+  AlertDialogHeader,
+  AlertDialogTitle,
+} from "@/components/ui/alert-dialog";
+import type { DownloadJob } from "@/types/download";
+
+interface ResumeDownloadDialogProps {
+  job: DownloadJob | null;
+  onResume: (job: DownloadJob) => void;
+  onDiscard: (job: DownloadJob) => void;
+}
+
+export function ResumeDownloadDialog({
+  job,
+  onResume,
+  onDiscard,
+}: ResumeDownloadDialogProps) {
+  const createdAt = job ? new Date(job.createdAt).toLocaleString() : "";
+
+  return (
+    <AlertDialog open={!!job}>
+      <AlertDialogContent>
+        <AlertDialogHeader>
+          <AlertDialogTitle>
+            <Trans>Resume Interrupted Download?</Trans>
+          </AlertDialogTitle>
+          <AlertDialogDescription className="space-y-3">
+            <p>
+              <Trans>
+                A download created at {createdAt} did not finish before the app
+                was closed.
+              </Trans>
+            </p>
+            {job && (
+              <p className="text-sm font-mono bg-muted p-2 rounded break-all">
+                {job.config.source} → {job.config.destination}
+              </p>
+            )}
+            <p>
+              <Trans>
+                Files that were already downloaded will not be downloaded
+                again.
+              </Trans>
+            </p>
+          </AlertDialogDescription>
+        </AlertDialogHeader>
+        <AlertDialogFooter>
+          <AlertDialogCancel onClick={() => job && onDiscard(job)}>
+            <Trans>Discard</Trans>
+          </AlertDialogCancel>
+          <AlertDialogAction onClick={() => job && onResume(job)}>
+            <Trans>Resume</Trans>
+          </AlertDialogAction>
+        </AlertDialogFooter>
+      </AlertDialogContent>
+    </AlertDialog>
+  );
+}
@@ -10,6 +10,7 @@ export function useDownloadForm() {
   const [createBackup, setCreateBackup] = useState(true);
   const [deleteExcluded, setDeleteExcluded] = useState(true);
   const [trackRenames, setTrackRenames] = useState(true);
+  const [bwLimit, setBwLimit] = useState("");
   const [selectedFiles, setSelectedFiles] = useState<string[] | null>(null);
 
   const isValid = (remoteConfigValid: boolean) => {
@@ -31,6 +32,8 @@ export function useDownloadForm() {
     setDeleteExcluded,
     trackRenames,
     setTrackRenames,
+    bwLimit,
+    setBwLimit,
     selectedFiles,
     setSelectedFiles,
     isValid,
@@ -1,11 +1,53 @@
 import { invoke } from "@tauri-apps/api/core";
 import { useRef, useState } from "react";
-import type { DownloadParams, DryRunResult } from "@/types/download";
+import {
+  type DownloadJob,
+  type DownloadParams,
+  type DownloadReport,
+  type DryRunResult,
+  EMPTY_SYNC_PLAN,
+} from "@/types/download";
+
+const formatBytes = (bytes: number) => {
+  const units = ["B", "KiB", "MiB", "GiB", "TiB"];
+  let value = bytes;
+  let unit = 0;
+  while (value >= 1024 && unit < units.length - 1) {
+    value /= 1024;
+    unit++;
+  }
+  return `${value.toFixed(unit === 0 ? 0 : 1)} ${units[unit]}`;
+};
+
+const formatReport = (report: DownloadReport) => {
+  const { stats, changes } = report;
+  const lines = [
+    "Download completed successfully.",
+    `Transferred ${formatBytes(stats.bytes)} in ${stats.elapsedTime.toFixed(1)}s (${formatBytes(report.averageSpeed)}/s)`,
+  ];
+  if (changes) {
+    lines.push(
+      `Added: ${changes.added.length}, Updated: ${changes.updated.length}, Deleted: ${changes.deleted.length}, Renamed: ${changes.renamed.length}, Errors: ${stats.errors}`,
+    );
+    for (const { path, error } of changes.errors) {
+      lines.push(`Error: ${path}: ${error}`);
+    }
+  } else {
+    lines.push(
+      `Transfers: ${stats.transfers}, Deletes: ${stats.deletes}, Renames: ${stats.renames}, Errors: ${stats.errors} (other downloads ran at the same time, see the log for the files)`,
+    );
+  }
+  if (report.backupDir) {
+    lines.push(`Backup: ${report.backupDir}`);
+  }
+  return lines.join("\n");
+};
 
 export function useDownloadProcess() {
   const [loading, setLoading] = useState(false);
   const [cancelling, setCancelling] = useState(false);
   const isCancelledRef = useRef(false);
+  const jobIdRef = useRef<number | null>(null);
   const [status, setStatus] = useState("");
   const [log, setLog] = useState("");
   const [dryRunResult, setDryRunResult] = useState<DryRunResult | null>(null);
@@ -58,7 +100,11 @@ export function useDownloadProcess() {
 
     try {
       appendLog("\nRequesting cancellation...");
-      await invoke("stop_rc_server");
+      if (jobIdRef.current !== null) {
+        await invoke("cancel_job", { id: jobIdRef.current });
+      } else {
+        await invoke("stop_rc_server");
+      }
     } catch (err) {
       console.error("Failed to stop rclone", err);
       appendLog(`\nFailed to stop rclone: ${err}`);
@@ -68,6 +114,24 @@ export function useDownloadProcess() {
   };
 
   const runDownload = async (params: DownloadParams) => {
+    await executeDownload(() =>
+      invoke<number>("queue_download", {
+        source: params.source,
+        destination: params.destination,
+        remoteConfig: params.remoteConfig,
+        syncMode: params.syncMode,
+        createSubfolder: params.createSubfolder,
+        selectedFiles: params.selectedFiles,
+        createBackup: params.createBackup,
+        deleteExcluded: params.deleteExcluded,
+        trackRenames: params.trackRenames,
+        bwLimit: params.bwLimit,
+        options: params.options,
+      }),
+    );
+  };
+
+  const executeDownload = async (startJob: () => Promise<number>) => {
     if (isCancelledRef.current) {
       handleTransferCancelled();
       return;
@@ -80,19 +144,12 @@ export function useDownloadProcess() {
     clearPendingState();
 
     try {
-      const output = await invoke<string>("download_gdrive", {
-        source: params.source,
-        destination: params.destination,
-        remoteConfig: params.remoteConfig,
-        syncMode: params.syncMode,
-        createSubfolder: params.createSubfolder,
-        selectedFiles: params.selectedFiles,
-        createBackup: params.createBackup,
-        deleteExcluded: params.deleteExcluded,
-        trackRenames: params.trackRenames,
+      jobIdRef.current = await startJob();
+      const report = await invoke<DownloadReport>("wait_for_job", {
+        id: jobIdRef.current,
       });
       setStatus("Download completed successfully.");
-      appendLog(`\n${output}`);
+      appendLog(`\n${formatReport(report)}`);
     } catch (error) {
       if (isCancellationError(error)) {
         handleTransferCancelled();
@@ -102,6 +159,7 @@ export function useDownloadProcess() {
         appendLog(`\nError: ${error}`);
       }
     } finally {
+      jobIdRef.current = null;
       setLoading(false);
       setCancelledState(false);
     }
@@ -117,6 +175,7 @@ export function useDownloadProcess() {
       `Remote: ${params.remoteConfig}`,
       `Backup: ${params.createBackup ? "Yes" : "No"}`,
       `Sync Mode: ${params.syncMode ? "Yes" : "No"}`,
+      `Bandwidth Limit: ${params.bwLimit ?? "Global"}`,
     ];
 
     if (params.syncMode) {
@@ -150,6 +209,8 @@ export function useDownloadProcess() {
         createSubfolder: params.createSubfolder,
         selectedFiles: params.selectedFiles,
         deleteExcluded: params.deleteExcluded,
+        createBackup: params.createBackup,
+        options: params.options,
       });
 
       // If user clicked cancel while dry run was in progress, abort here
@@ -159,6 +220,10 @@ export function useDownloadProcess() {
       }
 
       appendLog(`Dry run complete: ${result.stats}`);
+      const { plan } = result;
+      appendLog(
+        `Plan: ${plan.newFiles.length} new, ${plan.changedFiles.length} changed, ${plan.renamedFiles.length} renamed, ${plan.deletedFiles.length} deleted (${formatBytes(plan.bytesToTransfer)} to download)`,
+      );
 
       if (result.would_delete) {
         appendLog(
@@ -184,12 +249,32 @@ export function useDownloadProcess() {
         would_delete: false,
         deleted_files: [],
         stats: "Dry run check failed",
+        plan: EMPTY_SYNC_PLAN,
       });
       setPendingParams(params);
       setLoading(false);
     }
   };
 
+  const resumeDownload = async (job: DownloadJob) => {
+    setCancelledState(false);
+    setLog(
+      `Resuming interrupted download created at ${new Date(job.createdAt).toLocaleString()}\n`,
+    );
+    await executeDownload(async () => {
+      await invoke("resume_job", { id: job.id });
+      return job.id;
+    });
+  };
+
+  const discardInterruptedDownload = async (job: DownloadJob) => {
+    try {
+      await invoke("cancel_job", { id: job.id });
+    } catch (err) {
+      console.error("Failed to discard interrupted download", err);
+    }
+  };
+
   const confirmDownload = () => {
     if (pendingParams) {
       runDownload(pendingParams);
@@ -205,6 +290,8 @@ export function useDownloadProcess() {
     startDownload,
     confirmDownload,
     cancelDownload,
+    resumeDownload,
+    discardInterruptedDownload,
     appendLog,
   };
 }
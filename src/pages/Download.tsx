@@ -10,6 +10,7 @@ import { BackupWarningDialog } from "@/components/download/BackupWarningDialog";
 import { DestinationSection } from "@/components/download/DestinationSection";
 import { DownloadLogs } from "@/components/download/DownloadLogs";
 import { RemoteConfigSection } from "@/components/download/RemoteConfigSection";
+import { ResumeDownloadDialog } from "@/components/download/ResumeDownloadDialog";
 import { SourceInputSection } from "@/components/download/SourceInputSection";
 import FileBrowserModal from "@/components/FileBrowserModal";
 import { Button } from "@/components/ui/button";
@@ -18,6 +19,19 @@ import { useAppConfig } from "@/hooks/useAppConfig";
 import { useDownloadForm } from "@/hooks/useDownloadForm";
 import { useDownloadProcess } from "@/hooks/useDownloadProcess";
 import { useRemoteConfig } from "@/hooks/useRemoteConfig";
+import type { DownloadJob, LogBatch, RcCrash } from "@/types/download";
+import type { ScheduledRunResult } from "@/types/schedule";
+
+// Show a system notification, asking for permission the first time
+const notify = async (message: string) => {
+  if (!("Notification" in window)) return;
+  if (Notification.permission === "default") {
+    await Notification.requestPermission();
+  }
+  if (Notification.permission === "granted") {
+    new Notification("Unofficial Neuro KAR Manager", { body: message });
+  }
+};
 
 export default function DownloadPage() {
   const { config, loading: configLoading, saveConfig } = useAppConfig();
@@ -28,6 +42,7 @@ export default function DownloadPage() {
   const [showBrowser, setShowBrowser] = useState(false);
   const [authUrl, setAuthUrl] = useState<string | null>(null);
   const [showAuthDialog, setShowAuthDialog] = useState(false);
+  const [interruptedJobs, setInterruptedJobs] = useState<DownloadJob[]>([]);
 
   const configRef = useRef(config);
   useEffect(() => {
@@ -48,6 +63,7 @@ export default function DownloadPage() {
       form.setCreateBackup(config.createBackup);
       form.setDeleteExcluded(config.deleteExcluded);
       form.setTrackRenames(config.trackRenames);
+      form.setBwLimit(config.bwLimit);
 
       const savedFiles = config.selectedFiles?.[config.lastSource || ""];
       if (savedFiles) form.setSelectedFiles(savedFiles);
@@ -77,6 +93,7 @@ export default function DownloadPage() {
         createBackup: form.createBackup,
         deleteExcluded: form.deleteExcluded,
         trackRenames: form.trackRenames,
+        bwLimit: form.bwLimit,
         selectedFiles: newSelectedFiles,
       });
     }, 1000);
@@ -92,10 +109,20 @@ export default function DownloadPage() {
     form.createBackup,
     form.deleteExcluded,
     form.trackRenames,
+    form.bwLimit,
     form.selectedFiles,
     saveConfig,
   ]);
 
+  // Offer to resume a download interrupted by the app closing
+  useEffect(() => {
+    invoke<DownloadJob[]>("list_jobs")
+      .then((jobs) =>
+        setInterruptedJobs(jobs.filter((job) => job.status === "interrupted")),
+      )
+      .catch(console.error);
+  }, []);
+
   useEffect(() => {
     const unlistenPromise = listen<string>("gdrive-auth-url", (event) => {
       setAuthUrl(event.payload);
@@ -107,6 +134,64 @@ export default function DownloadPage() {
     };
   }, []);
 
+  // biome-ignore lint/correctness/useExhaustiveDependencies: appendLog only uses the state setter
+  useEffect(() => {
+    const unlistenPromise = listen<RcCrash>("rclone-crashed", (event) => {
+      const { code, signal, output, restarting } = event.payload;
+      download.appendLog(
+        `\nrclone exited unexpectedly (code ${code ?? "none"}, signal ${signal ?? "none"}).`,
+      );
+      for (const line of output) download.appendLog(line);
+      download.appendLog(
+        restarting
+          ? "Restarting rclone..."
+          : "rclone keeps crashing, not restarting it.",
+      );
+    });
+
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  // biome-ignore lint/correctness/useExhaustiveDependencies: appendLog only uses the state setter
+  useEffect(() => {
+    const unlistenPromise = listen<ScheduledRunResult>(
+      "scheduled-sync-finished",
+      (event) => {
+        const { name, status, error } = event.payload;
+        const message = `Scheduled sync "${name}" ${status}${
+          error ? `: ${error}` : "."
+        }`;
+        download.appendLog(`\n${message}`);
+        notify(message);
+      },
+    );
+
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, []);
+
+  // Stream rclone output into the log while a transfer is running
+  // biome-ignore lint/correctness/useExhaustiveDependencies: appendLog only uses the state setter
+  useEffect(() => {
+    if (!download.loading) return;
+
+    const unlistenPromise = listen<LogBatch>("rclone-log", (event) => {
+      const { events, dropped } = event.payload;
+      if (dropped > 0) download.appendLog(`... ${dropped} log lines skipped`);
+      for (const { level, object, message } of events) {
+        const prefix = object ? `${object}: ` : "";
+        download.appendLog(`[${level.toUpperCase()}] ${prefix}${message}`);
+      }
+    });
+
+    return () => {
+      unlistenPromise.then((unlisten) => unlisten());
+    };
+  }, [download.loading]);
+
   const handleCreateConfig = async () => {
     download.appendLog("\nStarting authorization flow...");
     try {
@@ -179,6 +264,7 @@ export default function DownloadPage() {
       createBackup: form.createBackup,
       deleteExcluded: effectiveDeleteExcluded,
       trackRenames: effectiveTrackRenames,
+      bwLimit: form.bwLimit.trim() || null,
     });
   };
 
@@ -194,6 +280,18 @@ export default function DownloadPage() {
         onConfirm={download.confirmDownload}
       />
 
+      <ResumeDownloadDialog
+        job={download.loading ? null : (interruptedJobs[0] ?? null)}
+        onResume={(job) => {
+          setInterruptedJobs((jobs) => jobs.filter((j) => j.id !== job.id));
+          download.resumeDownload(job);
+        }}
+        onDiscard={(job) => {
+          setInterruptedJobs((jobs) => jobs.filter((j) => j.id !== job.id));
+          download.discardInterruptedDownload(job);
+        }}
+      />
+
       <AuthDialog
         url={authUrl}
         open={showAuthDialog}
@@ -270,6 +368,8 @@ export default function DownloadPage() {
               onDeleteExcludedChange={form.setDeleteExcluded}
               trackRenames={form.trackRenames}
               onTrackRenamesChange={form.setTrackRenames}
+              bwLimit={form.bwLimit}
+              onBwLimitChange={form.setBwLimit}
               hasFileSelection={
                 !!form.selectedFiles && form.selectedFiles.length > 0
               }
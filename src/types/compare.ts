@@ -0,0 +1,25 @@
+export type FileComparison =
+  | "missing-locally"
+  | "extra-locally"
+  | "size-mismatch"
+  | "hash-mismatch"
+  | "identical";
+
+export interface ComparedFile {
+  path: string;
+  status: FileComparison;
+  localSize: number | null;
+  remoteSize: number | null;
+  localMd5: string | null;
+  remoteMd5: string | null;
+}
+
+export interface LocalRemoteComparison {
+  files: ComparedFile[];
+  missingLocally: number;
+  extraLocally: number;
+  sizeMismatch: number;
+  hashMismatch: number;
+  identical: number;
+  hashesChecked: boolean;
+}
@@ -7,6 +7,8 @@ export interface AppConfig {
   createBackup: boolean;
   deleteExcluded: boolean;
   trackRenames: boolean;
+  // rclone --bwlimit syntax, empty for no limit
+  bwLimit: string;
   // Map of remote -> selected files
   selectedFiles: Record<string, string[]>;
 }
@@ -20,5 +22,6 @@ export const NETWORK_DEFAULTS: AppConfig = {
   createBackup: true,
   deleteExcluded: true,
   trackRenames: true,
+  bwLimit: "",
   selectedFiles: {},
 };
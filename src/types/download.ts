@@ -1,7 +1,31 @@
+export interface PlannedFile {
+  path: string;
+  size: number | null;
+}
+
+export interface SyncPlan {
+  newFiles: PlannedFile[];
+  changedFiles: PlannedFile[];
+  renamedFiles: { from: string; to: string | null; size: number | null }[];
+  deletedFiles: PlannedFile[];
+  backedUpFiles: PlannedFile[];
+  bytesToTransfer: number;
+}
+
+export const EMPTY_SYNC_PLAN: SyncPlan = {
+  newFiles: [],
+  changedFiles: [],
+  renamedFiles: [],
+  deletedFiles: [],
+  backedUpFiles: [],
+  bytesToTransfer: 0,
+};
+
 export interface DryRunResult {
   would_delete: boolean;
   deleted_files: string[];
   stats: string;
+  plan: SyncPlan;
 }
 
 export interface DownloadParams {
@@ -14,4 +38,100 @@ export interface DownloadParams {
   createBackup: boolean;
   deleteExcluded: boolean;
   trackRenames: boolean;
+  bwLimit: string | null;
+  // Backend defaults are used when omitted
+  options?: TransferOptions;
+}
+
+export type CompareMode = "modTime" | "checksum" | "sizeOnly";
+
+export interface TransferOptions {
+  transfers: number;
+  checkers: number;
+  multiThreadStreams: number;
+  multiThreadCutoff: string;
+  retries: number;
+  lowLevelRetries: number;
+  maxDuration: string | null;
+  compare: CompareMode;
+}
+
+export interface SyncJobResult {
+  deletes: number;
+  checks: number;
+  transfers: number;
+  renames: number;
+  errors: number;
+  bytes: number;
+  elapsedTime: number;
+}
+
+export interface FileChanges {
+  added: string[];
+  updated: string[];
+  deleted: string[];
+  renamed: { from: string; to: string }[];
+  errors: { path: string; error: string }[];
+}
+
+export interface DownloadReport {
+  stats: SyncJobResult;
+  averageSpeed: number;
+  /** Null when other rclone jobs ran at the same time */
+  changes: FileChanges | null;
+  backupDir: string | null;
+}
+
+export type JobStatus =
+  | "queued"
+  | "running"
+  | "paused"
+  | "completed"
+  | "failed"
+  | "cancelled"
+  | "interrupted";
+
+export interface DownloadJob {
+  id: number;
+  config: DownloadParams;
+  status: JobStatus;
+  rcloneJobid: number | null;
+  backupPath: string | null;
+  attempts: number;
+  createdAt: string;
+  startedAt: string | null;
+  finishedAt: string | null;
+  error: string | null;
+  report: DownloadReport | null;
+  /** Why there is no report for a job that ran */
+  reportError: string | null;
+}
+
+export type LogLevel =
+  | "debug"
+  | "info"
+  | "notice"
+  | "warning"
+  | "error"
+  | "critical";
+
+export interface LogEvent {
+  level: LogLevel;
+  object: string | null;
+  message: string;
+  timestamp: string;
+}
+
+export interface LogBatch {
+  events: LogEvent[];
+  dropped: number;
+}
+
+export interface RcCrash {
+  code: number | null;
+  signal: number | null;
+  output: string[];
+  recentEvents: LogEvent[];
+  crashedAt: string;
+  restarting: boolean;
 }
@@ -0,0 +1,73 @@
+export interface LibraryEntry {
+  path: string;
+  size: number;
+  modified: number;
+  sha256: string;
+  title: string | null;
+  artist: string | null;
+  album: string | null;
+  date: string | null;
+  duration: number | null;
+  bitrate: number | null;
+  error: string | null;
+}
+
+export interface LibraryScanSummary {
+  root: string;
+  added: number;
+  updated: number;
+  removed: number;
+  unchanged: number;
+  failed: number;
+  failedDirs: number;
+}
+
+export interface LibraryScanProgress {
+  scanned: number;
+  total: number;
+}
+
+export type LibrarySort =
+  | "path"
+  | "title"
+  | "artist"
+  | "album"
+  | "date"
+  | "duration"
+  | "bitrate"
+  | "size"
+  | "modified";
+
+export interface LibraryQuery {
+  text?: string;
+  artist?: string;
+  album?: string;
+  year?: string;
+  minDuration?: number;
+  maxDuration?: number;
+  sort?: LibrarySort;
+  descending?: boolean;
+  offset?: number;
+  limit?: number;
+}
+
+export interface LibraryPage {
+  total: number;
+  entries: LibraryEntry[];
+}
+
+export interface LibraryFacet {
+  name: string;
+  count: number;
+}
+
+export interface LibrarySummary {
+  root: string | null;
+  scannedAt: string | null;
+  fileCount: number;
+  totalSize: number;
+  totalDuration: number;
+  artists: LibraryFacet[];
+  albums: LibraryFacet[];
+  years: LibraryFacet[];
+}
@@ -0,0 +1,15 @@
+import type { DownloadParams } from "./download";
+
+export interface Profile {
+  id: number;
+  name: string;
+  config: DownloadParams;
+  createdAt: string;
+  updatedAt: string;
+}
+
+export interface ProfileValidation {
+  destinationExists: boolean;
+  remoteExists: boolean;
+  errors: string[];
+}
@@ -0,0 +1,41 @@
+import type { DownloadParams, DownloadReport, JobStatus } from "./download";
+
+export type ScheduleDay =
+  | "monday"
+  | "tuesday"
+  | "wednesday"
+  | "thursday"
+  | "friday"
+  | "saturday"
+  | "sunday";
+
+export type ScheduleTrigger =
+  | { type: "daily"; time: string }
+  | { type: "weekly"; days: ScheduleDay[]; time: string }
+  | { type: "cron"; expression: string };
+
+export interface Schedule {
+  id: number;
+  name: string;
+  enabled: boolean;
+  trigger: ScheduleTrigger;
+  config: DownloadParams;
+  profileId: number | null;
+  requireAcPower: boolean;
+  requireUnmetered: boolean;
+  nextRun: string | null;
+  lastRun: string | null;
+  lastJobId: number | null;
+  lastStatus: JobStatus | null;
+  lastError: string | null;
+  waitingReason: string | null;
+}
+
+export interface ScheduledRunResult {
+  scheduleId: number;
+  name: string;
+  jobId: number;
+  status: JobStatus;
+  error: string | null;
+  report: DownloadReport | null;
+}
@@ -0,0 +1,18 @@
+export interface VerifyProgress {
+  checks: number;
+  totalChecks: number;
+  errors: number;
+  elapsedTime: number;
+}
+
+export interface VerifyReport {
+  success: boolean;
+  status: string;
+  hashType: string | null;
+  matched: number;
+  mismatched: string[];
+  missing: string[];
+  extra: string[];
+  errors: string[];
+  redownloadJob: number | null;
+}